@@ -0,0 +1,165 @@
+// Access control
+//
+// Whoever can talk to a session can type into it, so the daemon only serves
+// connections from allowed users (checked with the peer credentials of the
+// socket) and, if the session has a token, only requests that carry it.
+// Rejections are logged.
+//
+// The socket's mode keeps out everyone else before they get that far, unless
+// other users are allowed: then the socket (and the directory the daemon made
+// for it) must let them connect, and the peer credentials do the filtering.
+
+use anyhow::{bail, Context, Result};
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::os::fd::AsFd;
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::recording::utc_datetime;
+
+/// Who may use a daemon's socket
+pub struct Access {
+    /// Users allowed to connect, besides the daemon's own user and root
+    allowed_uids: Vec<u32>,
+    /// Whether `--allow-uid` let in other users
+    shared: bool,
+    /// Secret that every request must carry, if set
+    token: Option<String>,
+    /// Where to log rejections (stderr if not set)
+    log: Option<Mutex<File>>,
+}
+
+impl Access {
+    pub fn new(allow_uids: &[u32], token: Option<String>, log: Option<&Path>) -> Result<Self> {
+        let log = match log {
+            Some(path) => Some(Mutex::new(
+                OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(path)
+                    .with_context(|| format!("Failed to open log file {}", path.display()))?,
+            )),
+            None => None,
+        };
+
+        let mut allowed_uids = vec![rustix::process::getuid().as_raw(), 0];
+        allowed_uids.extend_from_slice(allow_uids);
+
+        Ok(Access {
+            allowed_uids,
+            shared: !allow_uids.is_empty(),
+            token: token.filter(|token| !token.is_empty()),
+            log,
+        })
+    }
+
+    /// Write a timestamped line to the log
+    pub fn log(&self, message: &str) {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+        let line = format!("{} {}\n", utc_datetime(now), message);
+        match &self.log {
+            Some(log) => {
+                let _ = log.lock().unwrap().write_all(line.as_bytes());
+            }
+            None => eprint!("{}", line),
+        }
+    }
+
+    /// Is the process on the other end of `stream` run by an allowed user?
+    pub fn check_peer(&self, stream: &impl AsFd) -> bool {
+        match peer_credentials(stream) {
+            Ok((uid, _)) if self.allowed_uids.contains(&uid) => true,
+            Ok((uid, pid)) => {
+                let pid = pid.map_or(String::new(), |pid| format!(" (pid {})", pid));
+                self.log(&format!("Rejected connection from uid {}{}: user not allowed", uid, pid));
+                false
+            }
+            Err(e) => {
+                self.log(&format!("Rejected connection: can't get peer credentials: {}", e));
+                false
+            }
+        }
+    }
+
+    /// Does a request carry the right token (if one is needed)?
+    pub fn check_token(&self, request: &serde_json::Value, stream: &impl AsFd) -> bool {
+        let Some(token) = &self.token else {
+            return true;
+        };
+        let given = request.get("token").and_then(|v| v.as_str()).unwrap_or_default();
+        if constant_time_eq(given.as_bytes(), token.as_bytes()) {
+            return true;
+        }
+        let peer = match peer_credentials(stream) {
+            Ok((uid, Some(pid))) => format!(" from uid {} (pid {})", uid, pid),
+            Ok((uid, None)) => format!(" from uid {}", uid),
+            Err(_) => String::new(),
+        };
+        let reason = if given.is_empty() { "missing token" } else { "wrong token" };
+        self.log(&format!("Rejected request{}: {}", peer, reason));
+        false
+    }
+
+    /// Bind `path` so that only the users who may connect can reach it. The
+    /// socket is created private and only opened up to everyone (0666) if
+    /// other users are allowed.
+    pub fn bind(&self, path: &str) -> Result<tokio::net::UnixListener> {
+        use nix::sys::stat::{umask, Mode};
+
+        // No window in which the socket has the default mode
+        let previous = umask(Mode::from_bits_truncate(0o177));
+        let listener = tokio::net::UnixListener::bind(path);
+        umask(previous);
+        let listener = listener.with_context(|| format!("Failed to listen on {}", path))?;
+
+        let mode = if self.shared { 0o666 } else { 0o600 };
+        std::fs::set_permissions(path, std::fs::Permissions::from_mode(mode))
+            .with_context(|| format!("Failed to set permissions of {}", path))?;
+        Ok(listener)
+    }
+
+    /// Let allowed users into the directory the daemon made for `socket_path`
+    /// (0711: they can reach the socket but not list the directory)
+    pub fn share_socket_dir(&self, socket_path: &str) -> Result<()> {
+        let Some(dir) = Path::new(socket_path).parent().filter(|_| self.shared) else {
+            return Ok(());
+        };
+        std::fs::set_permissions(dir, std::fs::Permissions::from_mode(0o711))
+            .with_context(|| format!("Failed to set permissions of {}", dir.display()))
+    }
+
+    /// Named sessions live in the private runtime directory, which other
+    /// users can't reach
+    pub fn check_named(&self) -> Result<()> {
+        if self.shared {
+            bail!("--allow-uid doesn't work with --name: other users can't reach the private runtime directory; use --socket in a directory they can reach instead");
+        }
+        Ok(())
+    }
+}
+
+/// Compare secrets without giving away how much of them matched
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+/// User ID (and process ID, where available) of the peer
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn peer_credentials(stream: &impl AsFd) -> nix::Result<(u32, Option<i32>)> {
+    use nix::sys::socket::{getsockopt, sockopt::PeerCredentials};
+
+    let cred = getsockopt(stream, PeerCredentials)?;
+    Ok((cred.uid(), Some(cred.pid())))
+}
+
+/// User ID (and process ID, where available) of the peer
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+fn peer_credentials(stream: &impl AsFd) -> nix::Result<(u32, Option<i32>)> {
+    use nix::sys::socket::{getsockopt, sockopt::LocalPeerCred};
+
+    let cred = getsockopt(stream, LocalPeerCred)?;
+    Ok((cred.uid(), None))
+}
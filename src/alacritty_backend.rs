@@ -4,13 +4,21 @@
 
 use std::sync::{Arc, Mutex};
 use alacritty_terminal::event::{Event, EventListener};
-use alacritty_terminal::term::{Config, Term};
+use alacritty_terminal::term::{Config, Term, TermMode};
 use alacritty_terminal::term::cell::Flags;
 use alacritty_terminal::grid::Dimensions;
-use alacritty_terminal::vte::ansi::{self, Color, NamedColor};
+use alacritty_terminal::vte::ansi::{self, Color, Handler, NamedColor, NamedPrivateMode, PrivateMode};
+use alacritty_terminal::vte::{Params, Parser, Perform};
 use alacritty_terminal::index::{Column, Line};
 
-use crate::terminal::{TerminalEmulator, UnhandledSequence};
+use crate::terminal::{
+    CellColor, CellFlags, KeyboardModes, MouseEncoding, MouseMode, MouseTracking, StyledCell, TerminalEmulator,
+    UnhandledSequence,
+};
+
+/// The kitty keyboard flags sit in TermMode in the protocol's bit order,
+/// starting at DISAMBIGUATE_ESC_CODES
+const KITTY_FLAGS_SHIFT: u32 = TermMode::DISAMBIGUATE_ESC_CODES.bits().trailing_zeros();
 
 /// Display-related flags that affect ANSI output (excludes internal flags like WRAPLINE)
 fn display_flags(flags: Flags) -> Flags {
@@ -60,18 +68,55 @@ impl EventListener for ResponseCapturingListener {
     }
 }
 
+/// Follows X10 mouse mode (9), which alacritty doesn't implement. Like the
+/// other mouse modes, setting it replaces whichever was set before.
+#[derive(Default)]
+struct X10Mouse {
+    on: bool,
+}
+
+impl Perform for X10Mouse {
+    fn csi_dispatch(&mut self, params: &Params, intermediates: &[u8], _ignore: bool, action: char) {
+        if intermediates != b"?" || !matches!(action, 'h' | 'l') {
+            return;
+        }
+        for mode in params.iter() {
+            match mode {
+                [9] => self.on = action == 'h',
+                [1000 | 1002 | 1003] if action == 'h' => self.on = false,
+                _ => {}
+            }
+        }
+    }
+
+    fn esc_dispatch(&mut self, intermediates: &[u8], _ignore: bool, byte: u8) {
+        // RIS resets every mode
+        if intermediates.is_empty() && byte == b'c' {
+            self.on = false;
+        }
+    }
+}
+
 /// Alacritty-based terminal emulator implementation
 pub struct AlacrittyTerminal {
     term: Term<ResponseCapturingListener>,
     parser: ansi::Processor,
+    x10_parser: Parser,
+    x10_mouse: X10Mouse,
     responses: Arc<Mutex<Vec<Vec<u8>>>>,
     rows: usize,
     cols: usize,
 }
 
 impl AlacrittyTerminal {
-    pub fn new(rows: usize, cols: usize) -> Self {
-        let config = Config::default();
+    /// Create a terminal keeping up to `scrollback` lines of history
+    pub fn new(rows: usize, cols: usize, scrollback: usize) -> Self {
+        let config = Config {
+            scrolling_history: scrollback,
+            // Let applications turn on the kitty keyboard protocol
+            kitty_keyboard: true,
+            ..Config::default()
+        };
         let dimensions = TermDimensions {
             columns: cols,
             screen_lines: rows,
@@ -84,11 +129,120 @@ impl AlacrittyTerminal {
         AlacrittyTerminal {
             term,
             parser,
+            x10_parser: Parser::new(),
+            x10_mouse: X10Mouse::default(),
             responses,
             rows,
             cols,
         }
     }
+
+    /// Plain text of a grid line (negative lines are history), trailing whitespace trimmed
+    fn line_text(&self, line: Line) -> String {
+        let grid = self.term.grid();
+        let row = &grid[line];
+        let text: String = (0..grid.columns())
+            .filter_map(|col| {
+                let cell = &row[Column(col)];
+                // Skip wide char spacer cells (placeholder after wide char)
+                if cell.flags.contains(Flags::WIDE_CHAR_SPACER) {
+                    None
+                } else {
+                    Some(cell.c)
+                }
+            })
+            .collect();
+        text.trim_end().to_string()
+    }
+
+    /// Text of a grid line with ANSI color codes embedded
+    fn line_ansi(&self, line: Line) -> String {
+        let grid = self.term.grid();
+        let row = &grid[line];
+
+        // Default colors for comparison
+        let default_fg = Color::Named(NamedColor::Foreground);
+        let default_bg = Color::Named(NamedColor::Background);
+        let empty_flags = Flags::empty();
+
+        let mut line_content = String::new();
+        let mut current_fg = default_fg;
+        let mut current_bg = default_bg;
+        let mut current_flags = empty_flags;
+
+        for col in 0..grid.columns() {
+            let cell = &row[Column(col)];
+
+            // Skip wide char spacer cells
+            if cell.flags.contains(Flags::WIDE_CHAR_SPACER) {
+                continue;
+            }
+
+            // Check if we need to emit SGR codes (only compare display-related flags)
+            let cell_display_flags = display_flags(cell.flags);
+            let need_sgr = cell.fg != current_fg
+                || cell.bg != current_bg
+                || cell_display_flags != current_flags;
+
+            if need_sgr {
+                let sgr = build_sgr_sequence(&cell.fg, &cell.bg, cell.flags);
+                if !sgr.is_empty() {
+                    line_content.push_str(&sgr);
+                }
+                current_fg = cell.fg;
+                current_bg = cell.bg;
+                current_flags = cell_display_flags;
+            }
+
+            line_content.push(cell.c);
+        }
+
+        // Reset at end of line if we changed any attributes
+        if current_fg != default_fg || current_bg != default_bg || current_flags != empty_flags {
+            line_content.push_str("\x1b[0m");
+        }
+
+        // Trim trailing spaces but preserve ANSI codes
+        trim_end_preserve_ansi(&line_content).to_string()
+    }
+
+    /// Join the last `lines` lines of history using `render`
+    fn history_with(&self, lines: usize, render: impl Fn(&Self, Line) -> String) -> String {
+        let count = lines.min(self.history_size()) as i32;
+        let mut result = String::new();
+        for line in (1..=count).rev() {
+            result.push_str(&render(self, Line(-line)));
+            result.push('\n');
+        }
+        result
+    }
+}
+
+/// Map an alacritty color to a cell color
+fn cell_color(color: &Color) -> CellColor {
+    match color {
+        Color::Named(named) => match *named as usize {
+            index @ 0..=15 => CellColor::Named(index as u8),
+            // Dim variants follow the bright ones in NamedColor
+            index @ 259..=266 => CellColor::Named((index - 259) as u8),
+            _ => CellColor::Default,
+        },
+        Color::Indexed(index) => CellColor::Indexed(*index),
+        Color::Spec(rgb) => CellColor::Rgb(rgb.r, rgb.g, rgb.b),
+    }
+}
+
+/// Map alacritty cell flags to cell attributes
+fn cell_flags(flags: Flags) -> CellFlags {
+    CellFlags {
+        bold: flags.contains(Flags::BOLD),
+        dim: flags.contains(Flags::DIM),
+        italic: flags.contains(Flags::ITALIC),
+        underline: flags.intersects(Flags::ALL_UNDERLINES),
+        inverse: flags.contains(Flags::INVERSE),
+        hidden: flags.contains(Flags::HIDDEN),
+        strikethrough: flags.contains(Flags::STRIKEOUT),
+    }
 }
 
 /// Build ANSI SGR escape sequence from color and flags
@@ -235,87 +389,64 @@ fn trim_end_preserve_ansi(s: &str) -> &str {
 impl TerminalEmulator for AlacrittyTerminal {
     fn process_bytes(&mut self, bytes: &[u8]) {
         self.parser.advance(&mut self.term, bytes);
+
+        self.x10_parser.advance(&mut self.x10_mouse, bytes);
+        if self.x10_mouse.on {
+            // X10 mode was set after the others
+            for mode in [NamedPrivateMode::ReportMouseClicks, NamedPrivateMode::ReportCellMouseMotion, NamedPrivateMode::ReportAllMouseMotion] {
+                self.term.unset_private_mode(PrivateMode::Named(mode));
+            }
+        }
     }
 
     fn get_screen_content(&self) -> String {
-        let grid = self.term.grid();
         let mut result = String::new();
-
-        for line_idx in 0..grid.screen_lines() {
-            let line = &grid[Line(line_idx as i32)];
-            let line_str: String = (0..grid.columns())
-                .filter_map(|col| {
-                    let cell = &line[Column(col)];
-                    // Skip wide char spacer cells (placeholder after wide char)
-                    if cell.flags.contains(Flags::WIDE_CHAR_SPACER) {
-                        None
-                    } else {
-                        Some(cell.c)
-                    }
-                })
-                .collect();
-            result.push_str(line_str.trim_end());
+        for line_idx in 0..self.term.grid().screen_lines() {
+            result.push_str(&self.line_text(Line(line_idx as i32)));
             result.push('\n');
         }
-
         result
     }
 
     fn get_screen_content_ansi(&self) -> String {
-        let grid = self.term.grid();
         let mut result = String::new();
+        for line_idx in 0..self.term.grid().screen_lines() {
+            result.push_str(&self.line_ansi(Line(line_idx as i32)));
+            result.push('\n');
+        }
+        result
+    }
 
-        // Default colors for comparison
-        let default_fg = Color::Named(NamedColor::Foreground);
-        let default_bg = Color::Named(NamedColor::Background);
-        let empty_flags = Flags::empty();
-
-        for line_idx in 0..grid.screen_lines() {
-            let line = &grid[Line(line_idx as i32)];
-            let mut line_content = String::new();
-            let mut current_fg = default_fg;
-            let mut current_bg = default_bg;
-            let mut current_flags = empty_flags;
-
-            for col in 0..grid.columns() {
-                let cell = &line[Column(col)];
-
-                // Skip wide char spacer cells
-                if cell.flags.contains(Flags::WIDE_CHAR_SPACER) {
-                    continue;
-                }
-
-                // Check if we need to emit SGR codes (only compare display-related flags)
-                let cell_display_flags = display_flags(cell.flags);
-                let need_sgr = cell.fg != current_fg
-                    || cell.bg != current_bg
-                    || cell_display_flags != current_flags;
-
-                if need_sgr {
-                    let sgr = build_sgr_sequence(&cell.fg, &cell.bg, cell.flags);
-                    if !sgr.is_empty() {
-                        line_content.push_str(&sgr);
-                    }
-                    current_fg = cell.fg;
-                    current_bg = cell.bg;
-                    current_flags = cell_display_flags;
-                }
-
-                line_content.push(cell.c);
-            }
+    fn get_cells(&self) -> Vec<Vec<StyledCell>> {
+        let grid = self.term.grid();
+        (0..grid.screen_lines())
+            .map(|line_idx| {
+                let row = &grid[Line(line_idx as i32)];
+                (0..grid.columns())
+                    .map(|col| &row[Column(col)])
+                    .filter(|cell| !cell.flags.contains(Flags::WIDE_CHAR_SPACER))
+                    .map(|cell| StyledCell {
+                        c: cell.c,
+                        fg: cell_color(&cell.fg),
+                        bg: cell_color(&cell.bg),
+                        flags: cell_flags(cell.flags),
+                        wide: cell.flags.contains(Flags::WIDE_CHAR),
+                    })
+                    .collect()
+            })
+            .collect()
+    }
 
-            // Reset at end of line if we changed any attributes
-            if current_fg != default_fg || current_bg != default_bg || current_flags != empty_flags {
-                line_content.push_str("\x1b[0m");
-            }
+    fn history_size(&self) -> usize {
+        self.term.grid().history_size()
+    }
 
-            // Trim trailing spaces but preserve ANSI codes
-            let trimmed = trim_end_preserve_ansi(&line_content);
-            result.push_str(trimmed);
-            result.push('\n');
-        }
+    fn get_history(&self, lines: usize) -> String {
+        self.history_with(lines, Self::line_text)
+    }
 
-        result
+    fn get_history_ansi(&self, lines: usize) -> String {
+        self.history_with(lines, Self::line_ansi)
     }
 
     fn cursor_position(&self) -> (usize, usize) {
@@ -357,4 +488,40 @@ impl TerminalEmulator for AlacrittyTerminal {
     fn get_debug_dropped(&self) -> usize {
         0
     }
+
+    fn keyboard_modes(&self) -> KeyboardModes {
+        let mode = self.term.mode();
+        KeyboardModes {
+            app_cursor: mode.contains(TermMode::APP_CURSOR),
+            app_keypad: mode.contains(TermMode::APP_KEYPAD),
+            kitty: (mode.intersection(TermMode::KITTY_KEYBOARD_PROTOCOL).bits() >> KITTY_FLAGS_SHIFT) as u8,
+        }
+    }
+
+    fn bracketed_paste(&self) -> bool {
+        self.term.mode().contains(TermMode::BRACKETED_PASTE)
+    }
+
+    fn mouse_mode(&self) -> MouseMode {
+        let mode = self.term.mode();
+        let tracking = if mode.contains(TermMode::MOUSE_MOTION) {
+            MouseTracking::Motion
+        } else if mode.contains(TermMode::MOUSE_DRAG) {
+            MouseTracking::Drag
+        } else if mode.contains(TermMode::MOUSE_REPORT_CLICK) {
+            MouseTracking::Click
+        } else if self.x10_mouse.on {
+            MouseTracking::Press
+        } else {
+            MouseTracking::Off
+        };
+        let encoding = if mode.contains(TermMode::SGR_MOUSE) {
+            MouseEncoding::Sgr
+        } else if mode.contains(TermMode::UTF8_MOUSE) {
+            MouseEncoding::Utf8
+        } else {
+            MouseEncoding::X10
+        };
+        MouseMode { tracking, encoding }
+    }
 }
@@ -0,0 +1,287 @@
+// Async client
+//
+// The `Client` API for tokio programs. Requests are tasks waiting on their
+// sockets instead of threads blocked on them, so one runtime can keep WAITs
+// and EXPECTs outstanding on hundreds of sessions at once.
+
+use anyhow::{bail, Context, Result};
+use serde::de::DeserializeOwned;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::UnixStream;
+
+use crate::client::{
+    expect_request, into_data, mouse_request, output_request, timeout_error, wait_request, Client, DebugInfo,
+    ExpectResult, ExpectTarget, Image, Mouse, OutputOptions, Screen, Screenshot, Status, WaitFor, WaitResult,
+    TIMEOUT_GRACE,
+};
+use crate::protocol::Response;
+
+/// Run `operation`, failing with a `TimeoutError` about `what` if it takes
+/// longer than `timeout`
+async fn within<T>(timeout: Option<Duration>, what: &str, operation: impl Future<Output = std::io::Result<T>>) -> Result<T> {
+    match timeout {
+        Some(timeout) => match tokio::time::timeout(timeout, operation).await {
+            Ok(result) => Ok(result?),
+            Err(_) => Err(timeout_error(what, timeout)),
+        },
+        None => Ok(operation.await?),
+    }
+}
+
+/// Connect to the daemon. A Unix socket connect fails with WouldBlock while
+/// the listen backlog is full, so keep trying until it goes through.
+async fn connect(socket_path: &Path, timeout: Option<Duration>) -> Result<UnixStream> {
+    within(timeout, "connecting to daemon", async {
+        loop {
+            match UnixStream::connect(socket_path).await {
+                Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => {
+                    tokio::time::sleep(Duration::from_millis(10)).await;
+                }
+                result => return result,
+            }
+        }
+    }).await.context("Failed to connect to daemon socket")
+}
+
+/// Connection settings for one daemon socket, for use from async code
+#[derive(Debug, Clone)]
+pub struct AsyncClient {
+    client: Client,
+}
+
+impl From<Client> for AsyncClient {
+    fn from(client: Client) -> Self {
+        AsyncClient { client }
+    }
+}
+
+impl AsyncClient {
+    /// A client for the daemon listening on `socket`
+    pub fn new(socket: impl Into<PathBuf>) -> Self {
+        Client::new(socket).into()
+    }
+
+    /// Address `session`, on the socket of a server
+    pub fn session(self, session: impl Into<String>) -> Self {
+        self.client.session(session).into()
+    }
+
+    /// Present `token` to daemons that require one
+    pub fn token(self, token: impl Into<String>) -> Self {
+        self.client.token(token).into()
+    }
+
+    /// Give up on each request after `timeout` (`None`, the default, waits
+    /// forever). `wait` and `expect` also use it as the daemon-side deadline.
+    pub fn timeout(self, timeout: impl Into<Option<Duration>>) -> Self {
+        self.client.timeout(timeout).into()
+    }
+
+    pub fn socket(&self) -> &Path {
+        self.client.socket()
+    }
+
+    /// The same client, for requests that aren't about one session
+    pub fn without_session(&self) -> Self {
+        self.client.without_session().into()
+    }
+
+    /// A blocking client with the same settings
+    pub fn client(&self) -> &Client {
+        &self.client
+    }
+
+    /// Send one request and read its response, whatever its status.
+    /// With a timeout, connecting, sending and waiting for the response each
+    /// give up after that long and fail with a `TimeoutError`.
+    pub async fn send(&self, request: serde_json::Value) -> Result<Response> {
+        self.send_with_timeout(request, self.client.timeout).await
+    }
+
+    async fn send_with_timeout(&self, request: serde_json::Value, timeout: Option<Duration>) -> Result<Response> {
+        let mut stream = connect(self.socket(), timeout).await?;
+
+        let json = serde_json::to_string(&self.client.apply(request))?;
+        within(timeout, "sending request", async {
+            stream.write_all(format!("{}\n", json).as_bytes()).await?;
+            stream.flush().await
+        }).await?;
+
+        let mut reader = BufReader::new(stream);
+        let mut line = String::new();
+        within(timeout, "waiting for daemon response", reader.read_line(&mut line)).await?;
+
+        let response: Response = serde_json::from_str(&line)?;
+        Ok(response)
+    }
+
+    /// Send one request and return the data of the response, failing
+    /// with a `DaemonError` if the daemon reports an error
+    pub async fn request(&self, request: serde_json::Value) -> Result<serde_json::Value> {
+        into_data(self.send(request).await?)
+    }
+
+    async fn request_as<T: DeserializeOwned>(&self, request: serde_json::Value, timeout: Option<Duration>) -> Result<T> {
+        let data = into_data(self.send_with_timeout(request, timeout).await?)?;
+        serde_json::from_value(data).context("Unexpected response from daemon")
+    }
+
+    /// Type `data` into the application
+    pub async fn input(&self, data: &str) -> Result<()> {
+        self.request(serde_json::json!({"type": "INPUT", "data": data})).await.map(drop)
+    }
+
+    /// Type `data` without it being logged or recorded
+    pub async fn input_secret(&self, data: &str) -> Result<()> {
+        self.request(serde_json::json!({"type": "INPUT", "data": data, "secret": true})).await.map(drop)
+    }
+
+    /// Press the keys named in `keys` (e.g. "C-c Up Enter"), encoded for the
+    /// keyboard modes the application has turned on
+    pub async fn input_keys(&self, keys: &str) -> Result<()> {
+        self.request(serde_json::json!({"type": "INPUT", "keys": keys})).await.map(drop)
+    }
+
+    /// Paste `data` as one block, bracketed if the application has turned on
+    /// bracketed paste. Returns whether it was; if not, `data` went in as typed.
+    pub async fn paste(&self, data: &str) -> Result<bool> {
+        let data = self.request(serde_json::json!({"type": "INPUT", "data": data, "paste": true})).await?;
+        Ok(data["bracketed"].as_bool().unwrap_or(false))
+    }
+
+    /// The screen as plain text
+    pub async fn output(&self) -> Result<Screen> {
+        self.output_with(&OutputOptions::default()).await
+    }
+
+    pub async fn output_with(&self, options: &OutputOptions) -> Result<Screen> {
+        self.request_as(output_request(options)?, self.client.timeout).await
+    }
+
+    /// The screen rendered as a PNG image
+    pub async fn screenshot(&self) -> Result<Screenshot> {
+        let image: Image = self.request_as(serde_json::json!({"type": "OUTPUT", "format": "png"}), self.client.timeout).await?;
+        image.decode()
+    }
+
+    /// Whether the application is still running. With `activity`, also
+    /// report whether there was output since the last check (and reset that).
+    pub async fn status(&self, activity: bool) -> Result<Status> {
+        self.request_as(serde_json::json!({"type": "STATUS", "activity": activity}), self.client.timeout).await
+    }
+
+    /// Wait until `until` happens, or the client's timeout passes
+    pub async fn wait(&self, until: WaitFor) -> Result<WaitResult> {
+        let timeout = self.client.timeout;
+        // Let the daemon report its own timeout before giving up on it
+        let mut result: WaitResult = self.request_as(wait_request(until, timeout), timeout.map(|t| t + TIMEOUT_GRACE)).await?;
+        result.exited |= result.exit_code.is_some();
+        Ok(result)
+    }
+
+    /// Wait until one of the regular expressions in `patterns` matches
+    /// `target`, the application exits or the client's timeout passes
+    pub async fn expect<S: AsRef<str>>(&self, patterns: &[S], target: ExpectTarget) -> Result<ExpectResult> {
+        let timeout = self.client.timeout;
+        // Let the daemon report its own timeout before giving up on it
+        self.request_as(expect_request(patterns, target, None, timeout), timeout.map(|t| t + TIMEOUT_GRACE)).await
+    }
+
+    /// Like `expect` on the output target, but only matching output from
+    /// `offset` on. Pass the `end_offset` of the last match to continue after it.
+    pub async fn expect_output_from<S: AsRef<str>>(&self, patterns: &[S], offset: u64) -> Result<ExpectResult> {
+        let timeout = self.client.timeout;
+        let request = expect_request(patterns, ExpectTarget::Output, Some(offset), timeout);
+        self.request_as(request, timeout.map(|t| t + TIMEOUT_GRACE)).await
+    }
+
+    /// Click, drag or scroll with the mouse. Fails with a `DaemonError` if
+    /// the application hasn't turned on mouse reporting.
+    pub async fn mouse(&self, mouse: Mouse) -> Result<()> {
+        self.request(mouse_request(mouse)).await.map(drop)
+    }
+
+    /// Send `signal` (a name like SIGINT or a number) to the application
+    pub async fn kill(&self, signal: &str) -> Result<()> {
+        self.request(serde_json::json!({"type": "KILL", "signal": signal})).await.map(drop)
+    }
+
+    pub async fn resize(&self, cols: u16, rows: u16) -> Result<()> {
+        self.request(serde_json::json!({"type": "RESIZE", "cols": cols, "rows": rows})).await.map(drop)
+    }
+
+    /// Escape sequences the emulator didn't handle, and the PTY's termios
+    /// settings. With `clear`, forget the sequences reported so far.
+    pub async fn debug(&self, clear: bool) -> Result<DebugInfo> {
+        self.request_as(serde_json::json!({"type": "DEBUG", "clear": clear}), self.client.timeout).await
+    }
+
+    /// Stop the daemon, terminating the application
+    pub async fn stop(&self) -> Result<()> {
+        self.request(serde_json::json!({"type": "STOP"})).await.map(drop)
+    }
+
+    /// Subscribe to the session's events. The client's timeout is a deadline
+    /// for the whole subscription.
+    pub async fn subscribe(&self) -> Result<AsyncEvents> {
+        let timeout = self.client.timeout;
+        let deadline = timeout.map(|t| Instant::now() + t);
+        let mut stream = connect(self.socket(), timeout).await?;
+        let request = self.client.apply(serde_json::json!({"type": "SUBSCRIBE"}));
+        stream.write_all(format!("{}\n", request).as_bytes()).await?;
+        stream.flush().await?;
+
+        let mut events = AsyncEvents {
+            reader: BufReader::new(stream),
+            deadline,
+            timeout: timeout.unwrap_or_default(),
+        };
+        // The first line is the response to SUBSCRIBE, the rest are events
+        match events.read_line().await? {
+            Some(line) => into_data(serde_json::from_str(&line)?)?,
+            None => bail!("Daemon closed the connection"),
+        };
+        Ok(events)
+    }
+}
+
+/// Events from a subscription, until the application exits or the daemon stops
+pub struct AsyncEvents {
+    reader: BufReader<UnixStream>,
+    deadline: Option<Instant>,
+    timeout: Duration,
+}
+
+impl AsyncEvents {
+    /// The next line from the daemon, or None at the end of the stream
+    async fn read_line(&mut self) -> Result<Option<String>> {
+        let remaining = self.deadline.map(|deadline| deadline.saturating_duration_since(Instant::now()));
+        if remaining.is_some_and(|remaining| remaining.is_zero()) {
+            return Err(timeout_error("waiting for events", self.timeout));
+        }
+
+        let mut line = String::new();
+        let read = match remaining {
+            Some(remaining) => tokio::time::timeout(remaining, self.reader.read_line(&mut line))
+                .await
+                .map_err(|_| timeout_error("waiting for events", self.timeout))?,
+            None => self.reader.read_line(&mut line).await,
+        };
+        match read? {
+            0 => Ok(None),
+            _ => Ok(Some(line)),
+        }
+    }
+
+    /// The next event, or None once the stream has ended
+    pub async fn next(&mut self) -> Option<Result<serde_json::Value>> {
+        match self.read_line().await {
+            Ok(Some(line)) => Some(serde_json::from_str(&line).map_err(Into::into)),
+            Ok(None) => None,
+            Err(e) => Some(Err(e)),
+        }
+    }
+}
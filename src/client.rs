@@ -0,0 +1,781 @@
+// Client
+//
+// A typed API over the socket protocol, for programs that drive sessions
+// without shelling out to the CLI and scraping what it prints. A `Client`
+// talks to the socket of a running session (or of a server, naming one of
+// its sessions); a `Session` starts a daemon with the interminai binary and
+// stops it again when dropped.
+
+use anyhow::{bail, Context, Result};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::ffi::OsStr;
+use std::io::{BufRead, BufReader, Write};
+use std::ops::Deref;
+use std::os::unix::net::UnixStream;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::protocol::Response;
+
+/// Extra time given to the daemon to report its own timeout on blocking
+/// requests before the client gives up on the connection
+pub const TIMEOUT_GRACE: Duration = Duration::from_secs(1);
+
+/// A client-side deadline expired
+#[derive(Debug)]
+pub struct TimeoutError(String);
+
+impl std::fmt::Display for TimeoutError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl std::error::Error for TimeoutError {}
+
+pub fn timeout_error(what: &str, timeout: Duration) -> anyhow::Error {
+    TimeoutError(format!("Timed out after {} ms {}", timeout.as_millis(), what)).into()
+}
+
+/// The daemon answered a request with an error
+#[derive(Debug)]
+pub struct DaemonError(pub String);
+
+impl std::fmt::Display for DaemonError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl std::error::Error for DaemonError {}
+
+/// Connect to the daemon, giving up once `timeout` has passed.
+/// A Unix socket connect only blocks while the listen backlog is full,
+/// so retry a non-blocking connect until the deadline.
+fn connect_with_timeout(socket_path: &Path, timeout: Duration) -> Result<UnixStream> {
+    use rustix::net::{connect, socket_with, AddressFamily, SocketAddrUnix, SocketFlags, SocketType};
+
+    let deadline = Instant::now() + timeout;
+    let addr = SocketAddrUnix::new(socket_path)?;
+    let fd = socket_with(AddressFamily::UNIX, SocketType::STREAM, SocketFlags::NONBLOCK | SocketFlags::CLOEXEC, None)?;
+    loop {
+        match connect(&fd, &addr) {
+            Ok(()) => break,
+            Err(rustix::io::Errno::AGAIN) if Instant::now() < deadline => {
+                thread::sleep(Duration::from_millis(10));
+            }
+            Err(rustix::io::Errno::AGAIN) => return Err(timeout_error("connecting to daemon", timeout)),
+            Err(e) => return Err(std::io::Error::from(e).into()),
+        }
+    }
+    rustix::io::ioctl_fionbio(&fd, false)?;
+    Ok(UnixStream::from(fd))
+}
+
+fn connect(socket_path: &Path, timeout: Option<Duration>) -> Result<UnixStream> {
+    match timeout {
+        Some(timeout) => connect_with_timeout(socket_path, timeout),
+        None => UnixStream::connect(socket_path).map_err(Into::into),
+    }.context("Failed to connect to daemon socket")
+}
+
+/// Did a socket operation fail because its read/write timeout expired?
+fn is_timeout(e: &std::io::Error) -> bool {
+    matches!(e.kind(), std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut)
+}
+
+/// The data of an ok response, or its error as a `DaemonError`
+pub(crate) fn into_data(response: Response) -> Result<serde_json::Value> {
+    if !response.is_ok() {
+        return Err(DaemonError(response.error.unwrap_or_default()).into());
+    }
+    Ok(response.data.unwrap_or_default())
+}
+
+/// Connection settings for one daemon socket
+#[derive(Debug, Clone)]
+pub struct Client {
+    socket: PathBuf,
+    /// Session to address, for sockets that belong to a server
+    session: Option<String>,
+    /// Token the daemon may require
+    token: Option<String>,
+    /// Deadline for each request
+    pub(crate) timeout: Option<Duration>,
+}
+
+impl Client {
+    /// A client for the daemon listening on `socket`
+    pub fn new(socket: impl Into<PathBuf>) -> Self {
+        Client {
+            socket: socket.into(),
+            session: None,
+            token: None,
+            timeout: None,
+        }
+    }
+
+    /// Address `session`, on the socket of a server
+    pub fn session(mut self, session: impl Into<String>) -> Self {
+        self.session = Some(session.into());
+        self
+    }
+
+    /// Present `token` to daemons that require one
+    pub fn token(mut self, token: impl Into<String>) -> Self {
+        self.token = Some(token.into());
+        self
+    }
+
+    /// Give up on each request after `timeout` (`None`, the default, waits
+    /// forever). `wait` and `expect` also use it as the daemon-side deadline.
+    pub fn timeout(mut self, timeout: impl Into<Option<Duration>>) -> Self {
+        self.timeout = timeout.into();
+        self
+    }
+
+    pub fn socket(&self) -> &Path {
+        &self.socket
+    }
+
+    /// The same client, for requests that aren't about one session
+    pub fn without_session(&self) -> Self {
+        Client { session: None, ..self.clone() }
+    }
+
+    pub(crate) fn apply(&self, mut request: serde_json::Value) -> serde_json::Value {
+        if let Some(session) = &self.session {
+            request["session"] = serde_json::json!(session);
+        }
+        if let Some(token) = &self.token {
+            request["token"] = serde_json::json!(token);
+        }
+        request
+    }
+
+    /// Send one request and read its response, whatever its status.
+    /// With a timeout, connecting, sending and waiting for the response each
+    /// give up after that long and fail with a `TimeoutError`.
+    pub fn send(&self, request: serde_json::Value) -> Result<Response> {
+        self.send_with_timeout(request, self.timeout)
+    }
+
+    fn send_with_timeout(&self, request: serde_json::Value, timeout: Option<Duration>) -> Result<Response> {
+        let mut stream = connect(&self.socket, timeout)?;
+        stream.set_read_timeout(timeout)?;
+        stream.set_write_timeout(timeout)?;
+
+        let json = serde_json::to_string(&self.apply(request))?;
+        let sent = stream.write_all(json.as_bytes())
+            .and_then(|_| stream.write_all(b"\n"))
+            .and_then(|_| stream.flush());
+        if let Err(e) = sent {
+            if let (true, Some(timeout)) = (is_timeout(&e), timeout) {
+                return Err(timeout_error("sending request", timeout));
+            }
+            return Err(e.into());
+        }
+
+        let mut reader = BufReader::new(stream);
+        let mut line = String::new();
+        if let Err(e) = reader.read_line(&mut line) {
+            if let (true, Some(timeout)) = (is_timeout(&e), timeout) {
+                return Err(timeout_error("waiting for daemon response", timeout));
+            }
+            return Err(e.into());
+        }
+
+        let response: Response = serde_json::from_str(&line)?;
+        Ok(response)
+    }
+
+    /// Send one request and return the data of the response, failing
+    /// with a `DaemonError` if the daemon reports an error
+    pub fn request(&self, request: serde_json::Value) -> Result<serde_json::Value> {
+        into_data(self.send(request)?)
+    }
+
+    fn request_as<T: DeserializeOwned>(&self, request: serde_json::Value, timeout: Option<Duration>) -> Result<T> {
+        let data = into_data(self.send_with_timeout(request, timeout)?)?;
+        serde_json::from_value(data).context("Unexpected response from daemon")
+    }
+
+    /// Type `data` into the application
+    pub fn input(&self, data: &str) -> Result<()> {
+        self.request(serde_json::json!({"type": "INPUT", "data": data})).map(drop)
+    }
+
+    /// Type `data` without it being logged or recorded
+    pub fn input_secret(&self, data: &str) -> Result<()> {
+        self.request(serde_json::json!({"type": "INPUT", "data": data, "secret": true})).map(drop)
+    }
+
+    /// Press the keys named in `keys` (e.g. "C-c Up Enter"), encoded for the
+    /// keyboard modes the application has turned on
+    pub fn input_keys(&self, keys: &str) -> Result<()> {
+        self.request(serde_json::json!({"type": "INPUT", "keys": keys})).map(drop)
+    }
+
+    /// Paste `data` as one block, bracketed if the application has turned on
+    /// bracketed paste. Returns whether it was; if not, `data` went in as typed.
+    pub fn paste(&self, data: &str) -> Result<bool> {
+        let data = self.request(serde_json::json!({"type": "INPUT", "data": data, "paste": true}))?;
+        Ok(data["bracketed"].as_bool().unwrap_or(false))
+    }
+
+    /// The screen as plain text
+    pub fn output(&self) -> Result<Screen> {
+        self.output_with(&OutputOptions::default())
+    }
+
+    pub fn output_with(&self, options: &OutputOptions) -> Result<Screen> {
+        self.request_as(output_request(options)?, self.timeout)
+    }
+
+    /// The screen rendered as a PNG image
+    pub fn screenshot(&self) -> Result<Screenshot> {
+        let image: Image = self.request_as(serde_json::json!({"type": "OUTPUT", "format": "png"}), self.timeout)?;
+        image.decode()
+    }
+
+    /// Whether the application is still running. With `activity`, also
+    /// report whether there was output since the last check (and reset that).
+    pub fn status(&self, activity: bool) -> Result<Status> {
+        self.request_as(serde_json::json!({"type": "STATUS", "activity": activity}), self.timeout)
+    }
+
+    /// Block until `until` happens, or the client's timeout passes
+    pub fn wait(&self, until: WaitFor) -> Result<WaitResult> {
+        // Let the daemon report its own timeout before giving up on it
+        let mut result: WaitResult = self.request_as(wait_request(until, self.timeout), self.timeout.map(|t| t + TIMEOUT_GRACE))?;
+        result.exited |= result.exit_code.is_some();
+        Ok(result)
+    }
+
+    /// Block until one of the regular expressions in `patterns` matches
+    /// `target`, the application exits or the client's timeout passes
+    pub fn expect<S: AsRef<str>>(&self, patterns: &[S], target: ExpectTarget) -> Result<ExpectResult> {
+        // Let the daemon report its own timeout before giving up on it
+        self.request_as(expect_request(patterns, target, None, self.timeout), self.timeout.map(|t| t + TIMEOUT_GRACE))
+    }
+
+    /// Like `expect` on the output target, but only matching output from
+    /// `offset` on. Pass the `end_offset` of the last match to continue after it.
+    pub fn expect_output_from<S: AsRef<str>>(&self, patterns: &[S], offset: u64) -> Result<ExpectResult> {
+        let request = expect_request(patterns, ExpectTarget::Output, Some(offset), self.timeout);
+        self.request_as(request, self.timeout.map(|t| t + TIMEOUT_GRACE))
+    }
+
+    /// Click, drag or scroll with the mouse. Fails with a `DaemonError` if
+    /// the application hasn't turned on mouse reporting.
+    pub fn mouse(&self, mouse: Mouse) -> Result<()> {
+        self.request(mouse_request(mouse)).map(drop)
+    }
+
+    /// Send `signal` (a name like SIGINT or a number) to the application
+    pub fn kill(&self, signal: &str) -> Result<()> {
+        self.request(serde_json::json!({"type": "KILL", "signal": signal})).map(drop)
+    }
+
+    pub fn resize(&self, cols: u16, rows: u16) -> Result<()> {
+        self.request(serde_json::json!({"type": "RESIZE", "cols": cols, "rows": rows})).map(drop)
+    }
+
+    /// Escape sequences the emulator didn't handle, and the PTY's termios
+    /// settings. With `clear`, forget the sequences reported so far.
+    pub fn debug(&self, clear: bool) -> Result<DebugInfo> {
+        self.request_as(serde_json::json!({"type": "DEBUG", "clear": clear}), self.timeout)
+    }
+
+    /// Stop the daemon, terminating the application
+    pub fn stop(&self) -> Result<()> {
+        self.request(serde_json::json!({"type": "STOP"})).map(drop)
+    }
+
+    /// Subscribe to the session's events. The client's timeout is a deadline
+    /// for the whole subscription.
+    pub fn subscribe(&self) -> Result<Events> {
+        let deadline = self.timeout.map(|t| Instant::now() + t);
+        let mut stream = connect(&self.socket, self.timeout)?;
+        let request = self.apply(serde_json::json!({"type": "SUBSCRIBE"}));
+        stream.write_all(format!("{}\n", request).as_bytes())?;
+        stream.flush()?;
+
+        let mut events = Events {
+            reader: BufReader::new(stream),
+            deadline,
+            timeout: self.timeout.unwrap_or_default(),
+        };
+        // The first line is the response to SUBSCRIBE, the rest are events
+        match events.read_line()? {
+            Some(line) => into_data(serde_json::from_str(&line)?)?,
+            None => bail!("Daemon closed the connection"),
+        };
+        Ok(events)
+    }
+}
+
+// Requests shared with the async client
+
+pub(crate) fn output_request(options: &OutputOptions) -> Result<serde_json::Value> {
+    let format = options.format.as_deref().unwrap_or("ascii");
+    if format == "png" {
+        bail!("Use screenshot() for PNG images");
+    }
+    let mut request = serde_json::json!({"type": "OUTPUT", "format": format});
+    if let Some(generation) = options.since {
+        request["since"] = serde_json::json!(generation);
+    }
+    if let Some(lines) = options.scrollback {
+        request["scrollback"] = serde_json::json!(lines);
+    }
+    Ok(request)
+}
+
+pub(crate) fn wait_request(until: WaitFor, timeout: Option<Duration>) -> serde_json::Value {
+    let mut request = serde_json::json!({
+        "type": "WAIT",
+        "activity": matches!(until, WaitFor::Activity)
+    });
+    if let WaitFor::Settle(settle) = until {
+        request["settle_ms"] = serde_json::json!(settle.as_millis() as u64);
+    }
+    if let Some(timeout) = timeout {
+        request["timeout_ms"] = serde_json::json!(timeout.as_millis() as u64);
+    }
+    request
+}
+
+pub(crate) fn expect_request<S: AsRef<str>>(patterns: &[S], target: ExpectTarget, offset: Option<u64>, timeout: Option<Duration>) -> serde_json::Value {
+    let patterns: Vec<&str> = patterns.iter().map(|p| p.as_ref()).collect();
+    let mut request = serde_json::json!({
+        "type": "EXPECT",
+        "patterns": patterns,
+        "target": target.as_str()
+    });
+    if let Some(offset) = offset {
+        request["offset"] = serde_json::json!(offset);
+    }
+    if let Some(timeout) = timeout {
+        request["timeout_ms"] = serde_json::json!(timeout.as_millis() as u64);
+    }
+    request
+}
+
+pub(crate) fn mouse_request(mouse: Mouse) -> serde_json::Value {
+    let position = |(row, col): (usize, usize)| serde_json::json!({"row": row, "col": col});
+    match mouse {
+        Mouse::Click { row, col, button } => {
+            serde_json::json!({"type": "MOUSE", "row": row, "col": col, "button": button.as_str()})
+        }
+        Mouse::Drag { from: (row, col), to, button } => serde_json::json!({
+            "type": "MOUSE",
+            "row": row,
+            "col": col,
+            "button": button.as_str(),
+            "drag_to": position(to)
+        }),
+        Mouse::Scroll { row, col, up } => {
+            serde_json::json!({"type": "MOUSE", "row": row, "col": col, "scroll": if up { "up" } else { "down" }})
+        }
+    }
+}
+
+/// A PNG screenshot as OUTPUT reports it
+#[derive(Deserialize)]
+pub(crate) struct Image {
+    png: String,
+    width: u32,
+    height: u32,
+}
+
+impl Image {
+    pub(crate) fn decode(self) -> Result<Screenshot> {
+        Ok(Screenshot {
+            png: BASE64.decode(self.png).context("Invalid image data")?,
+            width: self.width,
+            height: self.height,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct OutputOptions {
+    /// ascii (the default), ansi, cells, spans, html or svg
+    pub format: Option<String>,
+    /// Only report the rows that changed after this screen generation
+    pub since: Option<u64>,
+    /// Also report up to this many lines of scrollback history
+    pub scrollback: Option<usize>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub struct Cursor {
+    pub row: usize,
+    pub col: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub struct Size {
+    pub rows: usize,
+    pub cols: usize,
+}
+
+/// The screen as OUTPUT reports it (positions are 0-based)
+#[derive(Debug, Clone, Deserialize)]
+pub struct Screen {
+    /// Screen contents in the requested format: text with one line per row,
+    /// or an HTML/SVG document. Not set for diffs and cells/spans.
+    #[serde(default, rename = "screen")]
+    pub text: Option<String>,
+    pub cursor: Cursor,
+    pub size: Size,
+    /// Changes whenever the screen does, for `OutputOptions::since`
+    pub generation: u64,
+    /// One JSON array of cells per row, for the cells format
+    #[serde(default)]
+    pub cells: Option<Vec<serde_json::Value>>,
+    /// One JSON array of spans per row, for the spans format
+    #[serde(default)]
+    pub spans: Option<Vec<serde_json::Value>>,
+    /// With `since`, the rows that changed
+    #[serde(default)]
+    pub changed_rows: Option<Vec<ChangedRow>>,
+    /// With `scrollback`, the history lines above the screen
+    #[serde(default)]
+    pub scrollback: Option<String>,
+    /// With `scrollback`, how many history lines there are in all
+    #[serde(default)]
+    pub history_size: Option<usize>,
+}
+
+impl Screen {
+    /// The text of each row
+    pub fn lines(&self) -> impl Iterator<Item = &str> {
+        self.text.as_deref().unwrap_or_default().lines()
+    }
+
+    /// The text of the row the cursor is on
+    pub fn cursor_line(&self) -> Option<&str> {
+        self.lines().nth(self.cursor.row)
+    }
+
+    pub fn contains(&self, text: &str) -> bool {
+        self.text.as_deref().is_some_and(|screen| screen.contains(text))
+    }
+}
+
+/// A row reported by a diff, in the format that was asked for
+#[derive(Debug, Clone, Deserialize)]
+pub struct ChangedRow {
+    pub row: usize,
+    #[serde(default)]
+    pub text: Option<String>,
+    #[serde(default)]
+    pub cells: Option<serde_json::Value>,
+    #[serde(default)]
+    pub spans: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Screenshot {
+    pub png: Vec<u8>,
+    pub width: u32,
+    pub height: u32,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Status {
+    pub running: bool,
+    pub pid: i32,
+    pub command: String,
+    pub rows: usize,
+    pub cols: usize,
+    /// Whether there was output since the last check, if asked for
+    #[serde(default)]
+    pub activity: Option<bool>,
+    #[serde(default)]
+    pub exit_code: Option<i32>,
+}
+
+/// What `wait` blocks for
+#[derive(Debug, Clone, Copy)]
+pub enum WaitFor {
+    /// New output or the application exiting
+    Activity,
+    /// The application exiting
+    Exit,
+    /// No output for this long
+    Settle(Duration),
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct WaitResult {
+    #[serde(default)]
+    pub activity: bool,
+    #[serde(default)]
+    pub exited: bool,
+    #[serde(default)]
+    pub exit_code: Option<i32>,
+    #[serde(default)]
+    pub settled: bool,
+    #[serde(default)]
+    pub timed_out: bool,
+}
+
+/// What `expect` matches its patterns against
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExpectTarget {
+    /// The rendered screen
+    Screen,
+    /// The raw output stream, from the oldest output still kept (see
+    /// `expect_output_from` to skip output already matched)
+    Output,
+}
+
+impl ExpectTarget {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ExpectTarget::Screen => "screen",
+            ExpectTarget::Output => "output",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MouseButton {
+    #[default]
+    Left,
+    Middle,
+    Right,
+}
+
+impl MouseButton {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MouseButton::Left => "left",
+            MouseButton::Middle => "middle",
+            MouseButton::Right => "right",
+        }
+    }
+}
+
+/// What `mouse` does, at 0-based screen positions
+#[derive(Debug, Clone, Copy)]
+pub enum Mouse {
+    /// Press and release a button
+    Click { row: usize, col: usize, button: MouseButton },
+    /// Press a button at `from`, move to `to` and release it there
+    Drag { from: (usize, usize), to: (usize, usize), button: MouseButton },
+    /// Turn the wheel one step
+    Scroll { row: usize, col: usize, up: bool },
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ExpectResult {
+    pub matched: bool,
+    /// The screen when the match was found (or the wait ended)
+    pub screen: String,
+    pub cursor: Cursor,
+    /// Index into the patterns of the one that matched
+    #[serde(default)]
+    pub pattern_index: Option<usize>,
+    #[serde(default)]
+    pub pattern: Option<String>,
+    /// The matched text
+    #[serde(default, rename = "match")]
+    pub text: Option<String>,
+    /// Where the match starts
+    #[serde(default)]
+    pub row: Option<usize>,
+    #[serde(default)]
+    pub col: Option<usize>,
+    /// Output offset just past the match (or past all the output searched,
+    /// if nothing matched), for the output target
+    #[serde(default)]
+    pub end_offset: Option<u64>,
+    #[serde(default)]
+    pub timed_out: bool,
+    #[serde(default)]
+    pub exited: bool,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct UnhandledSequence {
+    pub sequence: String,
+    pub raw_hex: String,
+}
+
+/// Terminal settings of the PTY
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct Termios {
+    /// Why the settings couldn't be read
+    #[serde(default)]
+    pub error: Option<String>,
+    /// raw, cbreak or cooked
+    #[serde(default)]
+    pub mode: String,
+    #[serde(default)]
+    pub flags: Vec<String>,
+    /// iflag, oflag, lflag and cflag in hex
+    #[serde(default)]
+    pub hex: BTreeMap<String, String>,
+    /// Control characters like VINTR and VEOF
+    #[serde(default)]
+    pub c_cc: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct DebugInfo {
+    pub unhandled: Vec<UnhandledSequence>,
+    /// Sequences lost because the buffer was full
+    pub dropped: usize,
+    pub termios: Termios,
+}
+
+/// Events from a subscription, until the application exits or the daemon stops
+pub struct Events {
+    reader: BufReader<UnixStream>,
+    deadline: Option<Instant>,
+    timeout: Duration,
+}
+
+impl Events {
+    /// The next line from the daemon, or None at the end of the stream
+    fn read_line(&mut self) -> Result<Option<String>> {
+        if let Some(deadline) = self.deadline {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                return Err(timeout_error("waiting for events", self.timeout));
+            }
+            self.reader.get_ref().set_read_timeout(Some(remaining))?;
+        }
+
+        let mut line = String::new();
+        match self.reader.read_line(&mut line) {
+            Ok(0) => Ok(None),
+            Ok(_) => Ok(Some(line)),
+            Err(e) if is_timeout(&e) => Err(timeout_error("waiting for events", self.timeout)),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+impl Iterator for Events {
+    type Item = Result<serde_json::Value>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.read_line() {
+            Ok(Some(line)) => Some(serde_json::from_str(&line).map_err(Into::into)),
+            Ok(None) => None,
+            Err(e) => Some(Err(e)),
+        }
+    }
+}
+
+/// How to start a session
+#[derive(Debug, Clone, Default)]
+pub struct StartOptions {
+    /// Socket to listen on (a fresh one in a temp dir by default)
+    pub socket: Option<PathBuf>,
+    /// Terminal size as (cols, rows), 80x24 by default
+    pub size: Option<(u16, u16)>,
+    /// Terminal emulator: xterm (the default) or custom
+    pub emulator: Option<String>,
+    /// Token clients must present
+    pub token: Option<String>,
+    /// The interminai binary to run (looked up in PATH by default)
+    pub program: Option<PathBuf>,
+}
+
+/// A session started by this program. Dereferences to its `Client`;
+/// the daemon is stopped when the `Session` is dropped, unless detached.
+pub struct Session {
+    client: Client,
+    pid: Option<u32>,
+    stop_on_drop: bool,
+}
+
+impl Session {
+    /// Start `command` under a new daemon and wait until it accepts connections
+    pub fn start<S: AsRef<OsStr>>(command: &[S], options: &StartOptions) -> Result<Session> {
+        let program = options.program.clone().unwrap_or_else(|| PathBuf::from("interminai"));
+        let mut start = Command::new(&program);
+        start.args(["start", "--json"]);
+        if let Some(socket) = &options.socket {
+            start.arg("--socket").arg(socket);
+        }
+        if let Some((cols, rows)) = options.size {
+            start.arg("--size").arg(format!("{}x{}", cols, rows));
+        }
+        if let Some(emulator) = &options.emulator {
+            start.args(["--emulator", emulator]);
+        }
+        if let Some(token) = &options.token {
+            start.args(["--token", token]);
+        }
+        start.arg("--").args(command);
+
+        let output = start.output().with_context(|| format!("Failed to run {}", program.display()))?;
+        if !output.status.success() {
+            bail!("Failed to start session: {}", String::from_utf8_lossy(&output.stderr).trim());
+        }
+
+        // `start` returns once the daemon is listening
+        #[derive(Deserialize)]
+        struct Started {
+            socket: PathBuf,
+            pid: u32,
+        }
+        let started: Started = serde_json::from_slice(&output.stdout).context("Daemon didn't report its socket")?;
+
+        let mut client = Client::new(started.socket);
+        if let Some(token) = &options.token {
+            client = client.token(token);
+        }
+
+        Ok(Session { client, pid: Some(started.pid), stop_on_drop: true })
+    }
+
+    pub fn client(&self) -> &Client {
+        &self.client
+    }
+
+    /// Process ID of the daemon
+    pub fn pid(&self) -> Option<u32> {
+        self.pid
+    }
+
+    /// Stop the daemon, reporting failure (dropping the session ignores it)
+    pub fn stop(mut self) -> Result<()> {
+        self.stop_on_drop = false;
+        self.client.stop()
+    }
+
+    /// Leave the daemon running once this handle is gone
+    pub fn detach(mut self) -> Client {
+        self.stop_on_drop = false;
+        self.client.clone()
+    }
+}
+
+impl Deref for Session {
+    type Target = Client;
+
+    fn deref(&self) -> &Client {
+        &self.client
+    }
+}
+
+impl Drop for Session {
+    fn drop(&mut self) {
+        if self.stop_on_drop {
+            let _ = self.client.stop();
+        }
+    }
+}
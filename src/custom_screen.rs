@@ -3,8 +3,9 @@
 // This is the original terminal emulator that was extracted from main.rs.
 // It uses the vte crate for parsing ANSI escape sequences.
 
+use std::collections::VecDeque;
 use vte::Perform;
-use crate::terminal::{TerminalEmulator, UnhandledSequence};
+use crate::terminal::{KeyboardModes, MouseEncoding, MouseMode, MouseTracking, StyledCell, TerminalEmulator, UnhandledSequence};
 
 /// Ring buffer for tracking unhandled escape sequences
 struct DebugBuffer {
@@ -60,14 +61,27 @@ pub struct CustomScreen {
     parser: vte::Parser,
     /// Delayed wrap mode: when true, the next printable character will wrap to next line first
     pending_wrap: bool,
+    /// Lines scrolled off the top of the screen, oldest first
+    history: VecDeque<Vec<char>>,
+    /// Maximum number of history lines kept
+    scrollback: usize,
+    /// Cursor and keypad modes, the only keyboard modes tracked here
+    keyboard_modes: KeyboardModes,
+    /// Bracketed paste mode (2004)
+    bracketed_paste: bool,
+    /// Mouse tracking (1000, 1002, 1003) and encoding (1005, 1006) modes
+    mouse_tracking: MouseTracking,
+    utf8_mouse: bool,
+    sgr_mouse: bool,
 }
 
 impl CustomScreen {
-    pub fn new(rows: usize, cols: usize) -> Self {
-        Self::with_debug_buffer(rows, cols, 10)
+    /// Create a screen keeping up to `scrollback` lines of history
+    pub fn new(rows: usize, cols: usize, scrollback: usize) -> Self {
+        Self::with_debug_buffer(rows, cols, scrollback, 10)
     }
 
-    pub fn with_debug_buffer(rows: usize, cols: usize, debug_buffer_size: usize) -> Self {
+    pub fn with_debug_buffer(rows: usize, cols: usize, scrollback: usize, debug_buffer_size: usize) -> Self {
         CustomScreen {
             rows,
             cols,
@@ -79,6 +93,13 @@ impl CustomScreen {
             pending_responses: Vec::new(),
             parser: vte::Parser::new(),
             pending_wrap: false,
+            history: VecDeque::new(),
+            scrollback,
+            keyboard_modes: KeyboardModes::default(),
+            bracketed_paste: false,
+            mouse_tracking: MouseTracking::Off,
+            utf8_mouse: false,
+            sgr_mouse: false,
         }
     }
 
@@ -112,9 +133,41 @@ impl CustomScreen {
     }
 
     fn scroll_up(&mut self) {
-        self.cells.remove(0);
+        let line = self.cells.remove(0);
+        if self.scrollback > 0 {
+            if self.history.len() >= self.scrollback {
+                self.history.pop_front();
+            }
+            self.history.push_back(line);
+        }
         self.cells.push(vec![' '; self.cols]);
     }
+
+    /// Keep a CSI sequence we don't implement in the debug buffer
+    fn record_unhandled(&mut self, params: &vte::Params, intermediates: &[u8], action: char) {
+        let mut seq = String::from("\\e[");
+        for intermediate in intermediates {
+            seq.push(*intermediate as char);
+        }
+        let param_strs: Vec<String> = params.iter()
+            .map(|p| p.iter().map(|v| v.to_string()).collect::<Vec<_>>().join(":"))
+            .collect();
+        seq.push_str(&param_strs.join(";"));
+        seq.push(action);
+
+        let mut raw = vec![0x1b, b'['];
+        raw.extend_from_slice(intermediates);
+        for (i, p) in params.iter().enumerate() {
+            if i > 0 { raw.push(b';'); }
+            for (j, v) in p.iter().enumerate() {
+                if j > 0 { raw.push(b':'); }
+                raw.extend_from_slice(v.to_string().as_bytes());
+            }
+        }
+        raw.push(action as u8);
+
+        self.debug_buffer.push(seq, &raw);
+    }
 }
 
 impl TerminalEmulator for CustomScreen {
@@ -132,6 +185,29 @@ impl TerminalEmulator for CustomScreen {
         self.to_ascii()
     }
 
+    fn get_cells(&self) -> Vec<Vec<StyledCell>> {
+        // No attributes are tracked, so every cell has the default style
+        self.cells
+            .iter()
+            .map(|row| row.iter().map(|&c| StyledCell::plain(c)).collect())
+            .collect()
+    }
+
+    fn history_size(&self) -> usize {
+        self.history.len()
+    }
+
+    fn get_history(&self, lines: usize) -> String {
+        let skip = self.history.len().saturating_sub(lines);
+        let mut result = String::new();
+        for row in self.history.iter().skip(skip) {
+            let line: String = row.iter().collect();
+            result.push_str(line.trim_end());
+            result.push('\n');
+        }
+        result
+    }
+
     fn cursor_position(&self) -> (usize, usize) {
         (self.cursor_row, self.cursor_col)
     }
@@ -172,6 +248,25 @@ impl TerminalEmulator for CustomScreen {
     fn get_debug_dropped(&self) -> usize {
         self.debug_buffer.get_dropped()
     }
+
+    fn keyboard_modes(&self) -> KeyboardModes {
+        self.keyboard_modes
+    }
+
+    fn bracketed_paste(&self) -> bool {
+        self.bracketed_paste
+    }
+
+    fn mouse_mode(&self) -> MouseMode {
+        let encoding = if self.sgr_mouse {
+            MouseEncoding::Sgr
+        } else if self.utf8_mouse {
+            MouseEncoding::Utf8
+        } else {
+            MouseEncoding::X10
+        };
+        MouseMode { tracking: self.mouse_tracking, encoding }
+    }
 }
 
 impl Perform for CustomScreen {
@@ -415,36 +510,46 @@ impl Perform for CustomScreen {
                     _ => {}
                 }
             }
+            'h' | 'l' if intermediates == b"?" => {
+                // DEC private modes that change what input the application
+                // expects. Others (like the alternate screen) are only kept
+                // for debugging, without dropping the ones set alongside.
+                let set = action == 'h';
+                let mut unhandled = false;
+                for mode in params.iter() {
+                    match mode {
+                        // DECCKM - application cursor keys
+                        [1] => self.keyboard_modes.app_cursor = set,
+                        [mode @ (9 | 1000 | 1002 | 1003)] => {
+                            let tracking = match mode {
+                                9 => MouseTracking::Press,
+                                1000 => MouseTracking::Click,
+                                1002 => MouseTracking::Drag,
+                                _ => MouseTracking::Motion,
+                            };
+                            if set {
+                                self.mouse_tracking = tracking;
+                            } else if self.mouse_tracking == tracking {
+                                self.mouse_tracking = MouseTracking::Off;
+                            }
+                        }
+                        [1005] => self.utf8_mouse = set,
+                        [1006] => self.sgr_mouse = set,
+                        [2004] => self.bracketed_paste = set,
+                        _ => unhandled = true,
+                    }
+                }
+                if unhandled {
+                    self.record_unhandled(params, intermediates, action);
+                }
+            }
             'c' => {
                 let mode = params.iter().nth(0).and_then(|p| p.first()).copied().unwrap_or(0);
                 if mode == 0 {
                     self.pending_responses.push(b"\x1b[?1;2c".to_vec());
                 }
             }
-            _ => {
-                let mut seq = String::from("\\e[");
-                for intermediate in intermediates {
-                    seq.push(*intermediate as char);
-                }
-                let param_strs: Vec<String> = params.iter()
-                    .map(|p| p.iter().map(|v| v.to_string()).collect::<Vec<_>>().join(":"))
-                    .collect();
-                seq.push_str(&param_strs.join(";"));
-                seq.push(action);
-
-                let mut raw = vec![0x1b, b'['];
-                raw.extend_from_slice(intermediates);
-                for (i, p) in params.iter().enumerate() {
-                    if i > 0 { raw.push(b';'); }
-                    for (j, v) in p.iter().enumerate() {
-                        if j > 0 { raw.push(b':'); }
-                        raw.extend_from_slice(v.to_string().as_bytes());
-                    }
-                }
-                raw.push(action as u8);
-
-                self.debug_buffer.push(seq, &raw);
-            }
+            _ => self.record_unhandled(params, intermediates, action),
         }
     }
 
@@ -453,6 +558,10 @@ impl Perform for CustomScreen {
             b'H' => {
                 // Set Tab Stop (hts) - we use fixed 8-column tabs, ignore
             }
+            b'=' | b'>' if intermediates.is_empty() => {
+                // DECKPAM / DECKPNM - application or numeric keypad
+                self.keyboard_modes.app_keypad = byte == b'=';
+            }
             _ => {
                 let mut seq = String::from("\\e");
                 for intermediate in intermediates {
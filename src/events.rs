@@ -0,0 +1,49 @@
+// Session event stream
+//
+// Events are pushed to SUBSCRIBE clients as they happen, so clients can react
+// to changes instead of polling STATUS or OUTPUT.
+
+use serde::Serialize;
+use tokio::sync::mpsc::{channel, Receiver, Sender};
+
+/// Events a subscriber may fall behind by before it is dropped
+const QUEUE_SIZE: usize = 1024;
+
+/// Something that happened in the session
+#[derive(Clone, Debug, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Event {
+    /// The application wrote to the terminal
+    Output { bytes: usize, offset: u64 },
+    /// The rendered screen changed
+    ScreenChanged { generation: u64 },
+    /// The cursor moved
+    CursorMoved { row: usize, col: usize },
+    /// The application switched between raw and cooked mode, or toggled echo
+    ModeChanged { mode: &'static str, echo: bool },
+    /// The terminal was resized
+    Resize { rows: usize, cols: usize },
+    /// The application exited
+    Exit { exit_code: i32 },
+}
+
+/// Clients currently subscribed to events
+#[derive(Default)]
+pub struct Subscribers {
+    senders: Vec<Sender<Event>>,
+}
+
+impl Subscribers {
+    pub fn subscribe(&mut self) -> Receiver<Event> {
+        let (sender, receiver) = channel(QUEUE_SIZE);
+        self.senders.push(sender);
+        receiver
+    }
+
+    /// Send an event to every subscriber.
+    /// Subscribers that went away or stopped reading are dropped, which ends
+    /// their stream once they have drained what was already queued.
+    pub fn emit(&mut self, event: Event) {
+        self.senders.retain(|sender| sender.try_send(event.clone()).is_ok());
+    }
+}
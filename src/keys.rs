@@ -0,0 +1,310 @@
+// Named keys
+//
+// `input --keys "C-c Up Enter"` names keys tmux-style instead of spelling
+// out their escape sequences. What a key sends depends on the keyboard modes
+// the application has turned on (application cursor keys, application
+// keypad, the kitty keyboard protocol), so names are encoded by the daemon
+// against the live terminal state, right before they are written.
+
+use crate::terminal::KeyboardModes;
+
+/// Kitty keyboard protocol flags
+const KITTY_DISAMBIGUATE: u8 = 1;
+const KITTY_ALTERNATE_KEYS: u8 = 4;
+const KITTY_ALL_KEYS_AS_ESCAPES: u8 = 8;
+const KITTY_ASSOCIATED_TEXT: u8 = 16;
+
+/// Kitty's code for the keypad's 0; the other keypad keys follow it
+const KITTY_KEYPAD_0: u32 = 57399;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum KeyCode {
+    Char(char),
+    Enter,
+    Tab,
+    Backspace,
+    Escape,
+    Up,
+    Down,
+    Right,
+    Left,
+    Home,
+    End,
+    Insert,
+    Delete,
+    PageUp,
+    PageDown,
+    F(u8),
+    /// A keypad key, by the character it types ('\r' for Enter)
+    Keypad(char),
+}
+
+/// A key with modifiers, as named by the client
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Key {
+    name: String,
+    code: KeyCode,
+    shift: bool,
+    alt: bool,
+    ctrl: bool,
+}
+
+/// Parse space-separated key names like "C-c Up Up Enter F5 M-x S-Tab"
+pub fn parse(names: &str) -> Result<Vec<Key>, String> {
+    let keys: Vec<Key> = names.split_whitespace().map(parse_key).collect::<Result<_, _>>()?;
+    if keys.is_empty() {
+        return Err("No keys given".to_string());
+    }
+    Ok(keys)
+}
+
+/// Parse one key name: modifiers C- (Ctrl), M- (Alt/Meta) and S- (Shift),
+/// then a key name or a single character
+fn parse_key(name: &str) -> Result<Key, String> {
+    let mut key = Key { name: name.to_string(), code: KeyCode::Enter, shift: false, alt: false, ctrl: false };
+
+    let mut rest = name;
+    while rest.len() > 2 {
+        match rest.get(..2) {
+            Some("C-") => key.ctrl = true,
+            Some("M-") => key.alt = true,
+            Some("S-") => key.shift = true,
+            _ => break,
+        }
+        rest = &rest[2..];
+    }
+
+    let mut chars = rest.chars();
+    key.code = match (chars.next(), chars.next()) {
+        (Some(c), None) => KeyCode::Char(c),
+        _ => match named_key(rest) {
+            Some(code) => code,
+            None => return Err(format!("Unknown key '{}'", name)),
+        },
+    };
+    if key.code == KeyCode::Tab && rest.eq_ignore_ascii_case("BTab") {
+        key.shift = true;
+    }
+    Ok(key)
+}
+
+/// Key names, case-insensitively (tmux's names and some common spellings)
+fn named_key(name: &str) -> Option<KeyCode> {
+    let lower = name.to_ascii_lowercase();
+    let code = match lower.as_str() {
+        "enter" => KeyCode::Enter,
+        "tab" | "btab" => KeyCode::Tab,
+        "bspace" | "backspace" => KeyCode::Backspace,
+        "escape" | "esc" => KeyCode::Escape,
+        "space" => KeyCode::Char(' '),
+        "up" => KeyCode::Up,
+        "down" => KeyCode::Down,
+        "right" => KeyCode::Right,
+        "left" => KeyCode::Left,
+        "home" => KeyCode::Home,
+        "end" => KeyCode::End,
+        "ic" | "insert" => KeyCode::Insert,
+        "dc" | "delete" => KeyCode::Delete,
+        "ppage" | "pageup" | "pgup" => KeyCode::PageUp,
+        "npage" | "pagedown" | "pgdn" => KeyCode::PageDown,
+        "kpenter" => KeyCode::Keypad('\r'),
+        _ => {
+            if let Some(n) = lower.strip_prefix('f').and_then(|n| n.parse().ok()).filter(|n| (1..=12).contains(n)) {
+                return Some(KeyCode::F(n));
+            }
+            let mut chars = lower.strip_prefix("kp")?.chars();
+            return match (chars.next(), chars.next()) {
+                (Some(c @ ('0'..='9' | '.' | '/' | '*' | '-' | '+' | '=')), None) => Some(KeyCode::Keypad(c)),
+                _ => None,
+            };
+        }
+    };
+    Some(code)
+}
+
+impl Key {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// xterm/kitty modifier parameter: 1 plus the modifier bits
+    fn modifiers(&self) -> u8 {
+        1 + self.shift as u8 + 2 * self.alt as u8 + 4 * self.ctrl as u8
+    }
+
+    /// The bytes this key sends in `modes`, or None if it can't be sent
+    pub fn encode(&self, modes: KeyboardModes) -> Option<Vec<u8>> {
+        if modes.kitty & (KITTY_DISAMBIGUATE | KITTY_ALL_KEYS_AS_ESCAPES) != 0 {
+            Some(self.encode_kitty(modes.kitty).into_bytes())
+        } else {
+            self.encode_legacy(modes)
+        }
+    }
+
+    /// CSI sequences shared by both encodings, for keys that have no text
+    fn encode_functional(&self, kitty: bool) -> Option<String> {
+        let modifiers = self.modifiers();
+        // Cursor keys and F1-F4 end in a letter, the rest in ~
+        let (number, end) = match self.code {
+            KeyCode::Up => (1, 'A'),
+            KeyCode::Down => (1, 'B'),
+            KeyCode::Right => (1, 'C'),
+            KeyCode::Left => (1, 'D'),
+            KeyCode::Home => (1, 'H'),
+            KeyCode::End => (1, 'F'),
+            KeyCode::F(1) => (1, 'P'),
+            KeyCode::F(2) => (1, 'Q'),
+            // Kitty dropped CSI R for F3, it reads like a cursor position report
+            KeyCode::F(3) if kitty => (13, '~'),
+            KeyCode::F(3) => (1, 'R'),
+            KeyCode::F(4) => (1, 'S'),
+            KeyCode::Insert => (2, '~'),
+            KeyCode::Delete => (3, '~'),
+            KeyCode::PageUp => (5, '~'),
+            KeyCode::PageDown => (6, '~'),
+            KeyCode::F(n) => ([15, 17, 18, 19, 20, 21, 23, 24][n as usize - 5], '~'),
+            _ => return None,
+        };
+        Some(match (modifiers, end) {
+            (1, '~') => format!("\x1b[{}~", number),
+            (1, _) => format!("\x1b[{}", end),
+            _ => format!("\x1b[{};{}{}", number, modifiers, end),
+        })
+    }
+
+    fn encode_legacy(&self, modes: KeyboardModes) -> Option<Vec<u8>> {
+        let unmodified = self.modifiers() == 1;
+        match self.code {
+            KeyCode::Up | KeyCode::Down | KeyCode::Right | KeyCode::Left | KeyCode::Home | KeyCode::End
+                if unmodified && modes.app_cursor =>
+            {
+                let csi = self.encode_functional(false)?;
+                return Some(format!("\x1bO{}", &csi[2..]).into_bytes());
+            }
+            KeyCode::F(1..=4) if unmodified => {
+                let csi = self.encode_functional(false)?;
+                return Some(format!("\x1bO{}", &csi[2..]).into_bytes());
+            }
+            KeyCode::Keypad(c) if unmodified && modes.app_keypad => {
+                let end = match c {
+                    '\r' => 'M',
+                    '*' => 'j',
+                    '+' => 'k',
+                    '-' => 'm',
+                    '.' => 'n',
+                    '/' => 'o',
+                    '=' => 'X',
+                    digit => (b'p' + (digit as u8 - b'0')) as char,
+                };
+                return Some(format!("\x1bO{}", end).into_bytes());
+            }
+            _ => {}
+        }
+        if let Some(csi) = self.encode_functional(false) {
+            return Some(csi.into_bytes());
+        }
+
+        let mut bytes = Vec::new();
+        if self.alt {
+            bytes.push(0x1b);
+        }
+        match self.code {
+            KeyCode::Tab if self.shift => bytes.extend_from_slice(b"\x1b[Z"),
+            KeyCode::Tab => bytes.push(b'\t'),
+            KeyCode::Enter | KeyCode::Keypad('\r') => bytes.push(b'\r'),
+            KeyCode::Escape => bytes.push(0x1b),
+            KeyCode::Backspace if self.ctrl => bytes.push(0x08),
+            KeyCode::Backspace => bytes.push(0x7f),
+            KeyCode::Char(c) | KeyCode::Keypad(c) => {
+                let c = if self.shift { c.to_ascii_uppercase() } else { c };
+                if self.ctrl {
+                    bytes.push(control_code(c)?);
+                } else {
+                    bytes.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes());
+                }
+            }
+            _ => return None,
+        }
+        Some(bytes)
+    }
+
+    fn encode_kitty(&self, flags: u8) -> String {
+        if let Some(csi) = self.encode_functional(true) {
+            return csi;
+        }
+
+        let all_keys = flags & KITTY_ALL_KEYS_AS_ESCAPES != 0;
+        let unmodified = self.modifiers() == 1;
+        let (code, text) = match self.code {
+            KeyCode::Enter => (13, None),
+            KeyCode::Tab => (9, None),
+            KeyCode::Backspace => (127, None),
+            KeyCode::Escape => (27, None),
+            KeyCode::Keypad(c) => {
+                let index = match c {
+                    '0'..='9' => c as u32 - '0' as u32,
+                    '.' => 10,
+                    '/' => 11,
+                    '*' => 12,
+                    '-' => 13,
+                    '+' => 14,
+                    '\r' => 15,
+                    _ => 16,
+                };
+                (KITTY_KEYPAD_0 + index, None)
+            }
+            KeyCode::Char(c) => {
+                // Keys are reported by their unshifted character
+                let shifted = if self.shift { c.to_ascii_uppercase() } else { c };
+                let shift = self.shift || c.is_ascii_uppercase();
+                let text = (!self.ctrl && !self.alt).then_some(shifted);
+                // Plain text stays text unless every key is to be reported
+                if let (Some(text), false) = (text, all_keys) {
+                    return text.to_string();
+                }
+                let key = Key { shift, ..self.clone() };
+                return key.csi_u(c.to_ascii_lowercase() as u32, Some(shifted).filter(|_| shift), text, flags);
+            }
+            _ => unreachable!("functional keys are encoded above"),
+        };
+
+        // Without modifiers, Enter, Tab and Backspace keep their legacy bytes
+        if unmodified && !all_keys {
+            match self.code {
+                KeyCode::Enter => return "\r".to_string(),
+                KeyCode::Tab => return "\t".to_string(),
+                KeyCode::Backspace => return "\x7f".to_string(),
+                _ => {}
+            }
+        }
+        self.csi_u(code, None, text, flags)
+    }
+
+    /// CSI code[:shifted];modifiers[;text]u
+    fn csi_u(&self, code: u32, shifted: Option<char>, text: Option<char>, flags: u8) -> String {
+        let mut sequence = format!("\x1b[{}", code);
+        if let Some(shifted) = shifted.filter(|_| flags & KITTY_ALTERNATE_KEYS != 0) {
+            sequence.push_str(&format!(":{}", shifted as u32));
+        }
+        let text = text.filter(|_| flags & KITTY_ASSOCIATED_TEXT != 0);
+        if self.modifiers() != 1 || text.is_some() {
+            sequence.push_str(&format!(";{}", self.modifiers()));
+        }
+        if let Some(text) = text {
+            sequence.push_str(&format!(";{}", text as u32));
+        }
+        sequence.push('u');
+        sequence
+    }
+}
+
+/// The control character Ctrl+`c` sends, if it has one
+fn control_code(c: char) -> Option<u8> {
+    match c.to_ascii_uppercase() {
+        c @ '@'..='_' => Some(c as u8 - b'@'),
+        ' ' | '2' => Some(0),
+        '/' => Some(0x1f),
+        '?' => Some(0x7f),
+        _ => None,
+    }
+}
@@ -0,0 +1,31 @@
+//! Client library for interminai sessions.
+//!
+//! ```no_run
+//! use interminai::{Session, StartOptions, WaitFor};
+//! use std::time::Duration;
+//!
+//! let session = Session::start(&["bash"], &StartOptions::default())?;
+//! session.input("echo hello\r")?;
+//! let client = session.client().clone().timeout(Duration::from_secs(5));
+//! client.wait(WaitFor::Settle(Duration::from_millis(300)))?;
+//! println!("{}", client.output()?.text.unwrap_or_default());
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+//!
+//! `AsyncClient` offers the same requests to tokio programs, which can keep
+//! many sessions waiting at once without a thread for each.
+//!
+//! The daemon itself lives in the `interminai` binary; see docs/PROTOCOL.md
+//! for the requests a `Client` sends.
+
+pub mod async_client;
+pub mod client;
+pub mod protocol;
+
+pub use async_client::{AsyncClient, AsyncEvents};
+
+pub use client::{
+    ChangedRow, Client, Cursor, DaemonError, DebugInfo, Events, ExpectResult, ExpectTarget, Mouse, MouseButton,
+    OutputOptions, Screen, Screenshot, Session, Size, StartOptions, Status, Termios, TimeoutError,
+    UnhandledSequence, WaitFor, WaitResult,
+};
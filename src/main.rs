@@ -7,18 +7,34 @@
 mod terminal;
 mod custom_screen;
 mod alacritty_backend;
+mod output_log;
+mod events;
+mod render;
+mod screenshot;
+mod recording;
+mod access;
+mod server;
+mod sessions;
+mod tape;
+mod keys;
+mod mouse;
 
 use clap::{Parser as ClapParser, Subcommand};
 use anyhow::{Result, Context, bail};
 use std::process::{Command as ProcessCommand};
 use std::os::unix::process::CommandExt;
 use tempfile::Builder;
-use serde::{Deserialize, Serialize};
-use std::os::unix::net::{UnixListener, UnixStream};
-use std::io::{BufRead, BufReader, Write, Read};
+use tokio::net::UnixStream;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt};
+use tokio::io::unix::AsyncFd;
+use tokio::signal::unix::{signal, SignalKind};
+use tokio::sync::Notify;
+use std::io::{Write, Read};
+use std::future::Future;
+use std::ops::ControlFlow;
 use std::sync::{Arc, Mutex};
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use nix::pty::{openpty, Winsize};
 use nix::unistd::{setsid, Pid};
 use nix::sys::wait::{waitpid, WaitStatus, WaitPidFlag};
@@ -27,8 +43,18 @@ use nix::sys::termios::{tcgetattr, LocalFlags, InputFlags, OutputFlags, SpecialC
 use std::os::fd::{AsRawFd, OwnedFd};
 use std::fs;
 use std::path::Path;
+use regex::Regex;
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
 
 use terminal::TerminalEmulator;
+use output_log::OutputLog;
+use events::{Event, Subscribers};
+use recording::{RecordConfig, RecordFormat, Recorded, Recorder};
+use access::Access;
+use interminai::client::{
+    timeout_error, Client, DaemonError, ExpectTarget, Mouse, MouseButton, OutputOptions, TimeoutError, WaitFor,
+};
+use interminai::protocol::{Request, Response};
 
 /// Terminal emulator backend
 #[derive(Clone, Copy, Debug, Default, clap::ValueEnum)]
@@ -40,10 +66,50 @@ pub enum Emulator {
     Custom,
 }
 
+impl Emulator {
+    /// Name of the backend, as given to --emulator
+    fn name(self) -> &'static str {
+        match self {
+            Emulator::Xterm => "xterm",
+            Emulator::Custom => "custom",
+        }
+    }
+
+    /// TERM value for applications running on this backend
+    fn term(self) -> &'static str {
+        match self {
+            // xterm (alacritty) supports full xterm-256color capabilities
+            Emulator::Xterm => "xterm-256color",
+            // custom uses basic ANSI escape sequences
+            Emulator::Custom => "ansi",
+        }
+    }
+}
+
 #[derive(ClapParser)]
 #[command(name = "interminai")]
 #[command(about = "🌀 an Interactive Terminal for AI", long_about = None)]
 struct Cli {
+    /// Give up after this many milliseconds (connect, request and any waiting).
+    /// Exits with status 124 on timeout. 0 disables the deadline.
+    #[arg(long, global = true, value_name = "MS")]
+    timeout: Option<u64>,
+
+    /// Session to talk to, by name or ID, when the socket belongs to a server
+    #[arg(long, global = true, value_name = "NAME|ID")]
+    session: Option<String>,
+
+    /// Token of the session (default: $INTERMINAI_TOKEN). Given to start or
+    /// server, clients must present it; given to other commands, it is presented.
+    #[arg(long, global = true, value_name = "TOKEN")]
+    token: Option<String>,
+
+    /// Name of the agent or script calling (default: $INTERMINAI_CLIENT).
+    /// `output --diff` and `expect --target output` remember where they left
+    /// off per client, so callers sharing a session must each use their own name.
+    #[arg(long, global = true, value_name = "NAME")]
+    client: Option<String>,
+
     #[command(subcommand)]
     command: Commands,
 }
@@ -56,6 +122,11 @@ enum Commands {
         #[arg(long)]
         socket: Option<String>,
 
+        /// Name the session, so other commands can find it with --name NAME
+        /// (socket in $XDG_RUNTIME_DIR/interminai/NAME/)
+        #[arg(long, conflicts_with = "socket")]
+        name: Option<String>,
+
         /// Terminal size (e.g., 80x24)
         #[arg(long, default_value = "80x24")]
         size: String,
@@ -64,25 +135,125 @@ enum Commands {
         #[arg(long, value_enum, default_value = "xterm")]
         emulator: Emulator,
 
+        /// Lines of scrollback history to keep
+        #[arg(long, default_value_t = terminal::DEFAULT_SCROLLBACK)]
+        scrollback: usize,
+
+        /// Bytes of raw output to keep for READ and expect --target output
+        #[arg(long, value_name = "BYTES", default_value_t = output_log::DEFAULT_CAPACITY)]
+        output_buffer: usize,
+
         /// Run in foreground (for debugging/testing, default: daemon mode)
         #[arg(long)]
         no_daemon: bool,
 
+        /// Print the socket, PID and whether the socket was auto-generated as one JSON object
+        #[arg(long)]
+        json: bool,
+
+        #[command(flatten)]
+        access: AccessArgs,
+
         /// Dump all raw PTY output to this file (for debugging)
         #[arg(long)]
         pty_dump: Option<String>,
 
+        /// Record the session (output, input and resizes, with timing) to this file
+        #[arg(long, value_name = "FILE")]
+        record: Option<String>,
+
+        /// Recording format: asciicast (asciinema) or script (scriptreplay, timing in FILE.timing)
+        #[arg(long, value_enum, default_value = "asciicast", requires = "record")]
+        record_format: RecordFormat,
+
+        /// Rotate the recording to FILE.1 once it reaches this size
+        #[arg(long, value_name = "BYTES", default_value_t = recording::DEFAULT_MAX_BYTES, requires = "record")]
+        record_max_bytes: u64,
+
+        /// Number of rotated recordings to keep (FILE.1 is the most recent, 0 keeps none)
+        #[arg(long, value_name = "N", default_value_t = recording::DEFAULT_KEEP, requires = "record")]
+        record_keep: usize,
+
         /// Command to run
         #[arg(required = true, last = true)]
         command: Vec<String>,
     },
 
-    /// Send input to running session
-    Input {
-        /// Unix socket path (required)
+    /// Start a server that hosts many sessions on one socket (see new, list, close)
+    Server {
+        /// Unix socket path (auto-generated if not specified)
+        #[arg(long)]
+        socket: Option<String>,
+
+        /// Run in foreground (for debugging/testing, default: daemon mode)
+        #[arg(long)]
+        no_daemon: bool,
+
+        /// Print the socket, PID and whether the socket was auto-generated as one JSON object
+        #[arg(long)]
+        json: bool,
+
+        #[command(flatten)]
+        access: AccessArgs,
+    },
+
+    /// Start a new session on a server
+    New {
+        /// Server socket path (required)
+        #[arg(long, required = true)]
+        socket: String,
+
+        /// Name to address the session by, instead of its ID
+        #[arg(long)]
+        name: Option<String>,
+
+        /// Terminal size (e.g., 80x24)
+        #[arg(long, default_value = "80x24")]
+        size: String,
+
+        /// Terminal emulator backend (xterm or custom)
+        #[arg(long, value_enum, default_value = "xterm")]
+        emulator: Emulator,
+
+        /// Lines of scrollback history to keep
+        #[arg(long, default_value_t = terminal::DEFAULT_SCROLLBACK)]
+        scrollback: usize,
+
+        /// Bytes of raw output to keep for READ and expect --target output
+        #[arg(long, value_name = "BYTES", default_value_t = output_log::DEFAULT_CAPACITY)]
+        output_buffer: usize,
+
+        /// Command to run
+        #[arg(required = true, last = true)]
+        command: Vec<String>,
+    },
+
+    /// List running sessions, or with --socket the sessions on a server
+    List {
+        /// Server socket path
+        #[arg(long)]
+        socket: Option<String>,
+    },
+
+    /// Remove sockets and directories left behind by daemons that are gone
+    Gc,
+
+    /// Stop a session on a server, leaving the others running
+    Close {
+        /// Server socket path (required)
         #[arg(long, required = true)]
         socket: String,
 
+        /// Session name or ID
+        #[arg(value_name = "NAME|ID")]
+        target: String,
+    },
+
+    /// Send input to running session
+    Input {
+        #[command(flatten)]
+        socket: SocketArgs,
+
         /// Input text with escape sequences (alternative to stdin)
         /// Supports: \n \r \t \a \b \f \v \\ \e \xHH
         #[arg(long)]
@@ -92,13 +263,23 @@ enum Commands {
         /// Automatically appends \r (Enter) after input
         #[arg(long)]
         password: bool,
+
+        /// Named keys to press, tmux-style (e.g. "C-c Up Up Enter F5 M-x S-Tab").
+        /// Encoded for the keyboard modes the application has turned on.
+        #[arg(long, conflicts_with_all = ["text", "password"])]
+        keys: Option<String>,
+
+        /// Paste the input (--text or stdin) as one block, so the application
+        /// doesn't auto-indent it or act on each line. Needs bracketed paste
+        /// mode; without it the input is sent as typed, with a warning.
+        #[arg(long, conflicts_with_all = ["password", "keys"])]
+        paste: bool,
     },
 
     /// Get screen output from running session
     Output {
-        /// Unix socket path (required)
-        #[arg(long, required = true)]
-        socket: String,
+        #[command(flatten)]
+        socket: SocketArgs,
 
         /// Enable color output (default)
         #[arg(long)]
@@ -111,20 +292,34 @@ enum Commands {
         /// Cursor display mode (none, inverse, print, both)
         #[arg(long, default_value = "none")]
         cursor: String,
+
+        /// Print only the rows that changed since the previous output (by the same --client)
+        #[arg(long)]
+        diff: bool,
+
+        /// Print only the rows that changed since this screen generation
+        #[arg(long, value_name = "GENERATION")]
+        since: Option<u64>,
+
+        /// Also print up to this many lines of scrollback history above the screen
+        #[arg(long, value_name = "LINES")]
+        scrollback: Option<usize>,
+
+        /// Output format: ansi, ascii, cells/spans (one JSON array per row), or html/svg (standalone document)
+        #[arg(long, value_parser = ["ansi", "ascii", "cells", "spans", "html", "svg"])]
+        format: Option<String>,
     },
 
     /// Stop running session
     Stop {
-        /// Unix socket path (required)
-        #[arg(long, required = true)]
-        socket: String,
+        #[command(flatten)]
+        socket: SocketArgs,
     },
 
     /// Get session status
     Status {
-        /// Unix socket path (required)
-        #[arg(long, required = true)]
-        socket: String,
+        #[command(flatten)]
+        socket: SocketArgs,
 
         /// Quiet mode: just exit status (0 if running, 1 if exited)
         #[arg(long)]
@@ -133,42 +328,187 @@ enum Commands {
 
     /// Wait until session exits or activity occurs
     Wait {
-        /// Unix socket path (required)
-        #[arg(long, required = true)]
-        socket: String,
+        #[command(flatten)]
+        socket: SocketArgs,
 
         /// Quiet mode: wait for exit only, print exit code
         #[arg(long)]
         quiet: bool,
+
+        /// Wait until there has been no output for this many milliseconds
+        #[arg(long, conflicts_with = "quiet")]
+        settle: Option<u64>,
     },
 
     /// Send signal to running process
     Kill {
-        /// Unix socket path (required)
-        #[arg(long, required = true)]
-        socket: String,
+        #[command(flatten)]
+        socket: SocketArgs,
 
         /// Signal to send (named like SIGTERM, SIGKILL, SIGINT or numeric like 9, 15, 2)
         #[arg(long, default_value = "SIGTERM")]
         signal: String,
     },
 
+    /// Click, drag or scroll with the mouse, in applications that turned on
+    /// mouse reporting
+    Mouse {
+        #[command(flatten)]
+        socket: SocketArgs,
+
+        /// Where to click, start dragging or scroll (1-based, as output and
+        /// expect print positions)
+        #[arg(long, value_name = "ROW,COL")]
+        click: String,
+
+        #[arg(long, default_value = "left", value_parser = ["left", "middle", "right"])]
+        button: String,
+
+        /// Drag with the button held from --click to here
+        #[arg(long, value_name = "ROW,COL", conflicts_with = "scroll")]
+        drag_to: Option<String>,
+
+        /// Turn the wheel at --click instead of clicking
+        #[arg(long, value_parser = ["up", "down"])]
+        scroll: Option<String>,
+    },
+
     /// Resize the terminal
     Resize {
-        /// Unix socket path (required)
-        #[arg(long, required = true)]
-        socket: String,
+        #[command(flatten)]
+        socket: SocketArgs,
 
         /// New terminal size (e.g., 120x40)
         #[arg(long)]
         size: String,
     },
 
+    /// Wait until the screen or output matches one of the given patterns
+    /// (gives up after 10 seconds unless --timeout says otherwise)
+    Expect {
+        #[command(flatten)]
+        socket: SocketArgs,
+
+        /// Regular expressions to wait for (the first one listed that matches wins)
+        #[arg(required = true)]
+        patterns: Vec<String>,
+
+        /// What to match against: the rendered screen or the raw output stream
+        #[arg(long, default_value = "screen", value_parser = ["screen", "output"])]
+        target: String,
+
+        /// Print the screen at the time of the match
+        #[arg(long)]
+        print_screen: bool,
+
+        /// Output stream offset to match from, for --target output (default:
+        /// just past this client's last match; 0 is the oldest output kept)
+        #[arg(long)]
+        offset: Option<u64>,
+    },
+
+    /// Print raw output from the session, starting at a byte offset
+    Read {
+        #[command(flatten)]
+        socket: SocketArgs,
+
+        /// Output stream offset to start from (0 is the oldest output still kept)
+        #[arg(long, default_value = "0")]
+        offset: u64,
+
+        /// Print output as received (raw) or with escape sequences stripped (text)
+        #[arg(long, default_value = "text", value_parser = ["text", "raw"])]
+        format: String,
+
+        /// Stop after this many bytes of output
+        #[arg(long)]
+        max_bytes: Option<u64>,
+
+        /// Keep printing new output as it arrives, until the application exits
+        #[arg(long)]
+        follow: bool,
+    },
+
+    /// Save a PNG screenshot of the screen
+    Screenshot {
+        #[command(flatten)]
+        socket: SocketArgs,
+
+        /// File to write the PNG image to ("-" for stdout)
+        #[arg(short, long, value_name = "FILE")]
+        output: String,
+    },
+
+    /// Print session events (output, screen changes, exit, ...) as JSON lines
+    Events {
+        #[command(flatten)]
+        socket: SocketArgs,
+    },
+
+    /// Replay an output dump or recording into an emulator and print the screen (no session needed)
+    Render {
+        /// Raw output (e.g. from --pty-dump), asciicast recording, or script log (with FILE.timing)
+        file: String,
+
+        /// Terminal emulator backend (xterm or custom)
+        #[arg(long, value_enum, default_value = "xterm")]
+        emulator: Emulator,
+
+        /// Terminal size (e.g., 80x24); defaults to the recording's size, or 80x24
+        #[arg(long)]
+        size: Option<String>,
+
+        /// Output format: ansi, ascii, or json (screen, cursor, size, offset and time)
+        #[arg(long, default_value = "ansi", value_parser = ["ansi", "ascii", "json"])]
+        format: String,
+
+        /// Stop after this many bytes of output
+        #[arg(long, value_name = "BYTES")]
+        at_offset: Option<u64>,
+
+        /// Stop at this many seconds into the recording (recordings with timing only)
+        #[arg(long, value_name = "SECONDS", conflicts_with = "at_offset")]
+        at_time: Option<f64>,
+    },
+
+    /// Play a VHS-style .tape script against a new session
+    Play {
+        /// Tape file (Type, Enter, Ctrl+X, Sleep, Wait, Screenshot, Set Width/Height, ...)
+        file: String,
+
+        /// Terminal emulator backend (xterm or custom)
+        #[arg(long, value_enum, default_value = "xterm")]
+        emulator: Emulator,
+    },
+
+    /// Run several requests in a row, with no other client's requests in between
+    Batch {
+        #[command(flatten)]
+        socket: SocketArgs,
+
+        /// Skip the remaining requests once one fails
+        #[arg(long)]
+        stop_on_error: bool,
+
+        /// JSON file with the requests, as an array or one per line (default: stdin)
+        file: Option<String>,
+    },
+
+    /// Show the daemon's protocol version and what it supports
+    Hello {
+        #[command(flatten)]
+        socket: SocketArgs,
+
+        /// Fail unless the daemon supports this request type (e.g. RESIZE) or
+        /// format (e.g. OUTPUT:ansi). Can be given more than once
+        #[arg(long, value_name = "FEATURE")]
+        require: Vec<String>,
+    },
+
     /// Show unhandled escape sequences (for debugging)
     Debug {
-        /// Unix socket path (required)
-        #[arg(long, required = true)]
-        socket: String,
+        #[command(flatten)]
+        socket: SocketArgs,
 
         /// Clear the buffer after reading
         #[arg(long)]
@@ -176,61 +516,116 @@ enum Commands {
     },
 }
 
-// Protocol messages
-#[derive(Deserialize)]
-struct Request {
-    #[serde(rename = "type")]
-    req_type: String,
-    #[serde(flatten)]
-    data: serde_json::Value,
+// Terminal emulator factory
+fn create_terminal(rows: usize, cols: usize, emulator: Emulator, scrollback: usize) -> Box<dyn TerminalEmulator> {
+    match emulator {
+        Emulator::Xterm => Box::new(alacritty_backend::AlacrittyTerminal::new(rows, cols, scrollback)),
+        Emulator::Custom => Box::new(custom_screen::CustomScreen::new(rows, cols, scrollback)),
+    }
 }
 
-#[derive(Serialize, Deserialize)]
-struct Response {
-    status: String,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    data: Option<serde_json::Value>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    error: Option<String>,
+/// How client commands find a session's socket
+#[derive(clap::Args)]
+struct SocketArgs {
+    /// Unix socket path (default: $INTERMINAI_SOCKET)
+    #[arg(long = "socket", value_name = "PATH")]
+    path: Option<String>,
+
+    /// Name of a session started with `start --name`
+    #[arg(long, conflicts_with = "path")]
+    name: Option<String>,
 }
 
-impl Response {
-    fn ok(data: serde_json::Value) -> Self {
-        Response {
-            status: "ok".to_string(),
-            data: Some(data),
-            error: None,
+impl SocketArgs {
+    fn path(self) -> Result<String> {
+        if let Some(path) = self.path {
+            return Ok(path);
         }
-    }
-
-    fn error(msg: String) -> Self {
-        Response {
-            status: "error".to_string(),
-            data: None,
-            error: Some(msg),
+        if let Some(name) = self.name {
+            return Ok(sessions::named_socket_path(&name)?.to_string_lossy().to_string());
+        }
+        match std::env::var("INTERMINAI_SOCKET") {
+            Ok(path) if !path.is_empty() => Ok(path),
+            _ => bail!("No session given: use --socket PATH or --name NAME, or set INTERMINAI_SOCKET"),
         }
     }
 }
 
-// Terminal emulator factory
-fn create_terminal(rows: usize, cols: usize, emulator: Emulator) -> Box<dyn TerminalEmulator> {
-    match emulator {
-        Emulator::Xterm => Box::new(alacritty_backend::AlacrittyTerminal::new(rows, cols)),
-        Emulator::Custom => Box::new(custom_screen::CustomScreen::new(rows, cols)),
+/// Who may connect to a daemon, given to `start` and `server`
+#[derive(clap::Args)]
+struct AccessArgs {
+    /// Also allow this user to connect (the daemon's own user and root always can)
+    #[arg(long = "allow-uid", value_name = "UID")]
+    allow_uid: Vec<u32>,
+
+    /// Log rejected connections to this file (default: stderr)
+    #[arg(long, value_name = "FILE")]
+    log: Option<String>,
+}
+
+impl AccessArgs {
+    fn access(self, token: Option<String>) -> Result<Access> {
+        Access::new(&self.allow_uid, token, self.log.as_deref().map(Path::new))
     }
 }
 
+/// Session settings given to `start`
+struct DaemonConfig {
+    rows: u16,
+    cols: u16,
+    emulator: Emulator,
+    scrollback: usize,
+    output_buffer: usize,
+    pty_dump: Option<String>,
+    record: Option<RecordConfig>,
+    /// Environment variables to set for the application
+    env: Vec<(String, String)>,
+}
+
 struct DaemonState {
     master_fd: OwnedFd,
     child_pid: Pid,
+    /// Command line of the application
+    command: String,
     terminal: Box<dyn TerminalEmulator>,
+    /// Backend behind `terminal`
+    emulator: Emulator,
+    /// Lines of scrollback history `terminal` keeps
+    scrollback: usize,
     exit_code: Option<i32>,
-    socket_path: String,
-    socket_was_auto_generated: bool,
     should_shutdown: bool,
     pty_dump: Option<std::fs::File>,
+    /// Session recording, if enabled
+    recorder: Option<Recorder>,
     /// Activity flag: set when PTY output is received
     activity: bool,
+    /// Recent raw PTY output, for matching against the output stream
+    output_log: OutputLog,
+    /// Clients receiving the event stream
+    subscribers: Subscribers,
+    /// Incremented every time the rendered screen changes
+    screen_generation: u64,
+    /// Generation in which each screen row last changed
+    row_generations: Vec<u64>,
+    /// Last seen screen rows (with colors), cursor and termios mode, to detect changes
+    last_rows: Vec<String>,
+    last_cursor: (usize, usize),
+    last_mode: Option<(&'static str, bool)>,
+    /// Woken whenever output arrives, the application exits or the session stops
+    changed: Arc<Notify>,
+    /// Notified once when the session is stopped, to stop accepting connections
+    stopped: Arc<Notify>,
+    /// Held while writing input, so inputs from different clients can't interleave
+    writing: Arc<tokio::sync::Mutex<()>>,
+}
+
+/// Input for the application, ready to be written
+struct Input {
+    bytes: Vec<u8>,
+    /// Mask it in recordings
+    secret: bool,
+    /// Response data once it is written
+    response: serde_json::Value,
 }
 
 impl DaemonState {
@@ -248,20 +643,38 @@ impl DaemonState {
             }
             _ => {}
         }
+
+        if let Some(exit_code) = self.exit_code {
+            self.subscribers.emit(Event::Exit { exit_code });
+            self.changed.notify_waiters();
+        }
+    }
+
+    fn input_written(&mut self, input: Input) -> Response {
+        if let Some(recorder) = self.recorder.as_mut() {
+            recorder.input(&input.bytes, input.secret);
+        }
+        Response::ok(input.response)
     }
 
     fn read_pty_output(&mut self) {
         let mut buf = [0u8; 4096];
+        let mut total = 0;
         loop {
             match nix::unistd::read(self.master_fd.as_raw_fd(), &mut buf) {
                 Ok(0) => break,
                 Ok(n) => {
                     // Any output from PTY is activity
                     self.activity = true;
+                    total += n;
+                    self.output_log.push(&buf[..n]);
                     // Dump raw bytes if pty_dump is enabled
                     if let Some(ref mut dump) = self.pty_dump {
                         let _ = dump.write_all(&buf[..n]);
                     }
+                    if let Some(recorder) = self.recorder.as_mut() {
+                        recorder.output(&buf[..n]);
+                    }
                     self.terminal.process_bytes(&buf[..n]);
                 }
                 Err(_) => break,
@@ -272,8 +685,76 @@ impl DaemonState {
         for response in self.terminal.take_pending_responses() {
             let _ = nix::unistd::write(self.master_fd.as_raw_fd(), &response);
         }
+
+        if total > 0 {
+            let offset = self.output_log.end_offset();
+            self.subscribers.emit(Event::Output { bytes: total, offset });
+            self.note_changes();
+            self.changed.notify_waiters();
+        }
+    }
+
+    /// Bump the screen generation and notify subscribers of anything that
+    /// changed since the last call
+    fn note_changes(&mut self) {
+        let (rows, _) = self.terminal.dimensions();
+        let screen = screen_rows(&self.terminal.get_screen_content_ansi(), rows);
+        if screen != self.last_rows {
+            self.screen_generation += 1;
+            let generation = self.screen_generation;
+            self.row_generations.resize(rows, generation);
+            for (row, text) in screen.iter().enumerate() {
+                if self.last_rows.get(row) != Some(text) {
+                    self.row_generations[row] = generation;
+                }
+            }
+            self.last_rows = screen;
+            self.subscribers.emit(Event::ScreenChanged { generation });
+        }
+
+        let cursor = self.terminal.cursor_position();
+        if cursor != self.last_cursor {
+            self.last_cursor = cursor;
+            self.subscribers.emit(Event::CursorMoved { row: cursor.0, col: cursor.1 });
+        }
+
+        // Applications change the mode right before drawing, so checking
+        // after output is enough to catch it
+        let mode = termios_mode(&self.master_fd);
+        if mode.is_some() && mode != self.last_mode {
+            self.last_mode = mode;
+            if let Some((mode, echo)) = mode {
+                self.subscribers.emit(Event::ModeChanged { mode, echo });
+            }
+        }
     }
+}
 
+/// Split screen text into exactly `rows` rows
+fn screen_rows(screen: &str, rows: usize) -> Vec<String> {
+    let mut lines: Vec<String> = screen.lines().map(String::from).collect();
+    lines.resize(rows, String::new());
+    lines
+}
+
+/// Current PTY mode: "raw" or "cooked", and whether echo is on
+fn termios_mode(fd: &OwnedFd) -> Option<(&'static str, bool)> {
+    let termios = tcgetattr(fd).ok()?;
+    let mode = if termios.local_flags.contains(LocalFlags::ICANON) { "cooked" } else { "raw" };
+    Some((mode, termios.local_flags.contains(LocalFlags::ECHO)))
+}
+
+/// Parse a 1-based ROW,COL into a 0-based (row, col)
+fn parse_position(position: &str) -> Result<(usize, usize)> {
+    let Some((row, col)) = position.split_once(',') else {
+        bail!("Invalid position '{}', expected ROW,COL like 5,12", position);
+    };
+    let row: usize = row.trim().parse().context("Invalid row")?;
+    let col: usize = col.trim().parse().context("Invalid column")?;
+    if row == 0 || col == 0 {
+        bail!("Rows and columns count from 1");
+    }
+    Ok((row - 1, col - 1))
 }
 
 fn parse_terminal_size(size: &str) -> Result<(u16, u16)> {
@@ -358,7 +839,7 @@ fn parse_signal(sig: &str) -> Result<Signal> {
 fn auto_generate_socket_path() -> Result<String> {
     // Create a temporary directory with proper prefix
     let temp_dir = Builder::new()
-        .prefix("interminai-")
+        .prefix(sessions::TEMP_PREFIX)
         .tempdir()?;
 
     // Get the path and keep it persistent (leak the TempDir)
@@ -370,24 +851,59 @@ fn auto_generate_socket_path() -> Result<String> {
     Ok(socket_path)
 }
 
-fn cmd_start(socket: Option<String>, size: String, emulator: Emulator, daemon: bool, pty_dump: Option<String>, command: Vec<String>) -> Result<()> {
-    let socket_was_auto_generated = socket.is_none();
-    let socket_path = match socket {
-        Some(path) => path,
-        None => auto_generate_socket_path()?,
-    };
+fn cmd_start(socket_path: String, socket_was_auto_generated: bool, named: bool, access: Access, config: DaemonConfig, started: Started, command: Vec<String>) -> Result<()> {
+    daemonize(&socket_path, socket_was_auto_generated, started, |ready| {
+        // Auto-generated and named sockets live in a directory of their own
+        run_daemon(&socket_path, socket_was_auto_generated || named, access, config, command, ready)
+    })
+}
 
-    let (cols, rows) = parse_terminal_size(&size)?;
+/// How `start` and `server` run the daemon and report it
+#[derive(Clone, Copy)]
+struct Started {
+    /// In the background, otherwise in the foreground
+    daemon: bool,
+    /// Report as JSON rather than `Name: value` lines
+    json: bool,
+}
 
-    if !daemon {
-        // Run in foreground (default for now)
+/// Tells the process that started the daemon that it is listening.
+/// In the foreground there is no one to tell.
+struct Ready(Option<std::io::PipeWriter>);
+
+impl Ready {
+    /// Report the daemon's PID, once its socket accepts connections
+    fn listening(&mut self) {
+        if let Some(mut pipe) = self.0.take() {
+            let _ = writeln!(pipe, "{}", std::process::id());
+        }
+    }
+}
+
+fn print_started(socket_path: &str, pid: u32, socket_was_auto_generated: bool, json: bool) {
+    if json {
+        println!("{}", serde_json::json!({"socket": socket_path, "pid": pid, "auto_generated": socket_was_auto_generated}));
+    } else {
         println!("Socket: {}", socket_path);
-        println!("PID: {}", std::process::id());
+        println!("PID: {}", pid);
         println!("Auto-generated: {}", socket_was_auto_generated);
+    }
+}
+
+/// Print where the daemon listens, then run it: in the background if
+/// `started.daemon`, otherwise in the foreground. In the background this
+/// returns once the daemon is listening, or with the error it failed with.
+fn daemonize(socket_path: &str, socket_was_auto_generated: bool, started: Started, run: impl FnOnce(&mut Ready) -> Result<()>) -> Result<()> {
+    if !started.daemon {
+        // Run in foreground (default for now)
+        print_started(socket_path, std::process::id(), socket_was_auto_generated, started.json);
 
-        return run_daemon(socket_path, socket_was_auto_generated, rows, cols, emulator, pty_dump, command);
+        return run(&mut Ready(None));
     }
 
+    // The daemon writes its PID once listening, or why it failed
+    let (mut reader, writer) = std::io::pipe()?;
+
     // Double-fork to properly daemonize
     // Use fork crate which provides a safe wrapper around libc::fork()
     use fork::{fork as safe_fork, Fork};
@@ -399,18 +915,23 @@ fn cmd_start(socket: Option<String>, size: String, emulator: Emulator, daemon: b
             use nix::unistd::Pid;
             let _ = waitpid(Pid::from_raw(child), None);
 
-            // The intermediate child has printed the grandchild PID to stdout
-            // Now print the rest of the info
-            println!("Socket: {}", socket_path);
-            println!("Auto-generated: {}", socket_was_auto_generated);
-            Ok(())
+            // Only the daemon's copy of the pipe is left open now
+            drop(writer);
+            let mut report = String::new();
+            reader.read_to_string(&mut report)?;
+            match report.trim_end().parse() {
+                Ok(pid) => {
+                    print_started(socket_path, pid, socket_was_auto_generated, started.json);
+                    Ok(())
+                }
+                Err(_) if report.is_empty() => bail!("Daemon exited before listening"),
+                Err(_) => bail!("{}", report.trim_end()),
+            }
         }
         Ok(Fork::Child) => {
-            // Intermediate child: fork again, print grandchild PID, and exit
+            // Intermediate child: fork again and exit
             match safe_fork() {
-                Ok(Fork::Parent(grandchild_pid)) => {
-                    // Intermediate parent: print grandchild PID to stdout and exit
-                    println!("PID: {}", grandchild_pid);
+                Ok(Fork::Parent(_)) => {
                     std::process::exit(0);
                 }
                 Ok(Fork::Child) => {
@@ -448,8 +969,12 @@ fn cmd_start(socket: Option<String>, size: String, emulator: Emulator, daemon: b
                     }
 
                     // Run daemon
-                    if let Err(e) = run_daemon(socket_path, socket_was_auto_generated, rows, cols, emulator, pty_dump, command) {
-                        // Daemon errors go to /dev/null in daemon mode, which is fine
+                    let mut ready = Ready(Some(writer));
+                    if let Err(e) = run(&mut ready) {
+                        // Errors before listening go to `start`, later ones to /dev/null
+                        if let Some(mut pipe) = ready.0.take() {
+                            let _ = write!(pipe, "{:#}", e);
+                        }
                         eprintln!("Daemon error: {}", e);
                         std::process::exit(1);
                     }
@@ -467,7 +992,87 @@ fn cmd_start(socket: Option<String>, size: String, emulator: Emulator, daemon: b
     }
 }
 
-fn run_daemon(socket_path: String, socket_was_auto_generated: bool, rows: u16, cols: u16, emulator: Emulator, pty_dump: Option<String>, command: Vec<String>) -> Result<()> {
+fn run_daemon(socket_path: &str, owns_socket_dir: bool, access: Access, config: DaemonConfig, command: Vec<String>, ready: &mut Ready) -> Result<()> {
+    let runtime = tokio::runtime::Runtime::new()?;
+    runtime.block_on(async {
+        let state = spawn_session(config, command)?;
+        let stopped = state.lock().unwrap().stopped.clone();
+        serve(socket_path, access, stopped, ready, move |stream, access| handle_client(stream, access, state.clone())).await
+    })?;
+    // Don't hold up the exit for a BATCH that is still running
+    runtime.shutdown_background();
+
+    // Cleanup
+    if owns_socket_dir {
+        remove_socket(socket_path);
+    }
+
+    Ok(())
+}
+
+/// Remove a socket and the directory it lives in
+fn remove_socket(socket_path: &str) {
+    let _ = fs::remove_file(socket_path);
+    if let Some(parent) = Path::new(socket_path).parent() {
+        let _ = fs::remove_dir(parent);
+    }
+}
+
+/// Accept connections on `socket_path` until `stopped` is notified.
+/// Each connection `access` allows is served by `handle` in a task of its own.
+async fn serve<F, Fut>(socket_path: &str, access: Access, stopped: Arc<Notify>, ready: &mut Ready, handle: F) -> Result<()>
+where
+    F: Fn(UnixStream, Arc<Access>) -> Fut,
+    Fut: Future<Output = Result<()>> + Send + 'static,
+{
+    // Create socket and listen
+    let _ = fs::remove_file(socket_path); // Clean up if exists
+    let listener = access.bind(socket_path)?;
+    let access = Arc::new(access);
+    ready.listening();
+
+    // Accept connections
+    loop {
+        let stream = tokio::select! {
+            accepted = listener.accept() => match accepted {
+                Ok((stream, _)) => stream,
+                Err(e) => {
+                    eprintln!("Connection error: {}", e);
+                    continue;
+                }
+            },
+            _ = stopped.notified() => break,
+        };
+        if !access.check_peer(&stream) {
+            continue;
+        }
+
+        // Serve each connection in a task of its own so a pending WAIT or a
+        // slow client can't stall everyone else. Requests on a single
+        // connection are still handled in order.
+        let connection = handle(stream, access.clone());
+        tokio::spawn(async move {
+            if let Err(e) = connection.await {
+                eprintln!("Client handler error: {}", e);
+            }
+        });
+    }
+
+    // Give time for final requests to complete
+    tokio::time::sleep(Duration::from_millis(200)).await;
+
+    Ok(())
+}
+
+/// Run `command` on a new PTY and keep its screen up to date in the
+/// returned state until it exits or the session is stopped
+fn spawn_session(config: DaemonConfig, command: Vec<String>) -> Result<Arc<Mutex<DaemonState>>> {
+    let DaemonConfig { rows, cols, emulator, scrollback, output_buffer, pty_dump, record, env } = config;
+
+    if command.is_empty() {
+        bail!("No command given");
+    }
+
     // Create PTY
     let winsize = Winsize {
         ws_row: rows,
@@ -478,262 +1083,577 @@ fn run_daemon(socket_path: String, socket_was_auto_generated: bool, rows: u16, c
 
     let pty = openpty(Some(&winsize), None)?;
 
-    // Fork to spawn child in PTY
-    // Use fork crate which provides a safe wrapper around libc::fork()
-    use fork::{fork as safe_fork, Fork};
+    // Set master to non-blocking and keep it (and the slave) out of other
+    // sessions' children. Use nix's safe fcntl wrapper (requires 'fs' feature)
+    use nix::fcntl::{fcntl, FcntlArg, FdFlag, OFlag};
+
+    let flags = fcntl(pty.master.as_raw_fd(), FcntlArg::F_GETFL)
+        .context("Failed to get PTY flags")?;
+    let mut oflags = OFlag::from_bits_truncate(flags);
+    oflags.insert(OFlag::O_NONBLOCK);
+    fcntl(pty.master.as_raw_fd(), FcntlArg::F_SETFL(oflags))
+        .context("Failed to set PTY non-blocking")?;
+    fcntl(pty.master.as_raw_fd(), FcntlArg::F_SETFD(FdFlag::FD_CLOEXEC))
+        .context("Failed to set PTY close-on-exec")?;
+    fcntl(pty.slave.as_raw_fd(), FcntlArg::F_SETFD(FdFlag::FD_CLOEXEC))
+        .context("Failed to set PTY close-on-exec")?;
+
+    // Open PTY dump file if specified
+    let pty_dump_file = match &pty_dump {
+        Some(path) => Some(std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .context("Failed to open PTY dump file")?),
+        None => None,
+    };
 
-    match safe_fork() {
-        Ok(Fork::Parent(child)) => {
-            // Close slave side in parent by dropping it (not using close() to avoid double-close)
-            drop(pty.slave);
-
-            // Set master to non-blocking
-            // Use nix's safe fcntl wrapper (requires 'fs' feature)
-            use nix::fcntl::{fcntl, FcntlArg, OFlag};
-
-            let flags = fcntl(pty.master.as_raw_fd(), FcntlArg::F_GETFL)
-                .context("Failed to get PTY flags")?;
-            let mut oflags = OFlag::from_bits_truncate(flags);
-            oflags.insert(OFlag::O_NONBLOCK);
-            fcntl(pty.master.as_raw_fd(), FcntlArg::F_SETFL(oflags))
-                .context("Failed to set PTY non-blocking")?;
-
-            // Open PTY dump file if specified
-            let pty_dump_file = match &pty_dump {
-                Some(path) => Some(std::fs::OpenOptions::new()
-                    .create(true)
-                    .append(true)
-                    .open(path)
-                    .context("Failed to open PTY dump file")?),
-                None => None,
-            };
+    let recorder = match record {
+        Some(record) => Some(Recorder::create(record, rows as usize, cols as usize)?),
+        None => None,
+    };
 
-            // Create state
-            let state = Arc::new(Mutex::new(DaemonState {
-                master_fd: pty.master,
-                child_pid: Pid::from_raw(child),
-                terminal: create_terminal(rows as usize, cols as usize, emulator),
-                exit_code: None,
-                socket_path: socket_path.clone(),
-                socket_was_auto_generated,
-                should_shutdown: false,
-                pty_dump: pty_dump_file,
-                activity: false,
-            }));
+    let child = spawn_in_pty(pty.slave, emulator, &env, &command)
+        .with_context(|| format!("Failed to run {}", command[0]))?;
+
+    // Create state
+    let last_mode = termios_mode(&pty.master);
+    let state = Arc::new(Mutex::new(DaemonState {
+        master_fd: pty.master,
+        child_pid: Pid::from_raw(child.id() as i32),
+        command: command.join(" "),
+        terminal: create_terminal(rows as usize, cols as usize, emulator, scrollback),
+        emulator,
+        scrollback,
+        exit_code: None,
+        should_shutdown: false,
+        pty_dump: pty_dump_file,
+        recorder,
+        activity: false,
+        output_log: OutputLog::new(output_buffer),
+        subscribers: Subscribers::default(),
+        screen_generation: 0,
+        row_generations: vec![0; rows as usize],
+        last_rows: vec![String::new(); rows as usize],
+        last_cursor: (0, 0),
+        last_mode,
+        changed: Arc::new(Notify::new()),
+        stopped: Arc::new(Notify::new()),
+        writing: Arc::new(tokio::sync::Mutex::new(())),
+    }));
+
+    // Take in output as it arrives, on a dup of the fd the reader owns.
+    // Exits are noticed on SIGCHLD; listen before the reader first checks
+    // on the child so none is missed.
+    let pty = AsyncFd::new(rustix::io::fcntl_dupfd_cloexec(&state.lock().unwrap().master_fd, 0)?)?;
+    let child_exits = signal(SignalKind::child())?;
+    tokio::spawn(read_pty(state.clone(), pty, child_exits));
+
+    Ok(state)
+}
 
-            // Start PTY reader thread - use poll() for efficient event-driven I/O
-            let state_clone = state.clone();
-            // Dup the fd so the thread owns its own copy for polling
-            let poll_fd = rustix::io::dup(&state.lock().unwrap().master_fd)?;
-            thread::spawn(move || {
-                use rustix::event::{poll, PollFd, PollFlags};
-                let mut pty_closed = false;
-                loop {
-                    if pty_closed {
-                        // PTY closed but child may still be running - poll child status only
-                        let mut state = state_clone.lock().unwrap();
-                        state.check_child_status();
-                        if state.exit_code.is_some() {
-                            break;
-                        }
-                        drop(state);
-                        thread::sleep(Duration::from_millis(100));
-                        continue;
-                    }
+/// Feed the application's output to the session as it arrives and notice it
+/// exiting, until it has or the session is stopped
+async fn read_pty(state: Arc<Mutex<DaemonState>>, pty: AsyncFd<OwnedFd>, mut child_exits: tokio::signal::unix::Signal) {
+    let changed = state.lock().unwrap().changed.clone();
+    let mut pty_open = true;
+    loop {
+        let notified = changed.notified();
+        tokio::pin!(notified);
+        notified.as_mut().enable();
 
-                    // Wait for PTY events using poll()
-                    let mut poll_fds = [PollFd::new(&poll_fd, PollFlags::IN | PollFlags::HUP)];
-                    if poll(&mut poll_fds, None).is_err() {
-                        break;
-                    }
+        {
+            let mut state = state.lock().unwrap();
+            state.check_child_status();
+            if state.exit_code.is_some() || state.should_shutdown {
+                break;
+            }
+        }
 
-                    let mut state = state_clone.lock().unwrap();
-                    let revents = poll_fds[0].revents();
-                    if revents.contains(PollFlags::IN) {
-                        state.read_pty_output();
-                    }
-                    if revents.intersects(PollFlags::HUP | PollFlags::ERR) {
-                        state.read_pty_output();
-                        pty_closed = true;
-                    }
-                    state.check_child_status();
-                    if state.exit_code.is_some() {
-                        break;
-                    }
+        tokio::select! {
+            ready = pty.readable(), if pty_open => {
+                let Ok(mut ready) = ready else {
+                    pty_open = false;
+                    continue;
+                };
+                // Hangup: the PTY is closed, but the child may still be running
+                if ready.ready().is_read_closed() {
+                    pty_open = false;
                 }
-            });
+                state.lock().unwrap().read_pty_output();
+                ready.clear_ready();
+            }
+            _ = child_exits.recv() => {}
+            // Stopped
+            _ = &mut notified => {}
+        }
+    }
+}
 
-            // Create socket and listen
-            let _ = fs::remove_file(&socket_path); // Clean up if exists
-            let listener = UnixListener::bind(&socket_path)?;
+/// Start `command` with the PTY slave as its terminal. The slave is closed
+/// in the parent once the child has it.
+fn spawn_in_pty(slave: OwnedFd, emulator: Emulator, env: &[(String, String)], command: &[String]) -> std::io::Result<std::process::Child> {
+    use std::process::Stdio;
+
+    let mut child = ProcessCommand::new(&command[0]);
+    child
+        .args(&command[1..])
+        // TERM matches the terminal emulator backend
+        .env("TERM", emulator.term())
+        .envs(env.iter().map(|(name, value)| (name, value)))
+        .stdin(Stdio::from(slave.try_clone()?))
+        .stdout(Stdio::from(slave.try_clone()?))
+        .stderr(Stdio::from(slave));
+
+    // SAFETY: the daemon is multithreaded, so between fork and exec the child
+    // may only make async-signal-safe calls. These are plain system calls that
+    // neither allocate nor take locks.
+    unsafe {
+        child.pre_exec(|| {
+            // A new session, so the PTY slave (already on stdin/stdout/stderr)
+            // can become its controlling terminal
+            rustix::process::setsid()?;
+            // Non-fatal - the application just won't get terminal signals
+            let _ = rustix::process::ioctl_tiocsctty(std::os::fd::BorrowedFd::borrow_raw(0));
+            Ok(())
+        });
+    }
+    child.spawn()
+}
 
-            // Set socket to non-blocking so we can check shutdown flag
-            listener.set_nonblocking(true)?;
+async fn handle_client(stream: UnixStream, access: Arc<Access>, state: Arc<Mutex<DaemonState>>) -> Result<()> {
+    let mut connection = Connection::new(stream);
+    while let Some(request) = connection.next_request(&access).await? {
+        if !handle_request(request, &state, connection.stream()).await? {
+            break;
+        }
+    }
+    Ok(())
+}
 
-            // Accept connections
-            loop {
-                // Check if we should exit
-                {
-                    let state_locked = state.lock().unwrap();
-                    if state_locked.should_shutdown {
-                        break;
-                    }
-                }
+/// A client's connection, carrying newline-delimited requests
+struct Connection {
+    reader: tokio::io::BufReader<UnixStream>,
+}
 
-                match listener.accept() {
-                    Ok((stream, _)) => {
-                        // Process commands sequentially - no parallelism
-                        if let Err(e) = handle_client(stream, state.clone()) {
-                            eprintln!("Client handler error: {}", e);
-                        }
-                    }
-                    Err(ref e) if e.kind() == std::io::ErrorKind::WouldBlock => {
-                        // No connection available, sleep and try again
-                        thread::sleep(Duration::from_millis(50));
-                    }
-                    Err(e) => {
-                        eprintln!("Connection error: {}", e);
-                    }
-                }
+impl Connection {
+    fn new(stream: UnixStream) -> Self {
+        Connection { reader: tokio::io::BufReader::new(stream) }
+    }
+
+    /// The socket, for writing responses
+    fn stream(&mut self) -> &mut UnixStream {
+        self.reader.get_mut()
+    }
+
+    /// Read the next request that `access` allows. Returns None once the client
+    /// closes the connection (one-shot clients simply disconnect after reading
+    /// their response) or can't be served any further.
+    async fn next_request(&mut self, access: &Access) -> Result<Option<Request>> {
+        loop {
+            let mut line = String::new();
+            let bytes_read = self.reader.read_line(&mut line).await?;
+            if bytes_read == 0 {
+                // EOF - client disconnected
+                return Ok(None);
             }
 
-            // Give time for final requests to complete
-            thread::sleep(Duration::from_millis(200));
+            // Tolerate blank lines between requests
+            if line.trim().is_empty() {
+                continue;
+            }
 
-            // Cleanup
-            let state_locked = state.lock().unwrap();
-            if state_locked.socket_was_auto_generated {
-                let _ = fs::remove_file(&state_locked.socket_path);
-                // Also remove the parent directory (the temp dir)
-                if let Some(parent) = Path::new(&state_locked.socket_path).parent() {
-                    let _ = fs::remove_dir(parent);
+            let request: Request = match serde_json::from_str(&line) {
+                Ok(req) => req,
+                Err(e) => {
+                    // Can't trust the framing after garbage - answer and hang up
+                    let response = Response::error(format!("Invalid JSON: {}", e));
+                    write_response(self.stream(), &response).await?;
+                    return Ok(None);
                 }
+            };
+
+            if !access.check_token(&request.data, self.reader.get_ref()) {
+                let mut response = Response::error("Invalid or missing token".to_string());
+                response.id = request.id;
+                write_response(self.stream(), &response).await?;
+                return Ok(None);
             }
 
-            Ok(())
+            return Ok(Some(request));
         }
-        Ok(Fork::Child) => {
-            // Close master side in child by dropping it
-            drop(pty.master);
-
-            // Create new session - this makes the child a session leader
-            // This is required for the PTY slave to become the controlling terminal
-            setsid().context("Failed to create new session")?;
-
-            // Redirect stdin/stdout/stderr to slave using nix
-            use nix::unistd::dup2;
-            let slave_fd = pty.slave.as_raw_fd();
-            dup2(slave_fd, 0).context("Failed to dup2 stdin")?;
-            dup2(slave_fd, 1).context("Failed to dup2 stdout")?;
-            dup2(slave_fd, 2).context("Failed to dup2 stderr")?;
-
-            // Make the PTY slave the controlling terminal for this session
-            // TIOCSCTTY = "set controlling tty" - this must be done AFTER setsid()
-            // and AFTER making stdin/stdout/stderr point to the slave
-            if let Err(e) = rustix::process::ioctl_tiocsctty(&pty.slave) {
-                // Non-fatal - continue anyway
-                eprintln!("Warning: Failed to set controlling terminal: {}", e);
-            }
+    }
+}
 
-            // Drop slave after dup2 (automatically closes it)
-            drop(pty.slave);
+/// Answer one request about a session. Returns whether to keep serving the
+/// connection.
+async fn handle_request(request: Request, state: &Arc<Mutex<DaemonState>>, stream: &mut UnixStream) -> Result<bool> {
+    let Request { req_type, id, data } = request;
 
-            // Set TERM based on the terminal emulator backend
-            // xterm (alacritty) supports full xterm-256color capabilities
-            // custom uses basic ANSI escape sequences
-            match emulator {
-                Emulator::Xterm => std::env::set_var("TERM", "xterm-256color"),
-                Emulator::Custom => std::env::set_var("TERM", "ansi"),
+    // The connection becomes an event stream from here on
+    if req_type == "SUBSCRIBE" {
+        handle_subscribe(id, state, stream).await?;
+        return Ok(false);
+    }
+
+    let mut response = match req_type.as_str() {
+        "WAIT" => handle_wait(data, state, stream).await,
+        "EXPECT" => handle_expect(data, state, stream).await,
+        "STOP" => handle_stop(state),
+        "INPUT" | "MOUSE" => {
+            let input = {
+                let mut state = state.lock().unwrap();
+                match req_type.as_str() {
+                    "INPUT" => prepare_input(data, &mut state),
+                    _ => prepare_mouse(data, &mut state),
+                }
+            };
+            match input {
+                Ok(input) => send_input(state, input).await,
+                Err(response) => response,
             }
+        }
+        "BATCH" => handle_batch(data, state, stream).await,
+        req_type => {
+            let mut state = state.lock().unwrap();
+            handle_locked(req_type, data, &mut state)
+        }
+    };
+    response.id = id;
 
-            // Exec command
-            let program = &command[0];
-            let args = &command[1..];
+    write_response(stream, &response).await?;
 
-            let _ = ProcessCommand::new(program)
-                .args(args)
-                .exec();
+    // The daemon is going away - don't wait for further requests
+    Ok(!state.lock().unwrap().should_shutdown)
+}
 
-            std::process::exit(1);
-        }
-        Err(e) => bail!("Failed to fork for child: {}", e),
+/// Answer a request that is done as soon as it has the session's state
+fn handle_locked(req_type: &str, data: serde_json::Value, state: &mut DaemonState) -> Response {
+    match req_type {
+        "HELLO" => handle_hello(state),
+        "OUTPUT" => handle_output(data, state),
+        "READ" => handle_read(data, state),
+        "STATUS" => handle_running(data, state),
+        "KILL" => handle_kill(data, state),
+        "RESIZE" => handle_resize(data, state),
+        "DEBUG" => handle_debug(data, state),
+        _ => Response::error(format!("Unknown command: {}", req_type)),
     }
 }
 
-fn handle_client(mut stream: UnixStream, state: Arc<Mutex<DaemonState>>) -> Result<()> {
-    let mut reader = BufReader::new(stream.try_clone()?);
-    let mut line = String::new();
+/// Version of the socket protocol, reported by HELLO. Bumped whenever
+/// requests or responses change in a way clients need to know about.
+const PROTOCOL_VERSION: u64 = 1;
+
+/// Requests a session answers
+const SESSION_REQUESTS: &[&str] = &[
+    "HELLO", "INPUT", "MOUSE", "OUTPUT", "READ", "STATUS", "WAIT", "EXPECT", "SUBSCRIBE", "KILL", "STOP", "RESIZE", "DEBUG", "BATCH",
+];
+
+/// What any daemon says about itself in answer to HELLO, given the requests it answers
+fn hello_data(server: bool, requests: &[&str]) -> serde_json::Value {
+    serde_json::json!({
+        "protocol": PROTOCOL_VERSION,
+        "daemon": env!("CARGO_PKG_NAME"),
+        "version": env!("CARGO_PKG_VERSION"),
+        "server": server,
+        "requests": requests,
+        "formats": {
+            "OUTPUT": ["ascii", "ansi", "cells", "spans", "html", "svg", "png"],
+            "READ": ["raw", "text"]
+        }
+    })
+}
 
-    // Read line, checking for EOF (client disconnected before sending complete request)
-    let bytes_read = reader.read_line(&mut line)?;
-    if bytes_read == 0 {
-        // EOF - client disconnected without sending complete request
-        return Ok(());
+/// Add what depends on the session (its backend and limits) to HELLO data
+fn add_session_capabilities(data: &mut serde_json::Value, state: &DaemonState) {
+    data["emulator"] = serde_json::json!(state.emulator.name());
+    // The custom backend renders every format without colors
+    data["colors"] = serde_json::json!(matches!(state.emulator, Emulator::Xterm));
+    data["limits"] = serde_json::json!({
+        "scrollback": state.scrollback,
+        "output_buffer": state.output_log.capacity(),
+        "read_max_bytes": READ_DEFAULT_MAX_BYTES,
+        "batch_max_wait_ms": BATCH_MAX_WAIT.as_millis() as u64
+    });
+}
+
+fn handle_hello(state: &DaemonState) -> Response {
+    let mut data = hello_data(false, SESSION_REQUESTS);
+    add_session_capabilities(&mut data, state);
+    Response::ok(data)
+}
+
+async fn write_response(stream: &mut UnixStream, response: &Response) -> Result<()> {
+    let json = serde_json::to_string(response)?;
+    stream.write_all(format!("{}\n", json).as_bytes()).await?;
+    stream.flush().await?;
+    Ok(())
+}
+
+/// Acknowledge the subscription, then stream events as JSON lines until the
+/// application exits, the client disconnects or the daemon shuts down
+async fn handle_subscribe(id: Option<serde_json::Value>, state: &Arc<Mutex<DaemonState>>, stream: &mut UnixStream) -> Result<()> {
+    let (mut events, generation, exit_code, changed) = {
+        let mut state = state.lock().unwrap();
+        state.check_child_status();
+        (state.subscribers.subscribe(), state.screen_generation, state.exit_code, state.changed.clone())
+    };
+
+    let mut response = Response::ok(serde_json::json!({
+        "generation": generation,
+        "running": exit_code.is_none()
+    }));
+    response.id = id;
+    write_response(stream, &response).await?;
+
+    async fn write_event(stream: &mut UnixStream, event: &Event) -> Result<()> {
+        let json = serde_json::to_string(event)?;
+        stream.write_all(format!("{}\n", json).as_bytes()).await?;
+        stream.flush().await?;
+        Ok(())
     }
 
-    let request: Request = match serde_json::from_str(&line) {
-        Ok(req) => req,
-        Err(e) => {
-            let response = Response::error(format!("Invalid JSON: {}", e));
-            write_response(&mut stream, &response)?;
+    // Nothing else is going to happen
+    if let Some(exit_code) = exit_code {
+        return write_event(stream, &Event::Exit { exit_code }).await;
+    }
+
+    loop {
+        let notified = changed.notified();
+        tokio::pin!(notified);
+        notified.as_mut().enable();
+        if state.lock().unwrap().should_shutdown {
             return Ok(());
         }
+
+        let event = tokio::select! {
+            event = events.recv() => event,
+            _ = client_gone(stream) => return Ok(()),
+            // Woken to notice the session being stopped
+            _ = &mut notified => continue,
+        };
+        match event {
+            Some(event) => {
+                write_event(stream, &event).await?;
+                if let Event::Exit { .. } = event {
+                    return Ok(());
+                }
+            }
+            // Dropped for falling behind
+            None => return Ok(()),
+        }
+    }
+}
+
+/// How long INPUT keeps writing to an application that doesn't read its input
+const INPUT_WRITE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Write `input` to the application, after any input another client is
+/// writing
+async fn send_input(state: &Arc<Mutex<DaemonState>>, input: Input) -> Response {
+    let writing = state.lock().unwrap().writing.clone();
+    let _writing = writing.lock().await;
+    write_input(state, input, INPUT_WRITE_TIMEOUT).await
+}
+
+/// Write `input` to the application, giving up after `timeout`. While the
+/// PTY's buffer is full, wait for room without holding the session's state,
+/// so the application's output keeps being read and other requests are
+/// answered in the meantime. The caller holds the `writing` lock.
+async fn write_input(state: &Arc<Mutex<DaemonState>>, input: Input, timeout: Duration) -> Response {
+    let pty = rustix::io::fcntl_dupfd_cloexec(&state.lock().unwrap().master_fd, 0);
+
+    let write = async {
+        let pty = AsyncFd::with_interest(pty?, tokio::io::Interest::WRITABLE)?;
+        let mut rest = &input.bytes[..];
+        while !rest.is_empty() {
+            let mut ready = pty.writable().await?;
+            if let Ok(written) = ready.try_io(|pty| Ok(rustix::io::write(pty.get_ref(), rest)?)) {
+                rest = &rest[written?..];
+            }
+        }
+        std::io::Result::Ok(())
+    };
+    match tokio::time::timeout(timeout, write).await {
+        Ok(Ok(())) => state.lock().unwrap().input_written(input),
+        Ok(Err(e)) => Response::error(format!("Failed to write to PTY: {}", e)),
+        Err(_) => Response::error("Failed to write to PTY: the application isn't reading its input".to_string()),
+    }
+}
+
+/// The bytes an INPUT request sends, or the response if it is invalid
+fn prepare_input(data: serde_json::Value, state: &mut DaemonState) -> Result<Input, Response> {
+    let paste = data.get("paste").and_then(|v| v.as_bool()).unwrap_or(false);
+    if paste && data.get("keys").is_some() {
+        return Err(Response::error("Only 'data' can be pasted, not 'keys'".to_string()));
+    }
+
+    let keys = match data.get("keys") {
+        None => None,
+        Some(names) => match names.as_str().map(keys::parse) {
+            Some(Ok(keys)) => Some(keys),
+            Some(Err(e)) => return Err(Response::error(e)),
+            None => return Err(Response::error("'keys' must be a string of key names".to_string())),
+        },
+    };
+    let input_data = match (data.get("data").and_then(|v| v.as_str()), keys) {
+        (Some(_), Some(_)) => return Err(Response::error("Send either 'data' or 'keys', not both".to_string())),
+        (Some(s), None) => s.as_bytes().to_vec(),
+        (None, Some(keys)) => {
+            // Encode for the modes the application has set by now
+            state.read_pty_output();
+            let modes = state.terminal.keyboard_modes();
+            let mut bytes = Vec::new();
+            for key in &keys {
+                match key.encode(modes) {
+                    Some(key_bytes) => bytes.extend(key_bytes),
+                    None => {
+                        return Err(Response::error(format!("Key '{}' can't be sent in the terminal's keyboard mode", key.name())))
+                    }
+                }
+            }
+            bytes
+        }
+        (None, None) => return Err(Response::error("Missing 'data' field".to_string())),
     };
 
-    let response = match request.req_type.as_str() {
-        "INPUT" => handle_input(request.data, &state),
-        "OUTPUT" => handle_output(request.data, &state),
-        "STATUS" => handle_running(request.data, &state),
-        "WAIT" => handle_wait(request.data.clone(), &state, &stream),
-        "KILL" => handle_kill(request.data, &state),
-        "STOP" => handle_stop(&state),
-        "RESIZE" => handle_resize(request.data, &state),
-        "DEBUG" => handle_debug(request.data, &state),
-        _ => Response::error(format!("Unknown command: {}", request.req_type)),
+    // Pasted text is marked as such if the application asked for that
+    let bracketed = paste && {
+        state.read_pty_output();
+        state.terminal.bracketed_paste()
     };
+    let input_data = if bracketed { bracketed_paste(&input_data) } else { input_data };
 
-    write_response(&mut stream, &response)?;
+    let secret = data.get("secret").and_then(|v| v.as_bool()).unwrap_or(false);
 
-    Ok(())
-}
+    // Input typed at a prompt that doesn't echo (e.g. a password) stays out of recordings
+    let secret = secret || termios_mode(&state.master_fd) == Some(("cooked", false));
 
-fn write_response(stream: &mut UnixStream, response: &Response) -> Result<()> {
-    let json = serde_json::to_string(response)?;
-    stream.write_all(json.as_bytes())?;
-    stream.write_all(b"\n")?;
-    stream.flush()?;
-    Ok(())
+    let response = if paste {
+        serde_json::json!({ "bracketed": bracketed })
+    } else {
+        serde_json::json!({})
+    };
+    Ok(Input { bytes: input_data, secret, response })
 }
 
-fn handle_input(data: serde_json::Value, state: &Arc<Mutex<DaemonState>>) -> Response {
-    let input_data = match data.get("data").and_then(|v| v.as_str()) {
-        Some(s) => s,
-        None => return Response::error("Missing 'data' field".to_string()),
+/// The mouse reports a MOUSE request sends, or the response if it is invalid
+fn prepare_mouse(data: serde_json::Value, state: &mut DaemonState) -> Result<Input, Response> {
+    let position = |data: &serde_json::Value| {
+        let row = data.get("row").and_then(|v| v.as_u64())?;
+        let col = data.get("col").and_then(|v| v.as_u64())?;
+        Some((row as usize, col as usize))
+    };
+    let Some((row, col)) = position(&data) else {
+        return Err(Response::error("Missing 'row' or 'col' field".to_string()));
+    };
+
+    let button = match data.get("button").and_then(|v| v.as_str()) {
+        None => mouse::Button::Left,
+        Some(name) => match mouse::Button::parse(name) {
+            Some(button) => button,
+            None => return Err(Response::error(format!("Unknown button '{}' (left, middle or right)", name))),
+        },
+    };
+    let action = match (data.get("drag_to"), data.get("scroll").and_then(|v| v.as_str())) {
+        (Some(_), Some(_)) => return Err(Response::error("Send either 'drag_to' or 'scroll', not both".to_string())),
+        (Some(to), None) => match position(to) {
+            Some(to) => mouse::Action::Drag { button, to },
+            None => return Err(Response::error("'drag_to' needs a 'row' and a 'col'".to_string())),
+        },
+        (None, Some("up")) => mouse::Action::Scroll { up: true },
+        (None, Some("down")) => mouse::Action::Scroll { up: false },
+        (None, Some(direction)) => return Err(Response::error(format!("Unknown scroll direction '{}' (up or down)", direction))),
+        (None, None) => mouse::Action::Click(button),
     };
 
-    let state = state.lock().unwrap();
+    let (rows, cols) = state.terminal.dimensions();
+    let to = match action {
+        mouse::Action::Drag { to, .. } => to,
+        _ => (row, col),
+    };
+    if [(row, col), to].into_iter().any(|(row, col)| row >= rows || col >= cols) {
+        return Err(Response::error(format!("Mouse position is outside the {}x{} screen", cols, rows)));
+    }
 
-    match nix::unistd::write(state.master_fd.as_raw_fd(), input_data.as_bytes()) {
-        Ok(_) => Response::ok(serde_json::json!({})),
-        Err(e) => Response::error(format!("Failed to write to PTY: {}", e)),
+    // Encode for the mouse modes the application has set by now
+    state.read_pty_output();
+    let mode = state.terminal.mouse_mode();
+    let bytes = mouse::encode(action, row, col, mode).map_err(Response::error)?;
+    let response = serde_json::json!({
+        "tracking": mode.tracking,
+        "encoding": mode.encoding
+    });
+    Ok(Input { bytes, secret: false, response })
+}
+
+/// Wrap `text` in bracketed paste markers, dropping any end marker inside it
+/// so the paste can't end early
+fn bracketed_paste(text: &[u8]) -> Vec<u8> {
+    const START: &[u8] = b"\x1b[200~";
+    const END: &[u8] = b"\x1b[201~";
+
+    let mut text = text.to_vec();
+    // Removing one marker may join the pieces of another
+    while let Some(index) = text.windows(END.len()).position(|window| window == END) {
+        text.drain(index..index + END.len());
     }
+    [START, &text, END].concat()
 }
 
-fn handle_output(data: serde_json::Value, state: &Arc<Mutex<DaemonState>>) -> Response {
+fn handle_output(data: serde_json::Value, state: &mut DaemonState) -> Response {
     let format = data.get("format").and_then(|v| v.as_str()).unwrap_or("ascii");
 
-    let mut state = state.lock().unwrap();
     state.read_pty_output();
 
+    let since = match data.get("since") {
+        None => None,
+        Some(v) => match v.as_u64() {
+            Some(generation) => Some(generation),
+            None => return Response::error("'since' must be a generation number".to_string()),
+        },
+    };
+
+    // Rendered documents describe the whole screen, they can't be diffed
+    if since.is_some() && (format == "html" || format == "svg" || format == "png") {
+        return Response::error(format!("'since' is not supported with format {}", format));
+    }
+
     let screen_text = match format {
         "ansi" => state.terminal.get_screen_content_ansi(),
+        "html" => render::html(&state.terminal.get_cells(), state.terminal.cursor_position()),
+        "svg" => render::svg(&state.terminal.get_cells(), state.terminal.cursor_position(), state.terminal.dimensions().1),
         _ => state.terminal.get_screen_content(),
     };
+    let image = match format {
+        "png" => {
+            let (_, cols) = state.terminal.dimensions();
+            match screenshot::png(&state.terminal.get_cells(), state.terminal.cursor_position(), cols) {
+                Ok(image) => Some(image),
+                Err(e) => return Response::error(format!("Failed to render PNG: {}", e)),
+            }
+        }
+        _ => None,
+    };
+    // Structured formats describe each row as a list of cells or spans
+    let styled_rows: Option<Vec<serde_json::Value>> = match format {
+        "cells" | "spans" => Some(
+            state.terminal.get_cells().iter()
+                .map(|row| match format {
+                    "cells" => serde_json::json!(terminal::trim_row(row)),
+                    _ => serde_json::json!(terminal::row_spans(row)),
+                })
+                .collect(),
+        ),
+        _ => None,
+    };
     let (cursor_row, cursor_col) = state.terminal.cursor_position();
     let (rows, cols) = state.terminal.dimensions();
+    let generation = state.screen_generation;
 
-    let data = serde_json::json!({
-        "screen": screen_text,
+    let mut response = serde_json::json!({
+        "generation": generation,
         "cursor": {
             "row": cursor_row,
             "col": cursor_col
@@ -744,97 +1664,462 @@ fn handle_output(data: serde_json::Value, state: &Arc<Mutex<DaemonState>>) -> Re
         }
     });
 
-    Response::ok(data)
+    // History is separate from the screen so `screen` always means the same thing
+    if let Some(lines) = data.get("scrollback").and_then(|v| v.as_u64()) {
+        let history = match format {
+            "ansi" => state.terminal.get_history_ansi(lines as usize),
+            _ => state.terminal.get_history(lines as usize),
+        };
+        response["scrollback"] = serde_json::json!(history);
+        response["history_size"] = serde_json::json!(state.terminal.history_size());
+    }
+
+    // Row contents keyed by the field they are reported under
+    let (row_key, row_values) = match styled_rows {
+        Some(styled_rows) => (format, styled_rows),
+        None => ("text", screen_rows(&screen_text, rows).into_iter().map(serde_json::Value::from).collect()),
+    };
+
+    match since {
+        Some(since) => {
+            // A generation we never handed out can't be diffed against - send everything
+            let changed: Vec<serde_json::Value> = row_values
+                .into_iter()
+                .enumerate()
+                .filter(|(row, _)| since > generation || state.row_generations.get(*row).is_some_and(|&g| g > since))
+                .map(|(row, value)| serde_json::json!({"row": row, row_key: value}))
+                .collect();
+            response["unchanged"] = serde_json::json!(changed.is_empty());
+            response["changed_rows"] = serde_json::json!(changed);
+        }
+        None => match image {
+            Some((png, width, height)) => {
+                response["png"] = serde_json::json!(BASE64.encode(png));
+                response["width"] = serde_json::json!(width);
+                response["height"] = serde_json::json!(height);
+            }
+            None if row_key == "text" => response["screen"] = serde_json::json!(screen_text),
+            None => response[row_key] = serde_json::json!(row_values),
+        },
+    }
+
+    Response::ok(response)
+}
+
+/// Default limit for the amount of output returned by one READ
+const READ_DEFAULT_MAX_BYTES: usize = 64 * 1024;
+
+fn handle_read(data: serde_json::Value, state: &mut DaemonState) -> Response {
+    let offset = data.get("offset").and_then(|v| v.as_u64()).unwrap_or(0);
+    let max_bytes = data.get("max_bytes").and_then(|v| v.as_u64()).map(|n| n as usize).unwrap_or(READ_DEFAULT_MAX_BYTES);
+    if max_bytes == 0 {
+        return Response::error("'max_bytes' must be positive".to_string());
+    }
+    let format = data.get("format").and_then(|v| v.as_str()).unwrap_or("raw");
+    if format != "raw" && format != "text" {
+        return Response::error(format!("Invalid format: {}", format));
+    }
+
+    state.read_pty_output();
+    state.check_child_status();
+
+    let (start, bytes) = state.output_log.read(offset, max_bytes);
+    let more = start + (bytes.len() as u64) < state.output_log.end_offset();
+
+    // Raw output is passed on byte for byte, whatever the application wrote
+    let (data, encoding, consumed) = if format == "raw" {
+        (BASE64.encode(&bytes), "base64", bytes.len())
+    } else {
+        // Stop before a character or escape sequence that isn't complete yet,
+        // so the next read picks it up whole
+        match output_log::strip_ansi(&bytes) {
+            // Something longer than max_bytes can't ever be completed - don't get stuck on it
+            (_, 0) if more => (String::from_utf8_lossy(&bytes).into_owned(), "utf8", bytes.len()),
+            (text, consumed) => (text, "utf8", consumed),
+        }
+    };
+
+    Response::ok(serde_json::json!({
+        "data": data,
+        "encoding": encoding,
+        "offset": start,
+        "next_offset": start + consumed as u64,
+        "end_offset": state.output_log.end_offset(),
+        "dropped": start.saturating_sub(offset),
+        "running": state.exit_code.is_none()
+    }))
 }
 
-fn handle_running(data: serde_json::Value, state: &Arc<Mutex<DaemonState>>) -> Response {
+fn handle_running(data: serde_json::Value, state: &mut DaemonState) -> Response {
     let activity_mode = data.get("activity").and_then(|v| v.as_bool()).unwrap_or(false);
-    let mut state = state.lock().unwrap();
     state.check_child_status();
 
-    let running = state.exit_code.is_none();
+    let (rows, cols) = state.terminal.dimensions();
+    let mut response = serde_json::json!({
+        "running": state.exit_code.is_none(),
+        "pid": state.child_pid.as_raw(),
+        "command": state.command,
+        "rows": rows,
+        "cols": cols
+    });
 
     if activity_mode {
-        let activity = state.activity;
+        response["activity"] = serde_json::json!(state.activity);
         state.activity = false;  // Clear the flag after reading
-        let mut response = serde_json::json!({
-            "running": running,
-            "activity": activity
-        });
-        if let Some(exit_code) = state.exit_code {
-            response["exit_code"] = serde_json::json!(exit_code);
-        }
-        Response::ok(response)
-    } else if let Some(exit_code) = state.exit_code {
-        Response::ok(serde_json::json!({
-            "running": false,
-            "exit_code": exit_code
-        }))
-    } else {
-        Response::ok(serde_json::json!({
-            "running": true
-        }))
     }
+    if let Some(exit_code) = state.exit_code {
+        response["exit_code"] = serde_json::json!(exit_code);
+    }
+    Response::ok(response)
 }
 
-fn handle_wait(data: serde_json::Value, state: &Arc<Mutex<DaemonState>>, stream: &UnixStream) -> Response {
+/// Resolves once the client on the other end of `stream` has gone away
+async fn client_gone(stream: &UnixStream) {
     use rustix::net::{recv, RecvFlags};
 
-    let activity_mode = data.get("activity").and_then(|v| v.as_bool()).unwrap_or(false);
+    let mut buf = [0u8; 1];
+    loop {
+        if stream.readable().await.is_err() {
+            return;
+        }
+        // Peek so pending requests stay in the socket buffer
+        let peeked = stream.try_io(tokio::io::Interest::READABLE, || {
+            recv(stream, &mut buf, RecvFlags::PEEK | RecvFlags::DONTWAIT).map_err(std::io::Error::from)
+        });
+        match peeked {
+            // EOF - client disconnected
+            Ok((_, 0)) => return,
+            // Data from client (e.g. a pipelined request) - still connected
+            Ok(_) => std::future::pending().await,
+            // Nothing to read after all
+            Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => continue,
+            // Real error - assume client disconnected
+            Err(_) => return,
+        }
+    }
+}
 
+/// Check the session with `check` until it comes up with a response, again
+/// whenever the session changes and at the time `check` asks to be woken.
+/// Gives up if the client goes away.
+async fn wait_until(
+    state: &Arc<Mutex<DaemonState>>,
+    stream: &UnixStream,
+    mut check: impl FnMut(&mut DaemonState) -> ControlFlow<Response, Option<Instant>>,
+) -> Response {
+    let changed = state.lock().unwrap().changed.clone();
     loop {
-        // Check if client disconnected using recv with MSG_PEEK | MSG_DONTWAIT
-        let mut buf = [0u8; 1];
-        let flags = RecvFlags::PEEK | RecvFlags::DONTWAIT;
-        match recv(stream, &mut buf, flags) {
-            Ok((_, 0)) => {
-                // EOF - client disconnected
-                return Response::error("Client disconnected".to_string());
+        // Listen before checking, so no change slips through in between
+        let notified = changed.notified();
+        tokio::pin!(notified);
+        notified.as_mut().enable();
+
+        let wake_at = {
+            let mut state = state.lock().unwrap();
+            state.check_child_status();
+            match check(&mut state) {
+                ControlFlow::Break(response) => return response,
+                ControlFlow::Continue(wake_at) => wake_at,
             }
-            Ok(_) => {
-                // Unexpected data from client - ignore
+        };
+
+        let timer = async {
+            match wake_at {
+                Some(wake_at) => tokio::time::sleep_until(wake_at.into()).await,
+                None => std::future::pending().await,
             }
-            Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => {
-                // No data, client still connected - continue waiting
+        };
+        tokio::select! {
+            _ = &mut notified => {}
+            _ = timer => {}
+            _ = client_gone(stream) => return Response::error("Client disconnected".to_string()),
+        }
+    }
+}
+
+async fn handle_wait(data: serde_json::Value, state: &Arc<Mutex<DaemonState>>, stream: &UnixStream) -> Response {
+    let timeout = data.get("timeout_ms").and_then(|v| v.as_u64()).map(Duration::from_millis);
+
+    if let Some(settle_ms) = data.get("settle_ms").and_then(|v| v.as_u64()) {
+        return handle_wait_settle(Duration::from_millis(settle_ms), timeout, state, stream).await;
+    }
+
+    let activity_mode = data.get("activity").and_then(|v| v.as_bool()).unwrap_or(false);
+    let deadline = timeout.map(|t| Instant::now() + t);
+
+    wait_until(state, stream, |state| {
+        if activity_mode {
+            // Activity mode: return as soon as activity or exit is detected
+            // Get separate flags for PTY activity vs process exit
+            let pty_activity = state.activity;
+            let exited = state.exit_code.is_some();
+            if pty_activity || exited {
+                // Clear the PTY activity flag
+                state.activity = false;
+                return ControlFlow::Break(Response::ok(serde_json::json!({
+                    "activity": pty_activity,
+                    "exited": exited
+                })));
             }
-            Err(_) => {
-                // Real error - assume client disconnected
-                return Response::error("Client disconnected".to_string());
+        } else {
+            // Normal mode: wait for exit
+            if let Some(exit_code) = state.exit_code {
+                return ControlFlow::Break(Response::ok(serde_json::json!({
+                    "exit_code": exit_code
+                })));
             }
         }
 
-        {
-            let mut state = state.lock().unwrap();
-            state.check_child_status();
-
-            if activity_mode {
-                // Activity mode: return as soon as activity or exit is detected
-                // Get separate flags for PTY activity vs process exit
-                let pty_activity = state.activity;
-                let exited = state.exit_code.is_some();
-                if pty_activity || exited {
-                    // Clear the PTY activity flag
-                    state.activity = false;
-                    return Response::ok(serde_json::json!({
-                        "activity": pty_activity,
-                        "exited": exited
-                    }));
-                }
+        if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+            return ControlFlow::Break(Response::ok(if activity_mode {
+                serde_json::json!({
+                    "activity": false,
+                    "exited": false,
+                    "timed_out": true
+                })
             } else {
-                // Normal mode: wait for exit
-                if let Some(exit_code) = state.exit_code {
-                    return Response::ok(serde_json::json!({
-                        "exit_code": exit_code
-                    }));
+                serde_json::json!({
+                    "timed_out": true
+                })
+            }));
+        }
+        ControlFlow::Continue(deadline)
+    }).await
+}
+
+/// Settle mode: wait until the activity flag has stayed clear for `settle`
+async fn handle_wait_settle(settle: Duration, timeout: Option<Duration>, state: &Arc<Mutex<DaemonState>>, stream: &UnixStream) -> Response {
+    let start = Instant::now();
+    let deadline = timeout.map(|t| start + t);
+    let mut quiet_since = start;
+
+    wait_until(state, stream, |state| {
+        // Any output restarts the quiet period
+        if state.activity {
+            state.activity = false;
+            quiet_since = Instant::now();
+        }
+
+        // Nothing more can change once the process is gone
+        let exited = state.exit_code.is_some();
+        let settled = exited || quiet_since.elapsed() >= settle;
+        let timed_out = !settled && deadline.is_some_and(|deadline| Instant::now() >= deadline);
+
+        if settled || timed_out {
+            return ControlFlow::Break(Response::ok(serde_json::json!({
+                "settled": settled,
+                "timed_out": timed_out,
+                "exited": exited
+            })));
+        }
+        // Check again when the quiet period would be over
+        let wake_at = quiet_since + settle;
+        ControlFlow::Continue(Some(deadline.map_or(wake_at, |deadline| deadline.min(wake_at))))
+    }).await
+}
+
+/// Longest a BATCH may wait in all (in SLEEP and SETTLE steps and for the
+/// application to read its input), since other clients' input can't reach
+/// the session meanwhile
+const BATCH_MAX_WAIT: Duration = Duration::from_secs(10);
+
+/// Run the requests in `data["requests"]` in order, holding the `writing` lock
+/// throughout so no other client's input comes in between
+async fn handle_batch(data: serde_json::Value, state: &Arc<Mutex<DaemonState>>, stream: &UnixStream) -> Response {
+    let steps = match data.get("requests").and_then(|v| v.as_array()) {
+        Some(steps) => steps,
+        None => return Response::error("Missing 'requests' field".to_string()),
+    };
+    let stop_on_error = data.get("stop_on_error").and_then(|v| v.as_bool()).unwrap_or(false);
+
+    // Let input that is being written finish first, and keep other input out
+    // until the batch is done
+    let writing = state.lock().unwrap().writing.clone();
+    let _writing = writing.lock().await;
+    let deadline = Instant::now() + BATCH_MAX_WAIT;
+    let mut responses = Vec::new();
+    let mut stopped = false;
+    for step in steps {
+        let mut response = handle_batch_step(step, deadline, state, stream).await;
+        response.id = step.get("id").cloned();
+        let failed = response.status == "error";
+        responses.push(response);
+        if failed && stop_on_error {
+            stopped = true;
+            break;
+        }
+    }
+
+    Response::ok(serde_json::json!({
+        "responses": responses,
+        "stopped": stopped
+    }))
+}
+
+/// Run one step of a BATCH, which has until `deadline` to wait in all
+async fn handle_batch_step(step: &serde_json::Value, deadline: Instant, state: &Arc<Mutex<DaemonState>>, stream: &UnixStream) -> Response {
+    let req_type = match step.get("type").and_then(|v| v.as_str()) {
+        Some(req_type) => req_type,
+        None => return Response::error("Invalid request: missing 'type' field".to_string()),
+    };
+
+    let left = deadline.saturating_duration_since(Instant::now());
+    let wait = |field: &str, default: Option<Duration>| -> Result<Duration, Response> {
+        match step.get(field).and_then(|v| v.as_u64()).map(Duration::from_millis).or(default) {
+            Some(wait) if wait <= left => Ok(wait),
+            Some(_) => Err(Response::error(format!(
+                "'{}' is more than the {} ms this BATCH has left (a BATCH may wait {} ms in all)",
+                field,
+                left.as_millis(),
+                BATCH_MAX_WAIT.as_millis()
+            ))),
+            None => Err(Response::error(format!("Missing '{}' field", field))),
+        }
+    };
+
+    match req_type {
+        "SLEEP" => match wait("ms", None) {
+            Ok(duration) => {
+                // The application's output is read in the meantime
+                tokio::time::sleep(duration).await;
+                Response::ok(serde_json::json!({}))
+            }
+            Err(response) => response,
+        },
+        "SETTLE" => match (wait("settle_ms", None), wait("timeout_ms", Some(left))) {
+            (Ok(settle), Ok(timeout)) => handle_wait_settle(settle, Some(timeout), state, stream).await,
+            (Err(response), _) | (_, Err(response)) => response,
+        },
+        "INPUT" | "MOUSE" => {
+            let input = {
+                let mut state = state.lock().unwrap();
+                match req_type {
+                    "INPUT" => prepare_input(step.clone(), &mut state),
+                    _ => prepare_mouse(step.clone(), &mut state),
                 }
+            };
+            match input {
+                // The batch holds the `writing` lock already
+                Ok(input) => write_input(state, input, left.min(INPUT_WRITE_TIMEOUT)).await,
+                Err(response) => response,
+            }
+        }
+        // These wait for other clients or change what the connection is
+        "WAIT" | "EXPECT" | "SUBSCRIBE" | "STOP" | "BATCH" => {
+            Response::error(format!("{} can't be used in a BATCH", req_type))
+        }
+        _ => handle_locked(req_type, step.clone(), &mut state.lock().unwrap()),
+    }
+}
+
+/// Find the first pattern (in list order) that matches `text`.
+/// Returns the pattern index, the match text and its byte range in `text`.
+fn find_first_match(patterns: &[Regex], text: &str) -> Option<(usize, String, std::ops::Range<usize>)> {
+    patterns.iter().enumerate().find_map(|(index, re)| {
+        re.find(text).map(|m| (index, m.as_str().to_string(), m.range()))
+    })
+}
+
+/// Convert a byte offset in multi-line text to a 0-based (row, col) position
+fn text_position(text: &str, offset: usize) -> (usize, usize) {
+    let before = &text[..offset];
+    let row = before.matches('\n').count();
+    let line_start = before.rfind('\n').map(|i| i + 1).unwrap_or(0);
+    (row, before[line_start..].chars().count())
+}
+
+async fn handle_expect(data: serde_json::Value, state: &Arc<Mutex<DaemonState>>, stream: &UnixStream) -> Response {
+    let pattern_strs: Vec<String> = match data.get("patterns").and_then(|v| v.as_array()) {
+        Some(arr) if !arr.is_empty() => {
+            match arr.iter().map(|v| v.as_str().map(String::from)).collect::<Option<Vec<_>>>() {
+                Some(strs) => strs,
+                None => return Response::error("'patterns' must be an array of strings".to_string()),
+            }
+        }
+        _ => return Response::error("Missing 'patterns' field".to_string()),
+    };
+
+    let mut patterns = Vec::new();
+    for pattern in &pattern_strs {
+        match Regex::new(pattern) {
+            Ok(re) => patterns.push(re),
+            Err(e) => return Response::error(format!("Invalid pattern '{}': {}", pattern, e)),
+        }
+    }
+
+    let target = data.get("target").and_then(|v| v.as_str()).unwrap_or("screen");
+    if target != "screen" && target != "output" {
+        return Response::error(format!("Invalid target: {}", target));
+    }
+
+    let timeout = data.get("timeout_ms").and_then(|v| v.as_u64()).map(Duration::from_millis);
+    let deadline = timeout.map(|t| Instant::now() + t);
+
+    wait_until(state, stream, |state| {
+        state.read_pty_output();
+
+        // Where the output searched ends, or the match in it
+        let mut end_offset = None;
+        let found = if target == "output" {
+            // Clients keep track of where they left off themselves
+            let from = data.get("offset").and_then(|v| v.as_u64()).unwrap_or(0);
+            let (from, bytes) = state.output_log.read_from(from);
+            let (text, source_ends) = output_log::strip_ansi_with_offsets(&bytes);
+            end_offset = Some(from + bytes.len() as u64);
+            find_first_match(&patterns, &text).map(|(index, matched, range)| {
+                let consumed = range.end.checked_sub(1).map(|i| source_ends[i]).unwrap_or(0);
+                end_offset = Some(from + consumed as u64);
+                let (row, col) = text_position(&text, range.start);
+                (index, matched, row, col)
+            })
+        } else {
+            let screen = state.terminal.get_screen_content();
+            find_first_match(&patterns, &screen).map(|(index, matched, range)| {
+                let (row, col) = text_position(&screen, range.start);
+                (index, matched, row, col)
+            })
+        };
+
+        let exited = state.exit_code.is_some();
+        let timed_out = deadline.is_some_and(|deadline| Instant::now() >= deadline);
+
+        if found.is_none() && !exited && !timed_out {
+            return ControlFlow::Continue(deadline);
+        }
+
+        let screen = state.terminal.get_screen_content();
+        let (cursor_row, cursor_col) = state.terminal.cursor_position();
+        let mut response = serde_json::json!({
+            "matched": found.is_some(),
+            "screen": screen,
+            "cursor": {
+                "row": cursor_row,
+                "col": cursor_col
+            }
+        });
+
+        if let Some(end_offset) = end_offset {
+            response["end_offset"] = serde_json::json!(end_offset);
+        }
+        match found {
+            Some((index, matched, row, col)) => {
+                response["pattern_index"] = serde_json::json!(index);
+                response["pattern"] = serde_json::json!(pattern_strs[index]);
+                response["match"] = serde_json::json!(matched);
+                response["row"] = serde_json::json!(row);
+                response["col"] = serde_json::json!(col);
+            }
+            None => {
+                response["timed_out"] = serde_json::json!(!exited);
+                response["exited"] = serde_json::json!(exited);
             }
         }
 
-        thread::sleep(Duration::from_millis(100));
-    }
+        ControlFlow::Break(Response::ok(response))
+    }).await
 }
 
-fn handle_kill(data: serde_json::Value, state: &Arc<Mutex<DaemonState>>) -> Response {
+fn handle_kill(data: serde_json::Value, state: &mut DaemonState) -> Response {
     let signal_str = match data.get("signal").and_then(|v| v.as_str()) {
         Some(s) => s,
         None => return Response::error("Missing 'signal' field".to_string()),
@@ -845,8 +2130,6 @@ fn handle_kill(data: serde_json::Value, state: &Arc<Mutex<DaemonState>>) -> Resp
         Err(e) => return Response::error(format!("Invalid signal: {}", e)),
     };
 
-    let state = state.lock().unwrap();
-
     match kill(state.child_pid, signal) {
         Ok(_) => Response::ok(serde_json::json!({
             "signal_sent": signal_str
@@ -865,13 +2148,15 @@ fn handle_stop(state: &Arc<Mutex<DaemonState>>) -> Response {
 
     // Set shutdown flag to exit daemon loop
     state.should_shutdown = true;
+    state.changed.notify_waiters();
+    state.stopped.notify_one();
 
     Response::ok(serde_json::json!({
         "message": "Shutting down"
     }))
 }
 
-fn handle_resize(data: serde_json::Value, state: &Arc<Mutex<DaemonState>>) -> Response {
+fn handle_resize(data: serde_json::Value, state: &mut DaemonState) -> Response {
     let cols = match data.get("cols").and_then(|v| v.as_u64()) {
         Some(c) => c as u16,
         None => return Response::error("Missing 'cols' field".to_string()),
@@ -882,8 +2167,6 @@ fn handle_resize(data: serde_json::Value, state: &Arc<Mutex<DaemonState>>) -> Re
         None => return Response::error("Missing 'rows' field".to_string()),
     };
 
-    let mut state = state.lock().unwrap();
-
     // Send TIOCSWINSZ to update terminal size using rustix's safe wrapper
     use rustix::termios::{tcsetwinsize, Winsize as RustixWinsize};
 
@@ -900,6 +2183,11 @@ fn handle_resize(data: serde_json::Value, state: &Arc<Mutex<DaemonState>>) -> Re
 
     // Update terminal emulator dimensions
     state.terminal.resize(rows as usize, cols as usize);
+    state.subscribers.emit(Event::Resize { rows: rows as usize, cols: cols as usize });
+    if let Some(recorder) = state.recorder.as_mut() {
+        recorder.resize(rows as usize, cols as usize);
+    }
+    state.note_changes();
 
     Response::ok(serde_json::json!({
         "cols": cols,
@@ -907,11 +2195,9 @@ fn handle_resize(data: serde_json::Value, state: &Arc<Mutex<DaemonState>>) -> Re
     }))
 }
 
-fn handle_debug(data: serde_json::Value, state: &Arc<Mutex<DaemonState>>) -> Response {
+fn handle_debug(data: serde_json::Value, state: &mut DaemonState) -> Response {
     let clear = data.get("clear").and_then(|v| v.as_bool()).unwrap_or(false);
 
-    let mut state = state.lock().unwrap();
-
     let entries = state.terminal.get_debug_entries();
     let dropped = state.terminal.get_debug_dropped();
 
@@ -1048,21 +2334,345 @@ fn apply_cursor_inverse(screen: &str, cursor_row: usize, cursor_col: usize) -> S
     result
 }
 
-fn send_request(socket_path: &str, request: serde_json::Value) -> Result<Response> {
-    let mut stream = UnixStream::connect(socket_path)
-        .context("Failed to connect to daemon socket")?;
+/// Exit status for timeouts (same as coreutils timeout)
+const EXIT_TIMEOUT: i32 = 124;
+
+/// What every request from this client carries besides its own fields
+#[derive(Clone, Copy, Default)]
+struct RequestContext<'a> {
+    /// Session to address, for sockets that belong to a server
+    session: Option<&'a str>,
+    /// Token the daemon may require
+    token: Option<&'a str>,
+    /// Client name (--client), to keep what commands remember apart per caller
+    caller: &'a str,
+}
+
+impl RequestContext<'_> {
+    /// A client for `socket` carrying this context
+    fn client(&self, socket: &str, timeout: Option<Duration>) -> Client {
+        let mut client = Client::new(socket).timeout(timeout);
+        if let Some(session) = self.session {
+            client = client.session(session);
+        }
+        if let Some(token) = self.token {
+            client = client.token(token);
+        }
+        client
+    }
+}
+
+/// Print output from `offset` on (up to `max_bytes`), like `tail -f` with `follow`.
+/// Reports the offset to continue from on stderr.
+fn cmd_read(client: &Client, mut offset: u64, format: &str, max_bytes: Option<u64>, follow: bool, timeout: Option<Duration>) -> Result<()> {
+    use std::time::Instant;
+
+    let deadline = timeout.map(|t| Instant::now() + t);
+    let mut stdout = std::io::stdout();
+    let mut read = 0;
+    loop {
+        let remaining = match deadline {
+            Some(deadline) => {
+                let remaining = deadline.saturating_duration_since(Instant::now());
+                if remaining.is_zero() {
+                    return Err(timeout_error("reading output", timeout.unwrap_or_default()));
+                }
+                Some(remaining)
+            }
+            None => None,
+        };
+
+        let budget = max_bytes.map(|max| max - read);
+        let mut request = serde_json::json!({
+            "type": "READ",
+            "offset": offset,
+            "format": format
+        });
+        if let Some(budget) = budget {
+            request["max_bytes"] = serde_json::json!(budget);
+        }
+
+        let data = client.clone().timeout(remaining).request(request)?;
+        let dropped = data.get("dropped").and_then(|v| v.as_u64()).unwrap_or(0);
+        if dropped > 0 {
+            eprintln!("Warning: {} bytes of output were dropped before offset {}", dropped, offset + dropped);
+        }
+        if let Some(text) = data.get("data").and_then(|v| v.as_str()) {
+            match data.get("encoding").and_then(|v| v.as_str()) {
+                Some("base64") => stdout.write_all(&BASE64.decode(text).context("Invalid base64 output from daemon")?)?,
+                _ => stdout.write_all(text.as_bytes())?,
+            }
+            stdout.flush()?;
+        }
+
+        let next_offset = data.get("next_offset").and_then(|v| v.as_u64()).unwrap_or(offset);
+        let end_offset = data.get("end_offset").and_then(|v| v.as_u64()).unwrap_or(offset);
+        let running = data.get("running").and_then(|v| v.as_bool()).unwrap_or(false);
+        let progressed = next_offset > offset;
+        // The daemon held back a character cut off by our limit - that's where we stop
+        let cut_by_limit = budget.is_some_and(|budget| offset + budget <= end_offset && next_offset < offset + budget);
+        read += next_offset - offset;
+        offset = next_offset;
+
+        if cut_by_limit || max_bytes.is_some_and(|max| read >= max) {
+            break;
+        }
+        if progressed && offset < end_offset {
+            // More is already available
+            continue;
+        }
+        if !follow || !running {
+            break;
+        }
+        thread::sleep(Duration::from_millis(100));
+    }
+
+    eprintln!("Next offset: {}", offset);
+    Ok(())
+}
+
+/// Replay a recording (or raw output dump) into a fresh terminal and print
+/// the screen at its end, or at `at_offset` bytes / `at_time` seconds
+fn cmd_render(file: &str, emulator: Emulator, size: Option<&str>, format: &str, at_offset: Option<u64>, at_time: Option<f64>) -> Result<()> {
+    let recording = recording::load(Path::new(file))?;
+    if at_time.is_some() && !recording.timed {
+        anyhow::bail!("{} has no timing information; use --at-offset instead", file);
+    }
+
+    let (rows, cols) = match size {
+        Some(size) => {
+            let (cols, rows) = parse_terminal_size(size)?;
+            (rows as usize, cols as usize)
+        }
+        None => recording.size.unwrap_or((24, 80)),
+    };
+    let mut terminal = create_terminal(rows, cols, emulator, 0);
+
+    let mut offset: u64 = 0;
+    let mut time = 0.0;
+    for (event_time, event) in recording.events {
+        if at_time.is_some_and(|limit| event_time > limit) {
+            break;
+        }
+        match event {
+            Recorded::Output(bytes) => {
+                let remaining = at_offset.map_or(u64::MAX, |limit| limit - offset);
+                if remaining == 0 {
+                    break;
+                }
+                let len = (bytes.len() as u64).min(remaining) as usize;
+                terminal.process_bytes(&bytes[..len]);
+                offset += len as u64;
+            }
+            Recorded::Resize { rows, cols } => terminal.resize(rows, cols),
+        }
+        time = event_time;
+    }
+
+    match format {
+        "json" => {
+            let (cursor_row, cursor_col) = terminal.cursor_position();
+            let (rows, cols) = terminal.dimensions();
+            let mut result = serde_json::json!({
+                "screen": terminal.get_screen_content(),
+                "cursor": {"row": cursor_row, "col": cursor_col},
+                "size": {"rows": rows, "cols": cols},
+                "offset": offset,
+            });
+            if recording.timed {
+                result["time"] = serde_json::json!(time);
+            }
+            println!("{}", result);
+        }
+        "ascii" => print!("{}", terminal.get_screen_content()),
+        _ => print!("{}", terminal.get_screen_content_ansi()),
+    }
+    Ok(())
+}
+
+/// Send the requests in `file` (or stdin) as one BATCH and print each response
+/// as a JSON line. Fails if any of them failed.
+fn cmd_batch(client: &Client, file: Option<&str>, stop_on_error: bool) -> Result<()> {
+    let text = match file {
+        Some(path) => std::fs::read_to_string(path).with_context(|| format!("Failed to read {}", path))?,
+        None => std::io::read_to_string(std::io::stdin()).context("Failed to read stdin")?,
+    };
+    let requests: Vec<serde_json::Value> = match serde_json::from_str(&text) {
+        Ok(serde_json::Value::Array(requests)) => requests,
+        // Not an array: one request per line
+        _ => text.lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| serde_json::from_str(line).with_context(|| format!("Invalid request: {}", line)))
+            .collect::<Result<_>>()?,
+    };
+
+    let request = serde_json::json!({
+        "type": "BATCH",
+        "requests": requests,
+        "stop_on_error": stop_on_error
+    });
+    let data = client.request(request)?;
+    let responses = data.get("responses").and_then(|v| v.as_array()).cloned().unwrap_or_default();
+    for response in &responses {
+        println!("{}", response);
+    }
+
+    if data.get("stopped").and_then(|v| v.as_bool()).unwrap_or(false) {
+        eprintln!("Stopped at request {}; {} request(s) not run", responses.len(), requests.len() - responses.len());
+    }
+    if responses.iter().any(|response| response.get("status").and_then(|v| v.as_str()) == Some("error")) {
+        std::process::exit(1);
+    }
+
+    Ok(())
+}
+
+/// Print what the daemon supports, failing if a `require`d feature is missing
+fn cmd_hello(client: &Client, require: &[String]) -> Result<()> {
+    let response = client.send(serde_json::json!({"type": "HELLO"}))?;
+
+    if response.status == "error" {
+        let error = response.error.unwrap_or_default();
+        if error != "Unknown command: HELLO" {
+            eprintln!("Error: {}", error);
+            std::process::exit(1);
+        }
+        // Daemons from before the handshake support what protocol 0 defines
+        println!("Protocol: 0");
+        if let Some(feature) = require.first() {
+            eprintln!("Error: daemon predates HELLO, can't tell whether it supports {}", feature);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
+    let data = response.data.unwrap_or_default();
+    let list = |value: Option<&serde_json::Value>| -> Vec<String> {
+        value.and_then(|v| v.as_array())
+            .map(|items| items.iter().filter_map(|item| item.as_str().map(String::from)).collect())
+            .unwrap_or_default()
+    };
+
+    let protocol = data.get("protocol").and_then(|v| v.as_u64()).unwrap_or(0);
+    println!("Protocol: {}", protocol);
+    if let (Some(daemon), Some(version)) = (data.get("daemon").and_then(|v| v.as_str()), data.get("version").and_then(|v| v.as_str())) {
+        println!("Daemon: {} {}", daemon, version);
+    }
+    if let Some(server) = data.get("server").and_then(|v| v.as_bool()) {
+        println!("Server: {}", server);
+    }
+    if let Some(emulator) = data.get("emulator").and_then(|v| v.as_str()) {
+        println!("Emulator: {}", emulator);
+    }
+    if let Some(colors) = data.get("colors").and_then(|v| v.as_bool()) {
+        println!("Colors: {}", colors);
+    }
+    let requests = list(data.get("requests"));
+    println!("Requests: {}", requests.join(" "));
+    if let Some(formats) = data.get("formats").and_then(|v| v.as_object()) {
+        for (request, names) in formats {
+            println!("{} formats: {}", request, list(Some(names)).join(" "));
+        }
+    }
+    if let Some(limits) = data.get("limits").and_then(|v| v.as_object()) {
+        let limits: Vec<String> = limits.iter().map(|(name, value)| format!("{}={}", name, value)).collect();
+        println!("Limits: {}", limits.join(" "));
+    }
+
+    if protocol > PROTOCOL_VERSION {
+        eprintln!("Note: daemon speaks protocol {}, this client only knows up to {}", protocol, PROTOCOL_VERSION);
+    }
+
+    for feature in require {
+        let supported = match feature.split_once(':') {
+            Some((request, format)) => list(data.get("formats").and_then(|f| f.get(request.to_uppercase())))
+                .iter()
+                .any(|f| f == format),
+            None => requests.iter().any(|r| r.eq_ignore_ascii_case(feature)),
+        };
+        if !supported {
+            eprintln!("Error: daemon does not support {}", feature);
+            std::process::exit(1);
+        }
+    }
+
+    Ok(())
+}
+
+/// Print the sessions found in the runtime and temp directories
+fn cmd_list(context: RequestContext, timeout: Option<Duration>) -> Result<()> {
+    // Don't let one hung daemon hold up the whole list
+    let timeout = timeout.or(Some(Duration::from_secs(2)));
+    let mut stale = 0;
 
-    let json = serde_json::to_string(&request)?;
-    stream.write_all(json.as_bytes())?;
-    stream.write_all(b"\n")?;
-    stream.flush()?;
+    println!("{:<16} {:<8} {:<8} {:<12} {:<40} COMMAND", "NAME", "PID", "SIZE", "STATUS", "SOCKET");
+    for found in sessions::find() {
+        if !found.socket.exists() {
+            continue;
+        }
+        let socket = found.socket.to_string_lossy().to_string();
+        let name = found.name.as_deref().unwrap_or("-");
+        let client = context.client(&socket, timeout).without_session();
+        let status = match client.send(serde_json::json!({"type": "STATUS"})) {
+            Ok(response) => response,
+            Err(e) if e.downcast_ref::<std::io::Error>().is_some_and(|e| e.kind() == std::io::ErrorKind::ConnectionRefused) => {
+                stale += 1;
+                continue;
+            }
+            Err(e) => {
+                println!("{:<16} {:<8} {:<8} {:<12} {:<40} -", name, "-", "-", format!("error: {}", e.root_cause()), socket);
+                continue;
+            }
+        };
+
+        let Some(data) = status.data.filter(|_| status.status == "ok") else {
+            // A server answers requests for its sessions only
+            let status = match client.send(serde_json::json!({"type": "LIST"})) {
+                Ok(list) if list.status == "ok" => {
+                    let count = list.data.as_ref()
+                        .and_then(|data| data.get("sessions"))
+                        .and_then(|v| v.as_array())
+                        .map_or(0, |sessions| sessions.len());
+                    format!("server ({})", count)
+                }
+                Ok(list) => format!("error: {}", list.error.unwrap_or_default()),
+                Err(e) => format!("error: {}", e.root_cause()),
+            };
+            println!("{:<16} {:<8} {:<8} {:<12} {:<40} -", name, "-", "-", status, socket);
+            continue;
+        };
+        let field = |key: &str| data.get(key).and_then(|v| v.as_u64()).unwrap_or(0);
+        let status = match data.get("exit_code").and_then(|v| v.as_i64()) {
+            Some(code) => format!("exited ({})", code),
+            None => "running".to_string(),
+        };
+        println!(
+            "{:<16} {:<8} {:<8} {:<12} {:<40} {}",
+            name,
+            field("pid"),
+            format!("{}x{}", field("cols"), field("rows")),
+            status,
+            socket,
+            data.get("command").and_then(|v| v.as_str()).unwrap_or_default(),
+        );
+    }
 
-    let mut reader = BufReader::new(stream);
-    let mut line = String::new();
-    reader.read_line(&mut line)?;
+    if stale > 0 {
+        eprintln!("{} stale session(s) found; remove them with `interminai gc`", stale);
+    }
+    Ok(())
+}
 
-    let response: Response = serde_json::from_str(&line)?;
-    Ok(response)
+/// Subscribe to session events and print them as they arrive.
+/// With a timeout, stop after that long and fail with a `TimeoutError`.
+fn cmd_events(client: &Client) -> Result<()> {
+    let mut stdout = std::io::stdout();
+    // The stream ends after the exit event or when the daemon stops
+    for event in client.subscribe()? {
+        writeln!(stdout, "{}", event?)?;
+        stdout.flush()?;
+    }
+    Ok(())
 }
 
 #[cfg(test)]
@@ -1168,49 +2778,168 @@ mod tests {
     }
 }
 
-fn main() -> Result<()> {
+fn main() {
+    if let Err(e) = run() {
+        if e.downcast_ref::<TimeoutError>().is_some() {
+            eprintln!("Error: {}", e);
+            std::process::exit(EXIT_TIMEOUT);
+        }
+        if e.downcast_ref::<DaemonError>().is_some() {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+        eprintln!("Error: {:?}", e);
+        std::process::exit(1);
+    }
+}
+
+fn run() -> Result<()> {
     let cli = Cli::parse();
+    // 0 means no deadline
+    let timeout = cli.timeout.filter(|&t| t > 0).map(Duration::from_millis);
+    let token = cli.token.or_else(|| std::env::var("INTERMINAI_TOKEN").ok()).filter(|token| !token.is_empty());
+    let caller = cli.client.or_else(|| std::env::var("INTERMINAI_CLIENT").ok()).unwrap_or_default();
+    let context = RequestContext { session: cli.session.as_deref(), token: token.as_deref(), caller: &caller };
 
     match cli.command {
-        Commands::Start { socket, size, emulator, no_daemon, pty_dump, command } => {
-            cmd_start(socket, size, emulator, !no_daemon, pty_dump, command)?;
+        Commands::Start { socket, name, size, emulator, scrollback, output_buffer, no_daemon, json, access, pty_dump, record, record_format, record_max_bytes, record_keep, command } => {
+            let (cols, rows) = parse_terminal_size(&size)?;
+            let access = access.access(token)?;
+            let socket_was_auto_generated = socket.is_none() && name.is_none();
+            let socket_path = match (socket, &name) {
+                (Some(path), _) => path,
+                (None, Some(name)) => {
+                    access.check_named()?;
+                    sessions::create_named(name)?.to_string_lossy().to_string()
+                }
+                (None, None) => auto_generate_socket_path()?,
+            };
+            if socket_was_auto_generated {
+                access.share_socket_dir(&socket_path)?;
+            }
+            let config = DaemonConfig {
+                rows,
+                cols,
+                emulator,
+                scrollback,
+                output_buffer,
+                pty_dump,
+                env: Vec::new(),
+                record: record.map(|path| RecordConfig {
+                    path: path.into(),
+                    format: record_format,
+                    max_bytes: record_max_bytes,
+                    keep: record_keep,
+                    command: command.join(" "),
+                    term: emulator.term(),
+                }),
+            };
+            cmd_start(socket_path, socket_was_auto_generated, name.is_some(), access, config, Started { daemon: !no_daemon, json }, command)?;
+        }
+        Commands::Server { socket, no_daemon, json, access } => {
+            let access = access.access(token)?;
+            let socket_was_auto_generated = socket.is_none();
+            let socket_path = match socket {
+                Some(path) => path,
+                None => auto_generate_socket_path()?,
+            };
+            if socket_was_auto_generated {
+                access.share_socket_dir(&socket_path)?;
+            }
+            daemonize(&socket_path, socket_was_auto_generated, Started { daemon: !no_daemon, json }, |ready| {
+                server::run(&socket_path, socket_was_auto_generated, access, ready)
+            })?;
+        }
+        Commands::New { socket, name, size, emulator, scrollback, output_buffer, command } => {
+            let (cols, rows) = parse_terminal_size(&size)?;
+            let request = serde_json::json!({
+                "type": "NEW",
+                "command": command,
+                "name": name,
+                "rows": rows,
+                "cols": cols,
+                "emulator": emulator.name(),
+                "scrollback": scrollback,
+                "output_buffer": output_buffer
+            });
+
+            let data = context.client(&socket, timeout).without_session().request(request)?;
+            println!("Session: {}", data.get("id").and_then(|v| v.as_u64()).unwrap_or(0));
+            if let Some(name) = data.get("name").and_then(|v| v.as_str()) {
+                println!("Name: {}", name);
+            }
+            println!("PID: {}", data.get("pid").and_then(|v| v.as_i64()).unwrap_or(0));
+        }
+        Commands::List { socket: None } => {
+            cmd_list(context, timeout)?;
         }
-        Commands::Input { socket, text, password } => {
+        Commands::Gc => {
+            for dir in sessions::collect_garbage() {
+                println!("Removed {}", dir.display());
+            }
+        }
+        Commands::List { socket: Some(socket) } => {
+            let request = serde_json::json!({
+                "type": "LIST"
+            });
+
+            let data = context.client(&socket, timeout).without_session().request(request)?;
+            let sessions = data.get("sessions").and_then(|v| v.as_array()).cloned().unwrap_or_default();
+            println!("{:<4} {:<16} {:<8} {:<8} {:<12} COMMAND", "ID", "NAME", "PID", "SIZE", "STATUS");
+            for session in sessions {
+                let field = |key: &str| session.get(key).and_then(|v| v.as_u64()).unwrap_or(0);
+                let status = match session.get("exit_code").and_then(|v| v.as_i64()) {
+                    Some(code) => format!("exited ({})", code),
+                    None => "running".to_string(),
+                };
+                println!(
+                    "{:<4} {:<16} {:<8} {:<8} {:<12} {}",
+                    field("id"),
+                    session.get("name").and_then(|v| v.as_str()).unwrap_or("-"),
+                    field("pid"),
+                    format!("{}x{}", field("cols"), field("rows")),
+                    status,
+                    session.get("command").and_then(|v| v.as_str()).unwrap_or_default(),
+                );
+            }
+        }
+        Commands::Close { socket, target } => {
+            let request = serde_json::json!({
+                "type": "CLOSE",
+                "session": target
+            });
+
+            context.client(&socket, timeout).without_session().request(request)?;
+        }
+        Commands::Input { socket, text, password, keys, paste } => {
+            let client = context.client(&socket.path()?, timeout);
+            if let Some(keys) = keys {
+                return client.input_keys(&keys);
+            }
             // Priority: --password, --text, stdin
             let input = if password {
                 // Fetch current screen to show the password prompt from the application
-                let output_request = serde_json::json!({
-                    "type": "OUTPUT",
-                    "format": "ascii"
-                });
-                let output_response = send_request(&socket, output_request)?;
+                let screen = client.output()?;
 
                 // Show generic guidance, then the cursor line and previous line for context
                 eprintln!("Type your secret or password and press Enter.");
-                if let Some(data) = output_response.data.as_ref() {
-                    let cursor_row = data.get("cursor")
-                        .and_then(|c| c.get("row"))
-                        .and_then(|r| r.as_u64())
-                        .unwrap_or(0) as usize;
-                    if let Some(screen) = data.get("screen").and_then(|s| s.as_str()) {
-                        let lines: Vec<&str> = screen.lines().collect();
-                        // Show previous line if it exists and is non-empty
-                        if cursor_row > 0 {
-                            if let Some(prev_line) = lines.get(cursor_row - 1) {
-                                if !prev_line.trim().is_empty() {
-                                    eprintln!("{}", prev_line);
-                                }
-                            }
-                        }
-                        // Show cursor line
-                        if let Some(prompt_line) = lines.get(cursor_row) {
-                            if !prompt_line.trim().is_empty() {
-                                eprint!("{} ", prompt_line);
-                                std::io::stderr().flush().ok();
-                            }
+                let cursor_row = screen.cursor.row;
+                let lines: Vec<&str> = screen.lines().collect();
+                // Show previous line if it exists and is non-empty
+                if cursor_row > 0 {
+                    if let Some(prev_line) = lines.get(cursor_row - 1) {
+                        if !prev_line.trim().is_empty() {
+                            eprintln!("{}", prev_line);
                         }
                     }
                 }
+                // Show cursor line
+                if let Some(prompt_line) = lines.get(cursor_row) {
+                    if !prompt_line.trim().is_empty() {
+                        eprint!("{} ", prompt_line);
+                        std::io::stderr().flush().ok();
+                    }
+                }
 
                 // Read password with echo disabled, append \r for Enter
                 let password = rpassword::read_password()
@@ -1224,255 +2953,296 @@ fn main() -> Result<()> {
                 buf
             };
 
-            let request = serde_json::json!({
-                "type": "INPUT",
-                "data": input
-            });
-
-            let response = send_request(&socket, request)?;
-
-            if response.status == "error" {
-                eprintln!("Error: {}", response.error.unwrap_or_default());
-                std::process::exit(1);
+            if password {
+                client.input_secret(&input)?;
+            } else if paste {
+                if !client.paste(&input)? {
+                    eprintln!("Warning: the application hasn't turned on bracketed paste, sent the input as typed");
+                }
+            } else {
+                client.input(&input)?;
             }
         }
-        Commands::Output { socket, color, no_color, cursor } => {
+        Commands::Output { socket, color, no_color, cursor, diff, since, scrollback, format } => {
+            let socket = socket.path()?;
+            let client = context.client(&socket, timeout);
+            let baseline = sessions::diff_baseline_path(&socket, context.session, context.caller);
             // Default is color (ansi), --no-color disables it
-            let format = if no_color { "ascii" } else { "ansi" };
+            let format = format.as_deref().unwrap_or(if no_color { "ascii" } else { "ansi" });
             let _ = color; // --color is just for explicitness, default is already color
 
-            let request = serde_json::json!({
-                "type": "OUTPUT",
-                "format": format
-            });
-
-            let response = send_request(&socket, request)?;
-
-            if response.status == "error" {
-                eprintln!("Error: {}", response.error.unwrap_or_default());
-                std::process::exit(1);
+            let options = OutputOptions {
+                format: Some(format.to_string()),
+                since: match since {
+                    Some(generation) => Some(generation),
+                    // A generation never handed out gets every row
+                    None if diff => Some(sessions::read_baseline(&baseline).unwrap_or(u64::MAX)),
+                    None => None,
+                },
+                scrollback,
+            };
+            let screen = client.output_with(&options)?;
+            sessions::remember_baseline(&baseline, screen.generation);
+            let cursor_mode = cursor.as_str();
+            let (cursor_row, cursor_col) = (screen.cursor.row, screen.cursor.col);
+
+            // Print cursor info if requested (convert to 1-based for display)
+            if cursor_mode == "print" || cursor_mode == "both" {
+                println!("Cursor: row {}, col {}", cursor_row + 1, cursor_col + 1);
             }
 
-            if let Some(data) = response.data {
-                let cursor_mode = cursor.as_str();
+            if let Some(history) = &screen.scrollback {
+                print!("{}", history);
+            }
 
-                // Print cursor info if requested (convert to 1-based for display)
-                if cursor_mode == "print" || cursor_mode == "both" {
-                    if let (Some(cursor_row), Some(cursor_col)) = (
-                        data.get("cursor").and_then(|c| c.get("row")).and_then(|v| v.as_u64()),
-                        data.get("cursor").and_then(|c| c.get("col")).and_then(|v| v.as_u64())
-                    ) {
-                        println!("Cursor: row {}, col {}", cursor_row + 1, cursor_col + 1);
-                    }
+            // Diff mode: only the changed rows, prefixed with their 1-based row number
+            if let Some(changed) = &screen.changed_rows {
+                if changed.is_empty() {
+                    println!("No changes (generation {})", screen.generation);
+                } else {
+                    println!("Generation: {}", screen.generation);
                 }
-
-                if let Some(screen) = data.get("screen").and_then(|v| v.as_str()) {
-                    // Apply inverse video if requested
-                    if cursor_mode == "inverse" || cursor_mode == "both" {
-                        if let (Some(cursor_row), Some(cursor_col)) = (
-                            data.get("cursor").and_then(|c| c.get("row")).and_then(|v| v.as_u64()),
-                            data.get("cursor").and_then(|c| c.get("col")).and_then(|v| v.as_u64())
-                        ) {
-                            print!("{}", apply_cursor_inverse(screen, cursor_row as usize, cursor_col as usize));
-                        } else {
-                            print!("{}", screen);
-                        }
+                for entry in changed {
+                    if let Some(styled) = entry.cells.as_ref().or(entry.spans.as_ref()) {
+                        println!("{}: {}", entry.row + 1, styled);
+                        continue;
+                    }
+                    let text = entry.text.as_deref().unwrap_or("");
+                    if entry.row == cursor_row && (cursor_mode == "inverse" || cursor_mode == "both") {
+                        println!("{}: {}", entry.row + 1, apply_cursor_inverse(text, 0, cursor_col));
                     } else {
-                        print!("{}", screen);
+                        println!("{}: {}", entry.row + 1, text);
                     }
                 }
             }
-        }
-        Commands::Status { socket, quiet } => {
-            let request = serde_json::json!({
-                "type": "STATUS",
-                "activity": !quiet
-            });
 
-            let response = send_request(&socket, request)?;
-
-            if response.status == "error" {
-                eprintln!("Error: {}", response.error.unwrap_or_default());
-                std::process::exit(1);
+            // Structured formats: one JSON array per row
+            if let Some(styled_rows) = screen.cells.as_ref().or(screen.spans.as_ref()) {
+                for row in styled_rows {
+                    println!("{}", row);
+                }
             }
 
-            if let Some(data) = response.data {
-                let running = data.get("running").and_then(|v| v.as_bool()).unwrap_or(false);
-
-                if quiet {
-                    // Quiet mode: just exit status
-                    if running {
-                        std::process::exit(0);
-                    } else {
-                        if let Some(exit_code) = data.get("exit_code") {
-                            println!("{}", exit_code);
-                        }
-                        std::process::exit(1);
-                    }
+            if let Some(text) = &screen.text {
+                // Apply inverse video if requested (rendered documents already show the cursor)
+                if (cursor_mode == "inverse" || cursor_mode == "both") && format != "html" && format != "svg" {
+                    print!("{}", apply_cursor_inverse(text, cursor_row, cursor_col));
                 } else {
-                    // Default mode: print all status info
-                    println!("Running: {}", running);
-                    let has_activity = data.get("activity").and_then(|v| v.as_bool()).unwrap_or(false);
-                    println!("Activity: {}", has_activity);
-                    if !running {
-                        if let Some(exit_code) = data.get("exit_code") {
-                            println!("Exit code: {}", exit_code);
-                        }
-                    }
+                    print!("{}", text);
                 }
             }
         }
-        Commands::Wait { socket, quiet } => {
-            let request = serde_json::json!({
-                "type": "WAIT",
-                "activity": !quiet
-            });
-
-            let response = send_request(&socket, request)?;
+        Commands::Status { socket, quiet } => {
+            let client = context.client(&socket.path()?, timeout);
+            let status = client.status(!quiet)?;
 
-            if response.status == "error" {
-                eprintln!("Error: {}", response.error.unwrap_or_default());
-                std::process::exit(1);
+            if quiet {
+                // Quiet mode: just exit status
+                if status.running {
+                    std::process::exit(0);
+                } else {
+                    if let Some(exit_code) = status.exit_code {
+                        println!("{}", exit_code);
+                    }
+                    std::process::exit(1);
+                }
+            } else {
+                // Default mode: print all status info
+                println!("Running: {}", status.running);
+                println!("Activity: {}", status.activity.unwrap_or(false));
+                println!("PID: {}", status.pid);
+                println!("Command: {}", status.command);
+                println!("Size: {}x{}", status.cols, status.rows);
+                if !status.running {
+                    if let Some(exit_code) = status.exit_code {
+                        println!("Exit code: {}", exit_code);
+                    }
+                }
             }
+        }
+        Commands::Wait { socket, quiet, settle } => {
+            let client = context.client(&socket.path()?, timeout);
+            let until = match settle {
+                Some(settle) => WaitFor::Settle(Duration::from_millis(settle)),
+                None if quiet => WaitFor::Exit,
+                None => WaitFor::Activity,
+            };
+            let result = client.wait(until)?;
 
-            if let Some(data) = response.data {
-                if quiet {
+            match until {
+                WaitFor::Settle(_) => {
+                    // Settle mode: report whether the screen went quiet
+                    println!("Settled: {}", result.settled);
+                    println!("Application exited: {}", result.exited);
+                }
+                WaitFor::Exit => {
                     // Quiet mode: just print exit code
-                    if let Some(exit_code) = data.get("exit_code") {
+                    if let Some(exit_code) = result.exit_code {
                         println!("{}", exit_code);
                     }
-                } else {
+                }
+                WaitFor::Activity => {
                     // Default mode: report both terminal activity and exit status
-                    let has_activity = data.get("activity").and_then(|v| v.as_bool()).unwrap_or(false);
-                    let has_exited = data.get("exited").and_then(|v| v.as_bool()).unwrap_or(false);
-                    println!("Terminal activity: {}", if has_activity { "true" } else { "false" });
-                    println!("Application exited: {}", if has_exited { "true" } else { "false" });
+                    println!("Terminal activity: {}", result.activity);
+                    println!("Application exited: {}", result.exited);
                 }
             }
-        }
-        Commands::Kill { socket, signal } => {
-            let request = serde_json::json!({
-                "type": "KILL",
-                "signal": signal
-            });
-
-            let response = send_request(&socket, request)?;
 
-            if response.status == "error" {
-                eprintln!("Error: {}", response.error.unwrap_or_default());
-                std::process::exit(1);
+            if result.timed_out {
+                let what = match until {
+                    WaitFor::Settle(_) => "output to settle",
+                    WaitFor::Exit => "the application to exit",
+                    WaitFor::Activity => "activity",
+                };
+                eprintln!("Error: Timed out after {} ms waiting for {}", timeout.unwrap_or_default().as_millis(), what);
+                std::process::exit(EXIT_TIMEOUT);
             }
         }
+        Commands::Kill { socket, signal } => {
+            context.client(&socket.path()?, timeout).kill(&signal)?;
+        }
         Commands::Stop { socket } => {
-            let request = serde_json::json!({
-                "type": "STOP"
-            });
-
-            let response = send_request(&socket, request)?;
+            context.client(&socket.path()?, timeout).stop()?;
+        }
 
-            if response.status == "error" {
-                eprintln!("Error: {}", response.error.unwrap_or_default());
-                std::process::exit(1);
-            }
+        Commands::Mouse { socket, click, button, drag_to, scroll } => {
+            let client = context.client(&socket.path()?, timeout);
+            let (row, col) = parse_position(&click)?;
+            let button = match button.as_str() {
+                "middle" => MouseButton::Middle,
+                "right" => MouseButton::Right,
+                _ => MouseButton::Left,
+            };
+            let mouse = match (drag_to, scroll) {
+                (Some(to), _) => Mouse::Drag { from: (row, col), to: parse_position(&to)?, button },
+                (None, Some(direction)) => Mouse::Scroll { row, col, up: direction == "up" },
+                (None, None) => Mouse::Click { row, col, button },
+            };
+            client.mouse(mouse)?;
         }
 
         Commands::Resize { socket, size } => {
+            let client = context.client(&socket.path()?, timeout);
             // Parse and validate size
             let (cols, rows) = parse_terminal_size(&size)?;
 
-            let request = serde_json::json!({
-                "type": "RESIZE",
-                "cols": cols,
-                "rows": rows
-            });
+            client.resize(cols, rows)?;
+
+            println!("Terminal resized to {}x{}", cols, rows);
+        }
 
-            let response = send_request(&socket, request)?;
+        Commands::Expect { socket, patterns, target, print_screen, offset } => {
+            // Unlike other commands, expect gives up after 10 seconds by default
+            let timeout = match cli.timeout {
+                None => Some(Duration::from_millis(10000)),
+                Some(_) => timeout,
+            };
+            let socket = socket.path()?;
+            let client = context.client(&socket, timeout);
+
+            let result = if target == "output" {
+                // Each client continues after its own last match, unless told where to start
+                let last_match = sessions::expect_offset_path(&socket, context.session, context.caller);
+                let result = client.expect_output_from(&patterns, offset.or_else(|| sessions::read_baseline(&last_match)).unwrap_or(0))?;
+                if let (None, true, Some(end_offset)) = (offset, result.matched, result.end_offset) {
+                    sessions::remember_baseline(&last_match, end_offset);
+                }
+                result
+            } else {
+                if offset.is_some() {
+                    bail!("--offset only works with --target output");
+                }
+                client.expect(&patterns, ExpectTarget::Screen)?
+            };
 
-            if response.status == "error" {
-                eprintln!("Error: {}", response.error.unwrap_or_default());
-                std::process::exit(1);
+            if result.matched {
+                println!("Matched: {}", result.pattern_index.unwrap_or(0));
+                println!("Match: {}", result.text.as_deref().unwrap_or(""));
+                println!("Position: row {}, col {}", result.row.unwrap_or(0) + 1, result.col.unwrap_or(0) + 1);
             }
 
-            println!("Terminal resized to {}x{}", cols, rows);
-        }
+            if print_screen {
+                print!("{}", result.screen);
+            }
 
-        Commands::Debug { socket, clear } => {
-            let request = serde_json::json!({
-                "type": "DEBUG",
-                "clear": clear
-            });
+            if !result.matched {
+                if result.exited {
+                    eprintln!("Error: Application exited before any pattern matched");
+                    std::process::exit(1);
+                }
+                eprintln!("Error: Timed out after {} ms waiting for a pattern to match", timeout.unwrap_or_default().as_millis());
+                std::process::exit(EXIT_TIMEOUT);
+            }
+        }
 
-            let response = send_request(&socket, request)?;
+        Commands::Screenshot { socket, output } => {
+            let image = context.client(&socket.path()?, timeout).screenshot()?;
 
-            if response.status == "error" {
-                eprintln!("Error: {}", response.error.unwrap_or_default());
-                std::process::exit(1);
+            if output == "-" {
+                std::io::stdout().write_all(&image.png)?;
+            } else {
+                fs::write(&output, &image.png).with_context(|| format!("Failed to write {}", output))?;
+                println!("Screenshot: {} ({}x{})", output, image.width, image.height);
             }
+        }
+        Commands::Read { socket, offset, format, max_bytes, follow } => {
+            let client = context.client(&socket.path()?, None);
+            cmd_read(&client, offset, &format, max_bytes, follow, timeout)?;
+        }
 
-            if let Some(data) = response.data {
-                let unhandled = data.get("unhandled").and_then(|v| v.as_array());
-                let dropped = data.get("dropped").and_then(|v| v.as_u64()).unwrap_or(0);
+        Commands::Events { socket } => {
+            cmd_events(&context.client(&socket.path()?, timeout))?;
+        }
 
-                if let Some(entries) = unhandled {
-                    if entries.is_empty() {
-                        println!("No unhandled escape sequences");
-                    } else {
-                        println!("Unhandled escape sequences:");
-                        for entry in entries {
-                            let seq = entry.get("sequence").and_then(|v| v.as_str()).unwrap_or("?");
-                            let hex = entry.get("raw_hex").and_then(|v| v.as_str()).unwrap_or("?");
-                            println!("  {} ({})", seq, hex);
-                        }
-                    }
-                }
+        Commands::Render { file, emulator, size, format, at_offset, at_time } => {
+            cmd_render(&file, emulator, size.as_deref(), &format, at_offset, at_time)?;
+        }
 
-                if dropped > 0 {
-                    println!("Dropped: {} (buffer overflow)", dropped);
-                }
+        Commands::Play { file, emulator } => {
+            tape::play(&file, emulator)?;
+        }
 
-                // Display termios info
-                if let Some(termios) = data.get("termios") {
-                    if let Some(error) = termios.get("error").and_then(|v| v.as_str()) {
-                        println!("Termios: {}", error);
-                    } else {
-                        println!("Termios:");
-
-                        // Mode
-                        let mode = termios.get("mode").and_then(|v| v.as_str()).unwrap_or("?");
-                        println!("  Mode: {}", mode);
-
-                        // Flags
-                        if let Some(flags) = termios.get("flags").and_then(|v| v.as_array()) {
-                            let flag_strs: Vec<&str> = flags.iter()
-                                .filter_map(|v| v.as_str())
-                                .collect();
-                            if !flag_strs.is_empty() {
-                                println!("  Flags: {}", flag_strs.join(" "));
-                            }
-                        }
+        Commands::Batch { socket, stop_on_error, file } => {
+            cmd_batch(&context.client(&socket.path()?, timeout), file.as_deref(), stop_on_error)?;
+        }
 
-                        // Hex values
-                        if let Some(hex) = termios.get("hex") {
-                            let iflag = hex.get("iflag").and_then(|v| v.as_str()).unwrap_or("?");
-                            let oflag = hex.get("oflag").and_then(|v| v.as_str()).unwrap_or("?");
-                            let lflag = hex.get("lflag").and_then(|v| v.as_str()).unwrap_or("?");
-                            let cflag = hex.get("cflag").and_then(|v| v.as_str()).unwrap_or("?");
-                            println!("  Hex: iflag={} oflag={} lflag={} cflag={}", iflag, oflag, lflag, cflag);
-                        }
+        Commands::Hello { socket, require } => {
+            cmd_hello(&context.client(&socket.path()?, timeout), &require)?;
+        }
 
-                        // Control characters
-                        if let Some(c_cc) = termios.get("c_cc") {
-                            let vintr = c_cc.get("VINTR").and_then(|v| v.as_str()).unwrap_or("?");
-                            let veof = c_cc.get("VEOF").and_then(|v| v.as_str()).unwrap_or("?");
-                            let verase = c_cc.get("VERASE").and_then(|v| v.as_str()).unwrap_or("?");
-                            let vkill = c_cc.get("VKILL").and_then(|v| v.as_str()).unwrap_or("?");
-                            let vsusp = c_cc.get("VSUSP").and_then(|v| v.as_str()).unwrap_or("?");
-                            let vquit = c_cc.get("VQUIT").and_then(|v| v.as_str()).unwrap_or("?");
-                            println!("  c_cc: VINTR={} VEOF={} VERASE={} VKILL={} VSUSP={} VQUIT={}",
-                                vintr, veof, verase, vkill, vsusp, vquit);
-                        }
-                    }
+        Commands::Debug { socket, clear } => {
+            let debug = context.client(&socket.path()?, timeout).debug(clear)?;
+
+            if debug.unhandled.is_empty() {
+                println!("No unhandled escape sequences");
+            } else {
+                println!("Unhandled escape sequences:");
+                for entry in &debug.unhandled {
+                    println!("  {} ({})", entry.sequence, entry.raw_hex);
+                }
+            }
+
+            if debug.dropped > 0 {
+                println!("Dropped: {} (buffer overflow)", debug.dropped);
+            }
+
+            // Display termios info
+            let termios = &debug.termios;
+            if let Some(error) = &termios.error {
+                println!("Termios: {}", error);
+            } else {
+                println!("Termios:");
+                println!("  Mode: {}", termios.mode);
+                if !termios.flags.is_empty() {
+                    println!("  Flags: {}", termios.flags.join(" "));
                 }
+
+                let field = |map: &std::collections::BTreeMap<String, String>, key: &str| map.get(key).cloned().unwrap_or_else(|| "?".to_string());
+                println!("  Hex: iflag={} oflag={} lflag={} cflag={}",
+                    field(&termios.hex, "iflag"), field(&termios.hex, "oflag"), field(&termios.hex, "lflag"), field(&termios.hex, "cflag"));
+                println!("  c_cc: VINTR={} VEOF={} VERASE={} VKILL={} VSUSP={} VQUIT={}",
+                    field(&termios.c_cc, "VINTR"), field(&termios.c_cc, "VEOF"), field(&termios.c_cc, "VERASE"),
+                    field(&termios.c_cc, "VKILL"), field(&termios.c_cc, "VSUSP"), field(&termios.c_cc, "VQUIT"));
             }
         }
     }
@@ -0,0 +1,114 @@
+// Mouse events
+//
+// MOUSE requests are turned into the reports a terminal sends when the mouse
+// is used, in whichever encoding the application asked for. Applications
+// only get them if they turned mouse reporting on, so there is nothing to
+// send otherwise.
+
+use crate::terminal::{MouseEncoding, MouseMode, MouseTracking};
+
+/// Button code of the first wheel button; scrolling down is the next one
+const WHEEL_UP: u32 = 64;
+/// Added to the button code for motion with a button held
+const MOTION: u32 = 32;
+/// Button code of a release in the X10 and UTF-8 encodings
+const RELEASE: u32 = 3;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Button {
+    Left,
+    Middle,
+    Right,
+}
+
+impl Button {
+    pub fn parse(name: &str) -> Option<Button> {
+        match name {
+            "left" => Some(Button::Left),
+            "middle" => Some(Button::Middle),
+            "right" => Some(Button::Right),
+            _ => None,
+        }
+    }
+
+    fn code(self) -> u32 {
+        match self {
+            Button::Left => 0,
+            Button::Middle => 1,
+            Button::Right => 2,
+        }
+    }
+}
+
+/// What the mouse does at a position (0-based row and column)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Action {
+    /// Press and release a button
+    Click(Button),
+    /// Press a button, move to (row, col) and release it there
+    Drag { button: Button, to: (usize, usize) },
+    /// Turn the wheel one step, up or down
+    Scroll { up: bool },
+}
+
+/// The reports `action` at `row`, `col` makes in `mode`
+pub fn encode(action: Action, row: usize, col: usize, mode: MouseMode) -> Result<Vec<u8>, String> {
+    if mode.tracking == MouseTracking::Off {
+        return Err("The application hasn't turned on mouse reporting".to_string());
+    }
+
+    // X10 mode never reports releases
+    let releases = mode.tracking >= MouseTracking::Click;
+    let mut bytes = Vec::new();
+    let mut push = |code: u32, (row, col): (usize, usize), release: bool| -> Result<(), String> {
+        bytes.extend(report(code, row, col, release, mode.encoding)?);
+        Ok(())
+    };
+    match action {
+        Action::Click(button) => {
+            push(button.code(), (row, col), false)?;
+            if releases {
+                push(button.code(), (row, col), true)?;
+            }
+        }
+        Action::Drag { button, to } => {
+            push(button.code(), (row, col), false)?;
+            // In click mode the application only learns where the button went
+            // up, and in X10 mode only where it went down
+            if mode.tracking >= MouseTracking::Drag {
+                push(button.code() + MOTION, to, false)?;
+            }
+            if releases {
+                push(button.code(), to, true)?;
+            }
+        }
+        // Wheel buttons are never released
+        Action::Scroll { up } => push(WHEEL_UP + !up as u32, (row, col), false)?,
+    }
+    Ok(bytes)
+}
+
+/// One mouse report
+fn report(code: u32, row: usize, col: usize, release: bool, encoding: MouseEncoding) -> Result<Vec<u8>, String> {
+    if encoding == MouseEncoding::Sgr {
+        let end = if release { 'm' } else { 'M' };
+        return Ok(format!("\x1b[<{};{};{}{}", code, col + 1, row + 1, end).into_bytes());
+    }
+
+    // The other encodings can't tell which button went up
+    let code = if release { RELEASE } else { code };
+    let mut bytes = b"\x1b[M".to_vec();
+    for value in [code, col as u32 + 1, row as u32 + 1] {
+        let value = value + 32;
+        match encoding {
+            MouseEncoding::X10 if value <= 0xff => bytes.push(value as u8),
+            MouseEncoding::Utf8 if value < 0x800 => {
+                let c = char::from_u32(value).expect("below the surrogate range");
+                bytes.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes());
+            }
+            MouseEncoding::X10 => return Err("X10 mouse reports only reach row and column 223".to_string()),
+            _ => return Err("UTF-8 mouse reports only reach row and column 2015".to_string()),
+        }
+    }
+    Ok(bytes)
+}
@@ -0,0 +1,173 @@
+// Raw PTY output log
+//
+// Keeps the most recent bytes read from the PTY, addressed by their absolute
+// offset in the output stream. This lets clients match against output that
+// was never rendered as-is (or has already scrolled off the screen).
+
+use std::collections::VecDeque;
+use vte::Perform;
+
+/// Default number of output bytes kept in memory
+pub const DEFAULT_CAPACITY: usize = 1024 * 1024;
+
+/// Bounded buffer of PTY output, oldest bytes are dropped first
+pub struct OutputLog {
+    buf: VecDeque<u8>,
+    capacity: usize,
+    /// Absolute stream offset of the first byte in `buf`
+    start: u64,
+}
+
+impl OutputLog {
+    pub fn new(capacity: usize) -> Self {
+        OutputLog {
+            buf: VecDeque::new(),
+            capacity,
+            start: 0,
+        }
+    }
+
+    /// Append bytes, dropping the oldest ones if the log is full
+    pub fn push(&mut self, bytes: &[u8]) {
+        self.buf.extend(bytes);
+        if self.buf.len() > self.capacity {
+            let excess = self.buf.len() - self.capacity;
+            self.buf.drain(..excess);
+            self.start += excess as u64;
+        }
+    }
+
+    /// Most bytes kept at a time
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Offset just past the newest byte (total bytes ever pushed)
+    pub fn end_offset(&self) -> u64 {
+        self.start + self.buf.len() as u64
+    }
+
+    /// Get all bytes from `offset` to the end of the log.
+    /// Returns the offset actually used, which is later than requested if
+    /// the requested bytes have already been dropped.
+    pub fn read_from(&self, offset: u64) -> (u64, Vec<u8>) {
+        self.read(offset, usize::MAX)
+    }
+
+    /// Like `read_from`, but return at most `max_bytes` bytes
+    pub fn read(&self, offset: u64, max_bytes: usize) -> (u64, Vec<u8>) {
+        let offset = offset.clamp(self.start, self.end_offset());
+        let skip = (offset - self.start) as usize;
+        (offset, self.buf.iter().skip(skip).take(max_bytes).copied().collect())
+    }
+}
+
+/// Length of the longest prefix of `bytes` that doesn't end in the middle of
+/// a UTF-8 character
+pub fn utf8_complete_len(bytes: &[u8]) -> usize {
+    // A character is at most 4 bytes, so only the last 3 can be incomplete
+    for back in 1..=bytes.len().min(3) {
+        let byte = bytes[bytes.len() - back];
+        if byte & 0xC0 == 0x80 {
+            // Continuation byte - keep looking for the lead byte
+            continue;
+        }
+        let char_len = match byte {
+            0xF0..=0xF7 => 4,
+            0xE0..=0xEF => 3,
+            0xC0..=0xDF => 2,
+            _ => 1,
+        };
+        return if char_len > back { bytes.len() - back } else { bytes.len() };
+    }
+    bytes.len()
+}
+
+/// Collects printable text, dropping escape sequences
+struct TextCollector {
+    text: String,
+    /// For each byte of `text`, the index just past the input byte that produced it
+    source_ends: Vec<usize>,
+    position: usize,
+    /// Index just past the last complete character or escape sequence
+    complete: usize,
+}
+
+impl TextCollector {
+    fn new() -> Self {
+        TextCollector {
+            text: String::new(),
+            source_ends: Vec::new(),
+            position: 0,
+            complete: 0,
+        }
+    }
+
+    fn push(&mut self, c: char) {
+        self.text.push(c);
+        for _ in 0..c.len_utf8() {
+            self.source_ends.push(self.position);
+        }
+    }
+
+    fn collect(&mut self, bytes: &[u8]) {
+        let mut parser = vte::Parser::new();
+        for (i, byte) in bytes.iter().enumerate() {
+            self.position = i + 1;
+            parser.advance(self, *byte);
+        }
+    }
+}
+
+impl Perform for TextCollector {
+    fn print(&mut self, c: char) {
+        self.push(c);
+        self.complete = self.position;
+    }
+
+    fn execute(&mut self, byte: u8) {
+        match byte {
+            b'\n' | b'\t' => self.push(byte as char),
+            _ => {}
+        }
+        self.complete = self.position;
+    }
+
+    fn csi_dispatch(&mut self, _params: &vte::Params, _intermediates: &[u8], _ignore: bool, _action: char) {
+        self.complete = self.position;
+    }
+
+    fn esc_dispatch(&mut self, _intermediates: &[u8], _ignore: bool, _byte: u8) {
+        self.complete = self.position;
+    }
+
+    fn osc_dispatch(&mut self, _params: &[&[u8]], _bell_terminated: bool) {
+        self.complete = self.position;
+    }
+
+    fn unhook(&mut self) {
+        self.complete = self.position;
+    }
+}
+
+/// Strip ANSI escape sequences and control characters (except newline and tab).
+///
+/// Also returns how many input bytes were fully processed: a character or
+/// escape sequence cut off at the end of `bytes` is left out of the text and
+/// not counted, so it can be processed once the rest of it is available.
+pub fn strip_ansi(bytes: &[u8]) -> (String, usize) {
+    let mut collector = TextCollector::new();
+    collector.collect(bytes);
+    (collector.text, collector.complete)
+}
+
+/// Strip ANSI escape sequences and control characters (except newline and tab).
+///
+/// Returns the text along with, for each byte of the text, the index just past
+/// the input byte that produced it. This maps matches in the text back to
+/// positions in the raw stream.
+pub fn strip_ansi_with_offsets(bytes: &[u8]) -> (String, Vec<usize>) {
+    let mut collector = TextCollector::new();
+    collector.collect(bytes);
+    (collector.text, collector.source_ends)
+}
@@ -0,0 +1,55 @@
+// Protocol messages
+//
+// Clients and the daemon exchange one JSON object per line over the socket
+// (see docs/PROTOCOL.md). A request names its type and carries its own
+// fields next to it; the response echoes the request's id.
+
+use serde::{Deserialize, Serialize};
+
+/// A request as the daemon receives it
+#[derive(Deserialize)]
+pub struct Request {
+    #[serde(rename = "type")]
+    pub req_type: String,
+    /// Optional client-chosen request ID, echoed back in the response
+    #[serde(default)]
+    pub id: Option<serde_json::Value>,
+    #[serde(flatten)]
+    pub data: serde_json::Value,
+}
+
+/// The daemon's answer to one request
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Response {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<serde_json::Value>,
+    pub status: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data: Option<serde_json::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl Response {
+    pub fn ok(data: serde_json::Value) -> Self {
+        Response {
+            id: None,
+            status: "ok".to_string(),
+            data: Some(data),
+            error: None,
+        }
+    }
+
+    pub fn error(msg: String) -> Self {
+        Response {
+            id: None,
+            status: "error".to_string(),
+            data: None,
+            error: Some(msg),
+        }
+    }
+
+    pub fn is_ok(&self) -> bool {
+        self.status == "ok"
+    }
+}
@@ -0,0 +1,413 @@
+// Session recording
+//
+// Writes timestamped output, input and resize events to a file so a session
+// can be replayed later: as asciicast v2 (asciinema, agg for GIFs) or in the
+// advanced timing format of util-linux script (scriptreplay).
+//
+// Files are capped in size: once a file is full it is rotated to FILE.1
+// (FILE.1 to FILE.2 and so on) and a new, self-contained recording starts.
+//
+// Recordings (and raw --pty-dump files) can be read back with `load`.
+
+use anyhow::{bail, Context, Result};
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use crate::output_log::utf8_complete_len;
+
+/// Default size at which a recording file is rotated
+pub const DEFAULT_MAX_BYTES: u64 = 64 * 1024 * 1024;
+
+/// Default number of rotated recording files kept
+pub const DEFAULT_KEEP: usize = 2;
+
+/// Recording file format
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum RecordFormat {
+    /// asciicast v2, one JSON event per line (asciinema play, agg)
+    #[default]
+    Asciicast,
+    /// util-linux script log with timing in FILE.timing (scriptreplay -T FILE.timing -B FILE)
+    Script,
+}
+
+/// Recording settings given to `start`
+pub struct RecordConfig {
+    pub path: PathBuf,
+    pub format: RecordFormat,
+    pub max_bytes: u64,
+    pub keep: usize,
+    /// Command line of the recorded application
+    pub command: String,
+    /// TERM the application runs with
+    pub term: &'static str,
+}
+
+/// Timing file that goes with a script log
+fn timing_path(path: &Path) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(".timing");
+    PathBuf::from(name)
+}
+
+/// Path of the `n`th rotated file
+fn rotated_path(path: &Path, n: usize) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(format!(".{}", n));
+    PathBuf::from(name)
+}
+
+/// Format a Unix timestamp as "YYYY-MM-DD HH:MM:SS+00:00"
+pub fn utc_datetime(secs: u64) -> String {
+    // Days since the epoch to a civil date (Howard Hinnant's algorithm)
+    let days = (secs / 86400) as i64 + 719468;
+    let era = days.div_euclid(146097);
+    let doe = days.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    let time = secs % 86400;
+    format!("{:04}-{:02}-{:02} {:02}:{:02}:{:02}+00:00", year, month, day, time / 3600, time / 60 % 60, time % 60)
+}
+
+/// Writes session events to a recording file
+pub struct Recorder {
+    config: RecordConfig,
+    /// Current terminal size (rows, cols), for the header of the next file
+    size: (usize, usize),
+    data: File,
+    /// Timing file (script format only)
+    timing: Option<File>,
+    /// Bytes written to the current file (and its timing file)
+    written: u64,
+    /// When the current file was started
+    started: Instant,
+    /// Time of the last event in the current file
+    last_event: Duration,
+    /// Trailing bytes of an incomplete UTF-8 character, per stream (asciicast only)
+    pending_output: Vec<u8>,
+    pending_input: Vec<u8>,
+}
+
+impl Recorder {
+    /// Start recording to a new file, replacing any existing one
+    pub fn create(config: RecordConfig, rows: usize, cols: usize) -> Result<Self> {
+        let (data, timing) = Self::open_files(&config)?;
+        let mut recorder = Recorder {
+            config,
+            size: (rows, cols),
+            data,
+            timing,
+            written: 0,
+            started: Instant::now(),
+            last_event: Duration::ZERO,
+            pending_output: Vec::new(),
+            pending_input: Vec::new(),
+        };
+        recorder.write_header();
+        Ok(recorder)
+    }
+
+    fn open_files(config: &RecordConfig) -> Result<(File, Option<File>)> {
+        let data = File::create(&config.path)
+            .with_context(|| format!("Failed to create recording file {}", config.path.display()))?;
+        let timing = match config.format {
+            RecordFormat::Script => {
+                let path = timing_path(&config.path);
+                Some(File::create(&path).with_context(|| format!("Failed to create timing file {}", path.display()))?)
+            }
+            RecordFormat::Asciicast => None,
+        };
+        Ok((data, timing))
+    }
+
+    fn write_data(&mut self, bytes: &[u8]) {
+        if self.data.write_all(bytes).is_ok() {
+            self.written += bytes.len() as u64;
+        }
+    }
+
+    fn write_timing(&mut self, line: &str) {
+        if let Some(timing) = self.timing.as_mut() {
+            if timing.write_all(line.as_bytes()).is_ok() {
+                self.written += line.len() as u64;
+            }
+        }
+    }
+
+    fn write_header(&mut self) {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+        let (rows, cols) = self.size;
+        match self.config.format {
+            RecordFormat::Asciicast => {
+                let header = serde_json::json!({
+                    "version": 2,
+                    "width": cols,
+                    "height": rows,
+                    "timestamp": now,
+                    "command": self.config.command,
+                    "env": {"TERM": self.config.term},
+                });
+                self.write_data(format!("{}\n", header).as_bytes());
+            }
+            RecordFormat::Script => {
+                // scriptreplay skips the first line of the log
+                let date = utc_datetime(now);
+                let started = format!(
+                    "Script started on {} [COMMAND=\"{}\" TERM=\"{}\" COLUMNS=\"{}\" LINES=\"{}\"]\n",
+                    date, self.config.command, self.config.term, cols, rows
+                );
+                self.write_data(started.as_bytes());
+                let info = format!(
+                    "H 0.000000 START_TIME {}\nH 0.000000 COMMAND {}\nH 0.000000 TERM {}\n\
+                     H 0.000000 COLUMNS {}\nH 0.000000 LINES {}\n",
+                    date, self.config.command, self.config.term, cols, rows
+                );
+                self.write_timing(&info);
+            }
+        }
+    }
+
+    /// Move full files out of the way and start a new one
+    fn rotate(&mut self) {
+        let path = self.config.path.clone();
+        let script = self.config.format == RecordFormat::Script;
+        if self.config.keep > 0 {
+            for n in (1..self.config.keep).rev() {
+                let _ = fs::rename(rotated_path(&path, n), rotated_path(&path, n + 1));
+                if script {
+                    let _ = fs::rename(timing_path(&rotated_path(&path, n)), timing_path(&rotated_path(&path, n + 1)));
+                }
+            }
+            let _ = fs::rename(&path, rotated_path(&path, 1));
+            if script {
+                let _ = fs::rename(timing_path(&path), timing_path(&rotated_path(&path, 1)));
+            }
+        }
+
+        // If the new file can't be created, keep appending to the old one
+        if let Ok((data, timing)) = Self::open_files(&self.config) {
+            self.data = data;
+            self.timing = timing;
+            self.written = 0;
+            self.started = Instant::now();
+            self.last_event = Duration::ZERO;
+            self.write_header();
+        }
+    }
+
+    /// Record an event of the given asciicast type ("o", "i" or "r")
+    fn event(&mut self, kind: &str, bytes: &[u8]) {
+        if self.written >= self.config.max_bytes {
+            self.rotate();
+        }
+        let now = self.started.elapsed();
+        match self.config.format {
+            RecordFormat::Asciicast => {
+                // Events hold strings, so hold back a character split across reads
+                let pending = match kind {
+                    "o" => &mut self.pending_output,
+                    "i" => &mut self.pending_input,
+                    _ => return self.write_asciicast(now, kind, &String::from_utf8_lossy(bytes)),
+                };
+                pending.extend_from_slice(bytes);
+                let complete = utf8_complete_len(pending);
+                let text = String::from_utf8_lossy(&pending[..complete]).into_owned();
+                pending.drain(..complete);
+                if !text.is_empty() {
+                    self.write_asciicast(now, kind, &text);
+                }
+            }
+            RecordFormat::Script => {
+                let delay = now.saturating_sub(self.last_event);
+                let line = match kind {
+                    "r" => format!("S {}.{:06} SIGWINCH {}\n", delay.as_secs(), delay.subsec_micros(), String::from_utf8_lossy(bytes)),
+                    _ => {
+                        self.write_data(bytes);
+                        format!("{} {}.{:06} {}\n", kind.to_uppercase(), delay.as_secs(), delay.subsec_micros(), bytes.len())
+                    }
+                };
+                self.write_timing(&line);
+            }
+        }
+        self.last_event = now;
+    }
+
+    fn write_asciicast(&mut self, time: Duration, kind: &str, text: &str) {
+        let line = format!("[{:.6}, \"{}\", {}]\n", time.as_secs_f64(), kind, serde_json::json!(text));
+        self.write_data(line.as_bytes());
+    }
+
+    /// Record output from the application
+    pub fn output(&mut self, bytes: &[u8]) {
+        self.event("o", bytes);
+    }
+
+    /// Record input sent to the application. Secret input (typed while the
+    /// terminal doesn't echo, e.g. at a password prompt) is masked.
+    pub fn input(&mut self, bytes: &[u8], secret: bool) {
+        if secret {
+            let masked: Vec<u8> = bytes.iter().map(|&b| if b == b'\r' || b == b'\n' { b } else { b'*' }).collect();
+            self.event("i", &masked);
+        } else {
+            self.event("i", bytes);
+        }
+    }
+
+    /// Record a terminal resize
+    pub fn resize(&mut self, rows: usize, cols: usize) {
+        self.size = (rows, cols);
+        let size = match self.config.format {
+            RecordFormat::Asciicast => format!("{}x{}", cols, rows),
+            RecordFormat::Script => format!("ROWS={} COLS={}", rows, cols),
+        };
+        self.event("r", size.as_bytes());
+    }
+}
+
+/// Something that happened in a recorded session
+pub enum Recorded {
+    /// The application wrote to the terminal
+    Output(Vec<u8>),
+    /// The terminal was resized
+    Resize { rows: usize, cols: usize },
+}
+
+/// A recording read back from disk
+pub struct Recording {
+    /// Terminal size (rows, cols) at the start, if the recording has it
+    pub size: Option<(usize, usize)>,
+    /// Whether event times are known (false for raw output dumps)
+    pub timed: bool,
+    /// Events with their time in seconds since the start
+    pub events: Vec<(f64, Recorded)>,
+}
+
+/// Read a recording: asciicast, a script log (with FILE.timing next to it),
+/// or anything else as raw output such as a --pty-dump file
+pub fn load(path: &Path) -> Result<Recording> {
+    let bytes = fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+
+    let first_line = bytes.split(|&b| b == b'\n').next().unwrap_or_default();
+    if let Ok(header) = serde_json::from_slice::<serde_json::Value>(first_line) {
+        if header.get("version").and_then(|v| v.as_u64()) == Some(2) {
+            return load_asciicast(&bytes, &header);
+        }
+    }
+
+    let timing = timing_path(path);
+    if first_line.starts_with(b"Script started on ") && timing.exists() {
+        let timing = fs::read_to_string(&timing).with_context(|| format!("Failed to read {}", timing.display()))?;
+        return load_script(&bytes[(first_line.len() + 1).min(bytes.len())..], &timing);
+    }
+
+    Ok(Recording {
+        size: None,
+        timed: false,
+        events: vec![(0.0, Recorded::Output(bytes))],
+    })
+}
+
+/// Parse "COLSxROWS" into (rows, cols)
+fn parse_size(size: &str) -> Option<(usize, usize)> {
+    let (cols, rows) = size.split_once('x')?;
+    Some((rows.parse().ok()?, cols.parse().ok()?))
+}
+
+fn load_asciicast(bytes: &[u8], header: &serde_json::Value) -> Result<Recording> {
+    let size = match (header.get("height").and_then(|v| v.as_u64()), header.get("width").and_then(|v| v.as_u64())) {
+        (Some(rows), Some(cols)) => Some((rows as usize, cols as usize)),
+        _ => None,
+    };
+
+    let mut events = Vec::new();
+    for (index, line) in String::from_utf8_lossy(bytes).lines().enumerate().skip(1) {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let event: (f64, String, String) = serde_json::from_str(line)
+            .with_context(|| format!("Invalid asciicast event on line {}", index + 1))?;
+        match event.1.as_str() {
+            "o" => events.push((event.0, Recorded::Output(event.2.into_bytes()))),
+            "r" => {
+                let (rows, cols) = parse_size(&event.2)
+                    .with_context(|| format!("Invalid resize event on line {}", index + 1))?;
+                events.push((event.0, Recorded::Resize { rows, cols }));
+            }
+            // Input and markers don't change the screen
+            _ => {}
+        }
+    }
+    Ok(Recording { size, timed: true, events })
+}
+
+fn load_script(log: &[u8], timing: &str) -> Result<Recording> {
+    let mut events = Vec::new();
+    let (mut rows, mut cols) = (None, None);
+    let mut position = 0;
+    let mut time = 0.0;
+    for (index, line) in timing.lines().enumerate() {
+        let fields: Vec<&str> = line.splitn(3, ' ').collect();
+        if fields.len() < 2 {
+            continue;
+        }
+        let invalid = || format!("Invalid timing entry on line {}", index + 1);
+        match fields.as_slice() {
+            ["H", _, header] => {
+                // Header entries carry the initial size
+                let (name, value) = header.split_once(' ').unwrap_or((header, ""));
+                match name {
+                    "LINES" => rows = value.parse().ok(),
+                    "COLUMNS" => cols = value.parse().ok(),
+                    _ => {}
+                }
+            }
+            [kind @ ("O" | "I"), delay, len] => {
+                time += delay.parse::<f64>().with_context(invalid)?;
+                let len: usize = len.parse().with_context(invalid)?;
+                let end = position + len;
+                if end > log.len() {
+                    bail!("Timing file describes more data than the log has (line {})", index + 1);
+                }
+                if *kind == "O" {
+                    events.push((time, Recorded::Output(log[position..end].to_vec())));
+                }
+                position = end;
+            }
+            ["S", delay, signal] => {
+                time += delay.parse::<f64>().with_context(invalid)?;
+                let mut size = (None, None);
+                for field in signal.split(' ') {
+                    if let Some(value) = field.strip_prefix("ROWS=") {
+                        size.0 = value.parse().ok();
+                    } else if let Some(value) = field.strip_prefix("COLS=") {
+                        size.1 = value.parse().ok();
+                    }
+                }
+                if let (Some(rows), Some(cols)) = size {
+                    events.push((time, Recorded::Resize { rows, cols }));
+                }
+            }
+            // Classic timing files have no type, just "delay bytes" (all output)
+            [delay, len] => {
+                let delay: f64 = delay.parse().with_context(invalid)?;
+                let len: usize = len.parse().with_context(invalid)?;
+                time += delay;
+                let end = (position + len).min(log.len());
+                events.push((time, Recorded::Output(log[position..end].to_vec())));
+                position = end;
+            }
+            _ => bail!(invalid()),
+        }
+    }
+    let size = match (rows, cols) {
+        (Some(rows), Some(cols)) => Some((rows, cols)),
+        _ => None,
+    };
+    Ok(Recording { size, timed: true, events })
+}
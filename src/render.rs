@@ -0,0 +1,296 @@
+// Screen rendering to HTML and SVG
+//
+// Turns the styled cells of a terminal screen into a self-contained document
+// that can be attached to bug reports or viewed in a browser. Colors are
+// resolved with the xterm palette, so the result looks the same everywhere.
+
+use crate::terminal::{CellColor, StyledCell};
+
+/// An RGB color
+pub type Rgb = (u8, u8, u8);
+
+/// Default foreground color
+pub const DEFAULT_FG: Rgb = (0xe5, 0xe5, 0xe5);
+
+/// Default background color
+pub const DEFAULT_BG: Rgb = (0x00, 0x00, 0x00);
+
+/// xterm's 16 standard colors
+const ANSI_COLORS: [Rgb; 16] = [
+    (0x00, 0x00, 0x00), (0xcd, 0x00, 0x00), (0x00, 0xcd, 0x00), (0xcd, 0xcd, 0x00),
+    (0x00, 0x00, 0xee), (0xcd, 0x00, 0xcd), (0x00, 0xcd, 0xcd), (0xe5, 0xe5, 0xe5),
+    (0x7f, 0x7f, 0x7f), (0xff, 0x00, 0x00), (0x00, 0xff, 0x00), (0xff, 0xff, 0x00),
+    (0x5c, 0x5c, 0xff), (0xff, 0x00, 0xff), (0x00, 0xff, 0xff), (0xff, 0xff, 0xff),
+];
+
+/// Font size in pixels
+const FONT_SIZE: usize = 14;
+
+/// Width of a cell in SVG output, in pixels
+const CELL_WIDTH: f64 = 8.4;
+
+/// Height of a cell in SVG output, in pixels
+const CELL_HEIGHT: usize = 17;
+
+/// Space around the screen, in pixels
+const PADDING: usize = 8;
+
+const FONT_FAMILY: &str = "'DejaVu Sans Mono', Menlo, Consolas, monospace";
+
+/// Resolve a color using the xterm palette
+pub fn color_rgb(color: CellColor, default: Rgb) -> Rgb {
+    match color {
+        CellColor::Default => default,
+        CellColor::Named(index) => ANSI_COLORS[index as usize % 16],
+        CellColor::Indexed(index) => indexed_rgb(index),
+        CellColor::Rgb(r, g, b) => (r, g, b),
+    }
+}
+
+/// Color from the 256-color palette
+fn indexed_rgb(index: u8) -> Rgb {
+    match index {
+        0..=15 => ANSI_COLORS[index as usize],
+        16..=231 => {
+            // 6x6x6 color cube
+            let level = |n: u8| if n == 0 { 0 } else { 55 + n * 40 };
+            let n = index - 16;
+            (level(n / 36), level((n / 6) % 6), level(n % 6))
+        }
+        _ => {
+            // Grayscale ramp
+            let gray = 8 + (index - 232) * 10;
+            (gray, gray, gray)
+        }
+    }
+}
+
+fn hex(color: Rgb) -> String {
+    format!("#{:02x}{:02x}{:02x}", color.0, color.1, color.2)
+}
+
+/// How a cell is drawn once colors and attributes are resolved
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct Appearance {
+    pub fg: Rgb,
+    /// None if the cell has the default background
+    pub bg: Option<Rgb>,
+    pub bold: bool,
+    pub dim: bool,
+    pub italic: bool,
+    pub underline: bool,
+    pub strikethrough: bool,
+}
+
+/// Resolve the colors a cell is drawn with, applying inverse and hidden.
+/// The cursor is drawn as an inverted cell.
+pub fn appearance(cell: &StyledCell, is_cursor: bool) -> Appearance {
+    let mut fg = color_rgb(cell.fg, DEFAULT_FG);
+    let mut bg = match cell.bg {
+        CellColor::Default => None,
+        color => Some(color_rgb(color, DEFAULT_BG)),
+    };
+    if cell.flags.inverse != is_cursor {
+        let old_fg = fg;
+        fg = bg.unwrap_or(DEFAULT_BG);
+        bg = Some(old_fg);
+    }
+    if cell.flags.hidden {
+        fg = bg.unwrap_or(DEFAULT_BG);
+    }
+    Appearance {
+        fg,
+        bg,
+        bold: cell.flags.bold,
+        dim: cell.flags.dim,
+        italic: cell.flags.italic,
+        underline: cell.flags.underline,
+        strikethrough: cell.flags.strikethrough,
+    }
+}
+
+/// A run of cells drawn the same way
+struct Run {
+    text: String,
+    /// Column the run starts at
+    col: usize,
+    /// Number of columns covered
+    width: usize,
+    appearance: Appearance,
+}
+
+/// Split a row into runs, giving the cursor cell its own appearance
+fn row_runs(row: &[StyledCell], cursor_col: Option<usize>) -> Vec<Run> {
+    let mut runs: Vec<Run> = Vec::new();
+    let mut col = 0;
+    for cell in row {
+        let look = appearance(cell, cursor_col == Some(col));
+        let width = if cell.wide { 2 } else { 1 };
+        match runs.last_mut() {
+            Some(run) if run.appearance == look => {
+                run.text.push(cell.c);
+                run.width += width;
+            }
+            _ => runs.push(Run { text: cell.c.to_string(), col, width, appearance: look }),
+        }
+        col += width;
+    }
+    runs
+}
+
+/// Escape text for use in HTML or XML
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            // Control characters aren't allowed in XML
+            c if c.is_control() => escaped.push(' '),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn text_decoration(look: &Appearance) -> Option<&'static str> {
+    match (look.underline, look.strikethrough) {
+        (true, true) => Some("underline line-through"),
+        (true, false) => Some("underline"),
+        (false, true) => Some("line-through"),
+        (false, false) => None,
+    }
+}
+
+/// Render the screen as a standalone HTML document
+pub fn html(cells: &[Vec<StyledCell>], cursor: (usize, usize)) -> String {
+    let mut body = String::new();
+    for (row_idx, row) in cells.iter().enumerate() {
+        let cursor_col = (row_idx == cursor.0).then_some(cursor.1);
+        for run in row_runs(row, cursor_col) {
+            let look = &run.appearance;
+            let mut style = Vec::new();
+            if look.fg != DEFAULT_FG {
+                style.push(format!("color:{}", hex(look.fg)));
+            }
+            if let Some(bg) = look.bg {
+                style.push(format!("background:{}", hex(bg)));
+            }
+            if look.bold {
+                style.push("font-weight:bold".to_string());
+            }
+            if look.dim {
+                style.push("opacity:0.5".to_string());
+            }
+            if look.italic {
+                style.push("font-style:italic".to_string());
+            }
+            if let Some(decoration) = text_decoration(look) {
+                style.push(format!("text-decoration:{}", decoration));
+            }
+            if style.is_empty() {
+                body.push_str(&escape(&run.text));
+            } else {
+                body.push_str(&format!("<span style=\"{}\">{}</span>", style.join(";"), escape(&run.text)));
+            }
+        }
+        body.push('\n');
+    }
+
+    format!(
+        "<!DOCTYPE html>\n\
+         <html>\n\
+         <head>\n\
+         <meta charset=\"utf-8\">\n\
+         <title>interminai screen</title>\n\
+         <style>\n\
+         pre.terminal {{ display: inline-block; margin: 0; padding: {padding}px; \
+         font-family: {font}; font-size: {size}px; line-height: {height}px; \
+         color: {fg}; background: {bg}; }}\n\
+         </style>\n\
+         </head>\n\
+         <body>\n\
+         <pre class=\"terminal\">{body}</pre>\n\
+         </body>\n\
+         </html>\n",
+        padding = PADDING,
+        font = FONT_FAMILY,
+        size = FONT_SIZE,
+        height = CELL_HEIGHT,
+        fg = hex(DEFAULT_FG),
+        bg = hex(DEFAULT_BG),
+        body = body,
+    )
+}
+
+/// Render the screen as a standalone SVG image
+pub fn svg(cells: &[Vec<StyledCell>], cursor: (usize, usize), cols: usize) -> String {
+    let width = cols as f64 * CELL_WIDTH + 2.0 * PADDING as f64;
+    let height = cells.len() * CELL_HEIGHT + 2 * PADDING;
+
+    let mut backgrounds = String::new();
+    let mut texts = String::new();
+    for (row_idx, row) in cells.iter().enumerate() {
+        let cursor_col = (row_idx == cursor.0).then_some(cursor.1);
+        let top = PADDING + row_idx * CELL_HEIGHT;
+        for run in row_runs(row, cursor_col) {
+            let look = &run.appearance;
+            let x = PADDING as f64 + run.col as f64 * CELL_WIDTH;
+            let run_width = run.width as f64 * CELL_WIDTH;
+            if let Some(bg) = look.bg {
+                backgrounds.push_str(&format!(
+                    "<rect x=\"{:.1}\" y=\"{}\" width=\"{:.1}\" height=\"{}\" fill=\"{}\"/>\n",
+                    x, top, run_width, CELL_HEIGHT, hex(bg)
+                ));
+            }
+            // Blanks are left out of the text, keeping the rest on its columns
+            let text = run.text.trim_start_matches(' ');
+            let leading = run.text.len() - text.len();
+            let text = text.trim_end_matches(' ');
+            if text.is_empty() {
+                continue;
+            }
+            let trailing = run.text.len() - leading - text.len();
+            let x = x + leading as f64 * CELL_WIDTH;
+            let run_width = (run.width - leading - trailing) as f64 * CELL_WIDTH;
+            let mut attrs = format!("x=\"{:.1}\" y=\"{}\" fill=\"{}\"", x, top + FONT_SIZE - 1, hex(look.fg));
+            if look.bold {
+                attrs.push_str(" font-weight=\"bold\"");
+            }
+            if look.dim {
+                attrs.push_str(" opacity=\"0.5\"");
+            }
+            if look.italic {
+                attrs.push_str(" font-style=\"italic\"");
+            }
+            if let Some(decoration) = text_decoration(look) {
+                attrs.push_str(&format!(" text-decoration=\"{}\"", decoration));
+            }
+            // Keep the text on the cell grid whatever font the viewer picks
+            texts.push_str(&format!(
+                "<text {} textLength=\"{:.1}\" lengthAdjust=\"spacingAndGlyphs\">{}</text>\n",
+                attrs, run_width, escape(text)
+            ));
+        }
+    }
+
+    format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{width:.1}\" height=\"{height}\" \
+         viewBox=\"0 0 {width:.1} {height}\">\n\
+         <rect width=\"100%\" height=\"100%\" fill=\"{bg}\"/>\n\
+         {backgrounds}\
+         <g font-family=\"{font}\" font-size=\"{size}\" xml:space=\"preserve\">\n\
+         {texts}\
+         </g>\n\
+         </svg>\n",
+        width = width,
+        height = height,
+        bg = hex(DEFAULT_BG),
+        backgrounds = backgrounds,
+        font = FONT_FAMILY,
+        size = FONT_SIZE,
+        texts = texts,
+    )
+}
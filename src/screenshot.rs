@@ -0,0 +1,400 @@
+// PNG screenshots of the screen
+//
+// Draws the styled cells with a built-in bitmap font (see assets/README.md),
+// so screenshots look the same everywhere and need no GPU, X server or
+// installed fonts. Colors and the cursor are resolved like the HTML and SVG
+// renderers do.
+
+use anyhow::Result;
+use std::ops::RangeInclusive;
+
+use crate::render::{appearance, Rgb, DEFAULT_BG};
+use crate::terminal::StyledCell;
+
+/// Width of a cell in pixels
+pub const CELL_WIDTH: usize = 10;
+
+/// Height of a cell in pixels
+pub const CELL_HEIGHT: usize = 20;
+
+/// Pre-rendered glyphs, sorted by code point
+static FONT: &[u8] = include_bytes!("../assets/dejavu-sans-mono-10x20.bin");
+
+/// Bytes per glyph (4 bits per pixel)
+const GLYPH_BYTES: usize = CELL_WIDTH * CELL_HEIGHT / 2;
+
+/// Code point followed by the regular and bold glyphs
+const RECORD_BYTES: usize = 4 + 2 * GLYPH_BYTES;
+
+/// Maximum coverage value
+const OPAQUE: u8 = 15;
+
+/// Row of the text baseline, counted from the top of the cell
+const BASELINE: usize = 15;
+
+/// Center of line drawing characters
+const CENTER_X: usize = 4;
+const CENTER_Y: usize = 9;
+
+/// Coverage of each pixel in a cell, row by row (0 to OPAQUE)
+type Mask = [u8; CELL_WIDTH * CELL_HEIGHT];
+
+/// Look up the glyph for a character in the built-in font
+fn font_glyph(c: char, bold: bool) -> Option<Mask> {
+    let count = FONT.len() / RECORD_BYTES;
+    let code_at = |i: usize| {
+        let record = &FONT[i * RECORD_BYTES..];
+        u32::from_le_bytes([record[0], record[1], record[2], record[3]])
+    };
+
+    // Binary search over the sorted records
+    let (mut low, mut high) = (0, count);
+    while low < high {
+        let mid = (low + high) / 2;
+        match code_at(mid).cmp(&(c as u32)) {
+            std::cmp::Ordering::Less => low = mid + 1,
+            std::cmp::Ordering::Greater => high = mid,
+            std::cmp::Ordering::Equal => {
+                let start = mid * RECORD_BYTES + 4 + if bold { GLYPH_BYTES } else { 0 };
+                let mut mask = [0; CELL_WIDTH * CELL_HEIGHT];
+                for (i, byte) in FONT[start..start + GLYPH_BYTES].iter().enumerate() {
+                    mask[2 * i] = byte >> 4;
+                    mask[2 * i + 1] = byte & 0x0f;
+                }
+                return Some(mask);
+            }
+        }
+    }
+    None
+}
+
+fn fill(mask: &mut Mask, xs: RangeInclusive<usize>, ys: RangeInclusive<usize>, value: u8) {
+    for y in ys {
+        for x in xs.clone() {
+            mask[y * CELL_WIDTH + x] = value;
+        }
+    }
+}
+
+/// Line weights of the arms of box drawing characters U+2500 to U+257F,
+/// as [up, right, down, left]: 0 none, 1 light, 2 heavy, 3 double.
+/// Dashed lines are drawn solid, rounded corners square.
+const BOX_ARMS: [[u8; 4]; 128] = [
+    [0, 1, 0, 1], [0, 2, 0, 2], [1, 0, 1, 0], [2, 0, 2, 0], // ─ ━ │ ┃
+    [0, 1, 0, 1], [0, 2, 0, 2], [1, 0, 1, 0], [2, 0, 2, 0], // ┄ ┅ ┆ ┇
+    [0, 1, 0, 1], [0, 2, 0, 2], [1, 0, 1, 0], [2, 0, 2, 0], // ┈ ┉ ┊ ┋
+    [0, 1, 1, 0], [0, 2, 1, 0], [0, 1, 2, 0], [0, 2, 2, 0], // ┌ ┍ ┎ ┏
+    [0, 0, 1, 1], [0, 0, 1, 2], [0, 0, 2, 1], [0, 0, 2, 2], // ┐ ┑ ┒ ┓
+    [1, 1, 0, 0], [1, 2, 0, 0], [2, 1, 0, 0], [2, 2, 0, 0], // └ ┕ ┖ ┗
+    [1, 0, 0, 1], [1, 0, 0, 2], [2, 0, 0, 1], [2, 0, 0, 2], // ┘ ┙ ┚ ┛
+    [1, 1, 1, 0], [1, 2, 1, 0], [2, 1, 1, 0], [1, 1, 2, 0], // ├ ┝ ┞ ┟
+    [2, 1, 2, 0], [2, 2, 1, 0], [1, 2, 2, 0], [2, 2, 2, 0], // ┠ ┡ ┢ ┣
+    [1, 0, 1, 1], [1, 0, 1, 2], [2, 0, 1, 1], [1, 0, 2, 1], // ┤ ┥ ┦ ┧
+    [2, 0, 2, 1], [2, 0, 1, 2], [1, 0, 2, 2], [2, 0, 2, 2], // ┨ ┩ ┪ ┫
+    [0, 1, 1, 1], [0, 1, 1, 2], [0, 2, 1, 1], [0, 2, 1, 2], // ┬ ┭ ┮ ┯
+    [0, 1, 2, 1], [0, 1, 2, 2], [0, 2, 2, 1], [0, 2, 2, 2], // ┰ ┱ ┲ ┳
+    [1, 1, 0, 1], [1, 1, 0, 2], [1, 2, 0, 1], [1, 2, 0, 2], // ┴ ┵ ┶ ┷
+    [2, 1, 0, 1], [2, 1, 0, 2], [2, 2, 0, 1], [2, 2, 0, 2], // ┸ ┹ ┺ ┻
+    [1, 1, 1, 1], [1, 1, 1, 2], [1, 2, 1, 1], [1, 2, 1, 2], // ┼ ┽ ┾ ┿
+    [2, 1, 1, 1], [1, 1, 2, 1], [2, 1, 2, 1], [2, 1, 1, 2], // ╀ ╁ ╂ ╃
+    [2, 2, 1, 1], [1, 1, 2, 2], [1, 2, 2, 1], [2, 2, 1, 2], // ╄ ╅ ╆ ╇
+    [1, 2, 2, 2], [2, 1, 2, 2], [2, 2, 2, 1], [2, 2, 2, 2], // ╈ ╉ ╊ ╋
+    [0, 1, 0, 1], [0, 2, 0, 2], [1, 0, 1, 0], [2, 0, 2, 0], // ╌ ╍ ╎ ╏
+    [0, 3, 0, 3], [3, 0, 3, 0], [0, 3, 1, 0], [0, 1, 3, 0], // ═ ║ ╒ ╓
+    [0, 3, 3, 0], [0, 0, 1, 3], [0, 0, 3, 1], [0, 0, 3, 3], // ╔ ╕ ╖ ╗
+    [1, 3, 0, 0], [3, 1, 0, 0], [3, 3, 0, 0], [1, 0, 0, 3], // ╘ ╙ ╚ ╛
+    [3, 0, 0, 1], [3, 0, 0, 3], [1, 3, 1, 0], [3, 1, 3, 0], // ╜ ╝ ╞ ╟
+    [3, 3, 3, 0], [1, 0, 1, 3], [3, 0, 3, 1], [3, 0, 3, 3], // ╠ ╡ ╢ ╣
+    [0, 3, 1, 3], [0, 1, 3, 1], [0, 3, 3, 3], [1, 3, 0, 3], // ╤ ╥ ╦ ╧
+    [3, 1, 0, 1], [3, 3, 0, 3], [1, 3, 1, 3], [3, 1, 3, 1], // ╨ ╩ ╪ ╫
+    [3, 3, 3, 3], [0, 1, 1, 0], [0, 0, 1, 1], [1, 0, 0, 1], // ╬ ╭ ╮ ╯
+    [1, 1, 0, 0], [0, 0, 0, 0], [0, 0, 0, 0], [0, 0, 0, 0], // ╰ ╱ ╲ ╳
+    [0, 0, 0, 1], [1, 0, 0, 0], [0, 1, 0, 0], [0, 0, 1, 0], // ╴ ╵ ╶ ╷
+    [0, 0, 0, 2], [2, 0, 0, 0], [0, 2, 0, 0], [0, 0, 2, 0], // ╸ ╹ ╺ ╻
+    [0, 2, 0, 1], [1, 0, 2, 0], [0, 1, 0, 2], [2, 0, 1, 0], // ╼ ╽ ╾ ╿
+];
+
+/// Draw a box drawing character from the weights of its arms
+fn box_mask(arms: [u8; 4]) -> Mask {
+    const UP: usize = 0;
+    const RIGHT: usize = 1;
+    const DOWN: usize = 2;
+    const LEFT: usize = 3;
+    const LAST_X: usize = CELL_WIDTH - 1;
+    const LAST_Y: usize = CELL_HEIGHT - 1;
+    // Double lines are drawn 2 pixels either side of the center
+    const NEAR_X: usize = CENTER_X - 2;
+    const FAR_X: usize = CENTER_X + 2;
+    const NEAR_Y: usize = CENTER_Y - 2;
+    const FAR_Y: usize = CENTER_Y + 2;
+
+    let mut mask = [0; CELL_WIDTH * CELL_HEIGHT];
+    let double_v = arms[UP] == 3 || arms[DOWN] == 3;
+    let double_h = arms[LEFT] == 3 || arms[RIGHT] == 3;
+
+    // Double lines: a band covering both lines minus the band between them,
+    // which makes corners and junctions join up
+    let (x0, y0) = if double_v && double_h { (NEAR_X, NEAR_Y) } else { (CENTER_X, CENTER_Y) };
+    let (x1, y1) = if double_v && double_h { (FAR_X, FAR_Y) } else { (CENTER_X, CENTER_Y) };
+    let outer = [
+        (NEAR_X..=FAR_X, 0..=y1),
+        (x0..=LAST_X, NEAR_Y..=FAR_Y),
+        (NEAR_X..=FAR_X, y0..=LAST_Y),
+        (0..=x1, NEAR_Y..=FAR_Y),
+    ];
+    for (arm, (xs, ys)) in outer.iter().enumerate() {
+        if arms[arm] == 3 {
+            fill(&mut mask, xs.clone(), ys.clone(), OPAQUE);
+        }
+    }
+    for (arm, (xs, ys)) in outer.into_iter().enumerate() {
+        if arms[arm] == 3 {
+            let xs = if arm == UP || arm == DOWN { NEAR_X + 1..=FAR_X - 1 } else { *xs.start()..=*xs.end() };
+            let ys = if arm == LEFT || arm == RIGHT { NEAR_Y + 1..=FAR_Y - 1 } else { *ys.start()..=*ys.end() };
+            // Keep the outer line where the band ends at a double line
+            let xs = match arm {
+                RIGHT if double_v => *xs.start() + 1..=*xs.end(),
+                LEFT if double_v => *xs.start()..=*xs.end() - 1,
+                _ => xs,
+            };
+            let ys = match arm {
+                DOWN if double_h => *ys.start() + 1..=*ys.end(),
+                UP if double_h => *ys.start()..=*ys.end() - 1,
+                _ => ys,
+            };
+            fill(&mut mask, xs, ys, 0);
+        }
+    }
+
+    // Single lines meeting a double line stop at its nearer line if it goes
+    // straight through, otherwise at the farther one
+    let through_v = arms[UP] == 3 && arms[DOWN] == 3;
+    let through_h = arms[LEFT] == 3 && arms[RIGHT] == 3;
+    let overshoot = if arms.contains(&2) { 1 } else { 0 };
+    for (arm, &weight) in arms.iter().enumerate() {
+        if weight != 1 && weight != 2 {
+            continue;
+        }
+        let half = weight as usize - 1;
+        let across_x = CENTER_X - half..=CENTER_X + half;
+        let across_y = CENTER_Y - half..=CENTER_Y + half;
+        match arm {
+            UP => {
+                let end = if double_h { if through_h { NEAR_Y } else { FAR_Y } } else { CENTER_Y + overshoot };
+                fill(&mut mask, across_x, 0..=end, OPAQUE);
+            }
+            DOWN => {
+                let start = if double_h { if through_h { FAR_Y } else { NEAR_Y } } else { CENTER_Y - overshoot };
+                fill(&mut mask, across_x, start..=LAST_Y, OPAQUE);
+            }
+            LEFT => {
+                let end = if double_v { if through_v { NEAR_X } else { FAR_X } } else { CENTER_X + overshoot };
+                fill(&mut mask, 0..=end, across_y, OPAQUE);
+            }
+            _ => {
+                let start = if double_v { if through_v { FAR_X } else { NEAR_X } } else { CENTER_X - overshoot };
+                fill(&mut mask, start..=LAST_X, across_y, OPAQUE);
+            }
+        }
+    }
+    mask
+}
+
+/// Draw a diagonal line; `rising` goes from bottom left to top right
+fn diagonal(mask: &mut Mask, rising: bool) {
+    for y in 0..CELL_HEIGHT {
+        let x = y * (CELL_WIDTH - 1) / (CELL_HEIGHT - 1);
+        let x = if rising { CELL_WIDTH - 1 - x } else { x };
+        mask[y * CELL_WIDTH + x] = OPAQUE;
+    }
+}
+
+/// Draw a block element (U+2580 to U+259F)
+fn block_mask(c: char) -> Mask {
+    let mut mask = [0; CELL_WIDTH * CELL_HEIGHT];
+    let (w, h) = (CELL_WIDTH, CELL_HEIGHT);
+    let eighths_down = |n: usize| h - n * h / 8;
+    let eighths_across = |n: usize| n * w / 8;
+    let quadrants = |mask: &mut Mask, ul: bool, ur: bool, ll: bool, lr: bool| {
+        let (mx, my) = (w / 2, h / 2);
+        for (on, xs, ys) in [(ul, 0..=mx - 1, 0..=my - 1), (ur, mx..=w - 1, 0..=my - 1), (ll, 0..=mx - 1, my..=h - 1), (lr, mx..=w - 1, my..=h - 1)] {
+            if on {
+                fill(mask, xs, ys, OPAQUE);
+            }
+        }
+    };
+    let shade = |mask: &mut Mask, on: fn(usize, usize) -> bool| {
+        for y in 0..h {
+            for x in 0..w {
+                if on(x, y) {
+                    mask[y * w + x] = OPAQUE;
+                }
+            }
+        }
+    };
+
+    match c as u32 {
+        0x2580 => fill(&mut mask, 0..=w - 1, 0..=h / 2 - 1, OPAQUE),
+        n @ 0x2581..=0x2588 => fill(&mut mask, 0..=w - 1, eighths_down((n - 0x2580) as usize)..=h - 1, OPAQUE),
+        n @ 0x2589..=0x258F => fill(&mut mask, 0..=eighths_across((0x2590 - n) as usize) - 1, 0..=h - 1, OPAQUE),
+        0x2590 => fill(&mut mask, w / 2..=w - 1, 0..=h - 1, OPAQUE),
+        0x2591 => shade(&mut mask, |x, y| x % 2 == 0 && y % 2 == 0),
+        0x2592 => shade(&mut mask, |x, y| (x + y) % 2 == 0),
+        0x2593 => shade(&mut mask, |x, y| x % 2 == 0 || y % 2 == 0),
+        0x2594 => fill(&mut mask, 0..=w - 1, 0..=h / 8 - 1, OPAQUE),
+        0x2595 => fill(&mut mask, w - w / 8..=w - 1, 0..=h - 1, OPAQUE),
+        0x2596 => quadrants(&mut mask, false, false, true, false),
+        0x2597 => quadrants(&mut mask, false, false, false, true),
+        0x2598 => quadrants(&mut mask, true, false, false, false),
+        0x2599 => quadrants(&mut mask, true, false, true, true),
+        0x259A => quadrants(&mut mask, true, false, false, true),
+        0x259B => quadrants(&mut mask, true, true, true, false),
+        0x259C => quadrants(&mut mask, true, true, false, true),
+        0x259D => quadrants(&mut mask, false, true, false, false),
+        0x259E => quadrants(&mut mask, false, true, true, false),
+        _ => quadrants(&mut mask, false, true, true, true),
+    }
+    mask
+}
+
+/// Draw a braille pattern (U+2800 to U+28FF) as a 2x4 grid of dots
+fn braille_mask(c: char) -> Mask {
+    // Dot number (bit) to column and row, per the Unicode braille layout
+    const DOTS: [(usize, usize); 8] = [(0, 0), (0, 1), (0, 2), (1, 0), (1, 1), (1, 2), (0, 3), (1, 3)];
+    let bits = c as u32 - 0x2800;
+    let mut mask = [0; CELL_WIDTH * CELL_HEIGHT];
+    for (bit, (col, row)) in DOTS.iter().enumerate() {
+        if bits & (1 << bit) != 0 {
+            let x = 2 + col * 4;
+            let y = 1 + row * 5;
+            fill(&mut mask, x..=x + 1, y..=y + 1, OPAQUE);
+        }
+    }
+    mask
+}
+
+/// Coverage mask for a character, None if it can't be drawn
+fn glyph(c: char, bold: bool) -> Option<Mask> {
+    match c as u32 {
+        0x2571..=0x2573 => {
+            let mut mask = [0; CELL_WIDTH * CELL_HEIGHT];
+            if c != '╲' {
+                diagonal(&mut mask, true);
+            }
+            if c != '╱' {
+                diagonal(&mut mask, false);
+            }
+            Some(mask)
+        }
+        n @ 0x2500..=0x257F => Some(box_mask(BOX_ARMS[(n - 0x2500) as usize])),
+        0x2580..=0x259F => Some(block_mask(c)),
+        0x2800..=0x28FF => Some(braille_mask(c)),
+        _ => font_glyph(c, bold),
+    }
+}
+
+/// RGB image being drawn
+struct Canvas {
+    width: usize,
+    pixels: Vec<u8>,
+}
+
+impl Canvas {
+    fn new(width: usize, height: usize) -> Self {
+        let mut pixels = Vec::with_capacity(width * height * 3);
+        for _ in 0..width * height {
+            pixels.extend([DEFAULT_BG.0, DEFAULT_BG.1, DEFAULT_BG.2]);
+        }
+        Canvas { width, pixels }
+    }
+
+    /// Blend `color` over the pixel with the given coverage (0 to OPAQUE)
+    fn blend(&mut self, x: usize, y: usize, color: Rgb, coverage: u8) {
+        if x >= self.width || coverage == 0 {
+            return;
+        }
+        let i = (y * self.width + x) * 3;
+        let coverage = coverage.min(OPAQUE) as u16;
+        for (channel, value) in [color.0, color.1, color.2].into_iter().enumerate() {
+            let old = self.pixels[i + channel] as u16;
+            self.pixels[i + channel] = ((value as u16 * coverage + old * (OPAQUE as u16 - coverage)) / OPAQUE as u16) as u8;
+        }
+    }
+
+    fn fill(&mut self, x: usize, y: usize, width: usize, height: usize, color: Rgb) {
+        for py in y..y + height {
+            for px in x..x + width {
+                self.blend(px, py, color, OPAQUE);
+            }
+        }
+    }
+}
+
+/// Mix two colors half and half
+fn halfway(a: Rgb, b: Rgb) -> Rgb {
+    let mix = |a: u8, b: u8| ((a as u16 + b as u16) / 2) as u8;
+    (mix(a.0, b.0), mix(a.1, b.1), mix(a.2, b.2))
+}
+
+/// Draw one cell, `span` columns wide, with its top left corner at (x, y)
+fn draw_cell(canvas: &mut Canvas, cell: &StyledCell, is_cursor: bool, x: usize, y: usize, span: usize) {
+    let look = appearance(cell, is_cursor);
+    let bg = look.bg.unwrap_or(DEFAULT_BG);
+    let fg = if look.dim { halfway(look.fg, bg) } else { look.fg };
+    let width = span * CELL_WIDTH;
+    canvas.fill(x, y, width, CELL_HEIGHT, bg);
+
+    if cell.c != ' ' {
+        match glyph(cell.c, look.bold) {
+            Some(mask) => {
+                for gy in 0..CELL_HEIGHT {
+                    // Italics: slant by shifting rows above the baseline right
+                    let shift = if look.italic { BASELINE.saturating_sub(gy) / 4 } else { 0 };
+                    for gx in 0..CELL_WIDTH {
+                        canvas.blend(x + gx + shift, y + gy, fg, mask[gy * CELL_WIDTH + gx]);
+                    }
+                }
+            }
+            None => {
+                // Unknown character: draw an empty box in its place
+                canvas.fill(x + 1, y + 3, width - 2, 1, fg);
+                canvas.fill(x + 1, y + BASELINE, width - 2, 1, fg);
+                canvas.fill(x + 1, y + 3, 1, BASELINE - 3, fg);
+                canvas.fill(x + width - 2, y + 3, 1, BASELINE - 3, fg);
+            }
+        }
+    }
+
+    if look.underline {
+        canvas.fill(x, y + BASELINE + 2, width, 1, fg);
+    }
+    if look.strikethrough {
+        canvas.fill(x, y + CENTER_Y + 1, width, 1, fg);
+    }
+}
+
+/// Render the screen as a PNG image, `cols` cells wide.
+/// Returns the image with its width and height in pixels.
+pub fn png(cells: &[Vec<StyledCell>], cursor: (usize, usize), cols: usize) -> Result<(Vec<u8>, usize, usize)> {
+    let (width, height) = (cols * CELL_WIDTH, cells.len() * CELL_HEIGHT);
+    let mut canvas = Canvas::new(width, height);
+
+    for (row_idx, row) in cells.iter().enumerate() {
+        let mut col = 0;
+        for cell in row {
+            let span = if cell.wide { 2 } else { 1 };
+            let is_cursor = row_idx == cursor.0 && col == cursor.1;
+            draw_cell(&mut canvas, cell, is_cursor, col * CELL_WIDTH, row_idx * CELL_HEIGHT, span);
+            col += span;
+        }
+    }
+
+    let mut image = Vec::new();
+    let mut encoder = png::Encoder::new(&mut image, width as u32, height as u32);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(&canvas.pixels)?;
+    writer.finish()?;
+    Ok((image, width, height))
+}
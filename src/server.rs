@@ -0,0 +1,303 @@
+// Multi-session server
+//
+// One daemon hosting many sessions on a single socket, instead of one daemon
+// and socket per session. Requests say which session they are for with a
+// "session" field (its name or ID) and are then handled exactly as a
+// single-session daemon would. NEW, LIST and CLOSE manage the sessions.
+// HELLO describes the server, and the session too if one is given.
+
+use anyhow::Result;
+use nix::sys::wait::waitpid;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use tokio::net::UnixStream;
+use tokio::sync::Notify;
+
+use crate::access::Access;
+use crate::{output_log, terminal};
+use crate::{DaemonConfig, DaemonState, Emulator, Response};
+
+/// Requests the server answers itself, besides those it passes on to sessions
+const SERVER_REQUESTS: &[&str] = &["NEW", "LIST", "CLOSE"];
+
+/// A session hosted by the server. Its state is never locked while the
+/// server's is, so one busy session can't hold up the others.
+#[derive(Clone)]
+struct Session {
+    id: u64,
+    name: Option<String>,
+    state: Arc<Mutex<DaemonState>>,
+}
+
+#[derive(Default)]
+struct Server {
+    sessions: Vec<Session>,
+    /// ID of the most recently created session
+    last_id: u64,
+    should_shutdown: bool,
+    /// Woken to stop accepting connections
+    stopped: Arc<Notify>,
+}
+
+impl Server {
+    /// Find a session by name, or by ID (a number or a string of digits)
+    fn find(&self, key: &serde_json::Value) -> Option<usize> {
+        let id = match key {
+            serde_json::Value::String(name) => {
+                if let Some(index) = self.sessions.iter().position(|s| s.name.as_deref() == Some(name.as_str())) {
+                    return Some(index);
+                }
+                name.parse().ok()?
+            }
+            key => key.as_u64()?,
+        };
+        self.sessions.iter().position(|s| s.id == id)
+    }
+
+
+    /// Why a session named `name` can't be added, if it can't
+    fn check_new(&self, name: &Option<String>) -> Option<String> {
+        if self.should_shutdown {
+            return Some("Server is shutting down".to_string());
+        }
+        match name {
+            Some(name) if self.sessions.iter().any(|s| s.name.as_ref() == Some(name)) => {
+                Some(format!("Session '{}' already exists", name))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Stop a session's application
+fn stop(state: &Arc<Mutex<DaemonState>>) {
+    crate::handle_stop(state);
+
+    // Nobody checks on the child anymore - reap it once it exits
+    let state = state.lock().unwrap();
+    if state.exit_code.is_none() {
+        let pid = state.child_pid;
+        thread::spawn(move || waitpid(pid, None));
+    }
+}
+
+/// How a session was referred to, for error messages
+fn describe(key: &serde_json::Value) -> String {
+    match key.as_str() {
+        Some(name) => name.to_string(),
+        None => key.to_string(),
+    }
+}
+
+/// Serve sessions on `socket_path` until a STOP without a session
+pub fn run(socket_path: &str, socket_was_auto_generated: bool, access: Access, ready: &mut crate::Ready) -> Result<()> {
+    let server = Arc::new(Mutex::new(Server::default()));
+    let stopped = server.lock().unwrap().stopped.clone();
+
+    let runtime = tokio::runtime::Runtime::new()?;
+    runtime.block_on(crate::serve(socket_path, access, stopped, ready, move |stream, access| {
+        handle_client(stream, access, server.clone())
+    }))?;
+    // Don't wait for clients still connected to closed sessions
+    runtime.shutdown_background();
+
+    if socket_was_auto_generated {
+        crate::remove_socket(socket_path);
+    }
+
+    Ok(())
+}
+
+async fn handle_client(stream: UnixStream, access: Arc<Access>, server: Arc<Mutex<Server>>) -> Result<()> {
+    let mut connection = crate::Connection::new(stream);
+    while let Some(request) = connection.next_request(&access).await? {
+        let stream = connection.stream();
+        let mut response = match request.req_type.as_str() {
+            "HELLO" => handle_hello(&request.data, &server),
+            "NEW" => handle_new(&request.data, &server),
+            "LIST" => handle_list(&server),
+            "CLOSE" => handle_close(&request.data, &server),
+            _ => match request.data.get("session").filter(|key| !key.is_null()) {
+                None if request.req_type == "STOP" => {
+                    let mut response = handle_shutdown(&server);
+                    response.id = request.id;
+                    crate::write_response(stream, &response).await?;
+                    break;
+                }
+                None => Response::error("No session given (this server hosts many sessions)".to_string()),
+                // Stopping a session closes it, leaving the others running
+                Some(_) if request.req_type == "STOP" => handle_close(&request.data, &server),
+                Some(key) => {
+                    let state = {
+                        let server = server.lock().unwrap();
+                        server.find(key).map(|index| server.sessions[index].state.clone())
+                    };
+                    match state {
+                        Some(state) => {
+                            if !crate::handle_request(request, &state, stream).await? {
+                                break;
+                            }
+                            continue;
+                        }
+                        None => Response::error(format!("No such session: {}", describe(key))),
+                    }
+                }
+            },
+        };
+        response.id = request.id;
+        crate::write_response(stream, &response).await?;
+    }
+    Ok(())
+}
+
+fn handle_hello(data: &serde_json::Value, server: &Arc<Mutex<Server>>) -> Response {
+    let requests: Vec<&str> = crate::SESSION_REQUESTS.iter().chain(SERVER_REQUESTS).copied().collect();
+    let mut hello = crate::hello_data(true, &requests);
+
+    if let Some(key) = data.get("session").filter(|key| !key.is_null()) {
+        let state = {
+            let server = server.lock().unwrap();
+            server.find(key).map(|index| server.sessions[index].state.clone())
+        };
+        match state {
+            Some(state) => crate::add_session_capabilities(&mut hello, &state.lock().unwrap()),
+            None => return Response::error(format!("No such session: {}", describe(key))),
+        }
+    }
+
+    Response::ok(hello)
+}
+
+fn handle_new(data: &serde_json::Value, server: &Arc<Mutex<Server>>) -> Response {
+    let command: Vec<String> = match data.get("command").and_then(|v| v.as_array()) {
+        Some(args) => args.iter().filter_map(|arg| arg.as_str().map(String::from)).collect(),
+        None => return Response::error("Missing 'command' field".to_string()),
+    };
+    if command.is_empty() {
+        return Response::error("'command' must not be empty".to_string());
+    }
+
+    let name = data.get("name").and_then(|v| v.as_str()).map(String::from);
+    if let Some(name) = &name {
+        if name.is_empty() || name.bytes().all(|b| b.is_ascii_digit()) {
+            return Response::error(format!("Invalid session name '{}': names can't be empty or a number", name));
+        }
+    }
+
+    let emulator = match data.get("emulator").and_then(|v| v.as_str()) {
+        Some(emulator) => match <Emulator as clap::ValueEnum>::from_str(emulator, true) {
+            Ok(emulator) => emulator,
+            Err(_) => return Response::error(format!("Unknown emulator: {}", emulator)),
+        },
+        None => Emulator::default(),
+    };
+
+    // The emulators can't have an empty grid
+    let size = |field: &str, default: u16| match data.get(field) {
+        Some(value) => value.as_u64().and_then(|n| u16::try_from(n).ok()).filter(|&n| n > 0),
+        None => Some(default),
+    };
+    let (Some(rows), Some(cols)) = (size("rows", 24), size("cols", 80)) else {
+        return Response::error(format!("Invalid 'rows' or 'cols': expected a number from 1 to {}", u16::MAX));
+    };
+
+    let config = DaemonConfig {
+        rows,
+        cols,
+        emulator,
+        scrollback: data.get("scrollback").and_then(|v| v.as_u64()).map_or(terminal::DEFAULT_SCROLLBACK, |n| n as usize),
+        output_buffer: data.get("output_buffer").and_then(|v| v.as_u64()).map_or(output_log::DEFAULT_CAPACITY, |n| n as usize),
+        pty_dump: None,
+        record: None,
+        env: Vec::new(),
+    };
+
+    if let Some(error) = server.lock().unwrap().check_new(&name) {
+        return Response::error(error);
+    }
+
+    // Don't hold up requests to other sessions while the application starts
+    let state = match crate::spawn_session(config, command) {
+        Ok(state) => state,
+        Err(e) => return Response::error(format!("Failed to start session: {:#}", e)),
+    };
+    let pid = state.lock().unwrap().child_pid.as_raw();
+
+    // Another NEW may have taken the name in the meantime
+    let mut server = server.lock().unwrap();
+    if let Some(error) = server.check_new(&name) {
+        drop(server);
+        stop(&state);
+        return Response::error(error);
+    }
+
+    server.last_id += 1;
+    let id = server.last_id;
+    server.sessions.push(Session { id, name: name.clone(), state });
+
+    Response::ok(serde_json::json!({
+        "id": id,
+        "name": name,
+        "pid": pid
+    }))
+}
+
+fn handle_list(server: &Arc<Mutex<Server>>) -> Response {
+    let sessions = server.lock().unwrap().sessions.clone();
+    let sessions: Vec<serde_json::Value> = sessions.iter().map(|session| {
+        let mut state = session.state.lock().unwrap();
+        state.check_child_status();
+        let (rows, cols) = state.terminal.dimensions();
+        let mut entry = serde_json::json!({
+            "id": session.id,
+            "name": session.name,
+            "command": state.command,
+            "pid": state.child_pid.as_raw(),
+            "running": state.exit_code.is_none(),
+            "rows": rows,
+            "cols": cols
+        });
+        if let Some(exit_code) = state.exit_code {
+            entry["exit_code"] = serde_json::json!(exit_code);
+        }
+        entry
+    }).collect();
+
+    Response::ok(serde_json::json!({ "sessions": sessions }))
+}
+
+fn handle_close(data: &serde_json::Value, server: &Arc<Mutex<Server>>) -> Response {
+    let key = match data.get("session") {
+        Some(key) if !key.is_null() => key,
+        _ => return Response::error("Missing 'session' field".to_string()),
+    };
+
+    let session = {
+        let mut server = server.lock().unwrap();
+        server.find(key).map(|index| server.sessions.remove(index))
+    };
+    match session {
+        Some(session) => {
+            stop(&session.state);
+            Response::ok(serde_json::json!({ "closed": session.id }))
+        }
+        None => Response::error(format!("No such session: {}", describe(key))),
+    }
+}
+
+/// Close every session and stop the server
+fn handle_shutdown(server: &Arc<Mutex<Server>>) -> Response {
+    let sessions = {
+        let mut server = server.lock().unwrap();
+        server.should_shutdown = true;
+        server.stopped.notify_one();
+        std::mem::take(&mut server.sessions)
+    };
+    for session in &sessions {
+        stop(&session.state);
+    }
+
+    Response::ok(serde_json::json!({
+        "message": "Shutting down"
+    }))
+}
@@ -0,0 +1,182 @@
+// Session discovery
+//
+// Named sessions (`start --name`) live in the runtime directory, one
+// directory per name: $XDG_RUNTIME_DIR/interminai/NAME/socket. Sessions with
+// an auto-generated socket live in temp dirs called interminai-XXXXXX. Both
+// are found by scanning, so sessions can be listed and leftovers of daemons
+// that died without cleaning up can be removed.
+//
+// The runtime directory also holds, per client, the screen generation
+// `output` last saw of each session, for `output --diff`, and how far into
+// its output `expect --target output` has matched.
+
+use anyhow::{bail, Context, Result};
+use std::fs;
+use std::os::unix::fs::{DirBuilderExt, MetadataExt};
+use std::os::unix::net::UnixStream;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+/// Name of the socket inside a session directory
+const SOCKET_NAME: &str = "socket";
+
+/// Prefix of auto-generated socket directories
+pub const TEMP_PREFIX: &str = "interminai-";
+
+/// How old an empty session directory must be before it is removed, so a
+/// daemon that is just starting up doesn't lose its directory
+const EMPTY_DIR_AGE: Duration = Duration::from_secs(60);
+
+/// Prefix of the files remembering what `output` last saw
+const BASELINE_PREFIX: &str = ".diff-";
+
+/// Prefix of the files remembering how far `expect` has matched
+const EXPECT_PREFIX: &str = ".expect-";
+
+/// How long an unused baseline or expect offset is kept
+const BASELINE_AGE: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Directory holding the named sessions
+pub fn runtime_dir() -> PathBuf {
+    match std::env::var_os("XDG_RUNTIME_DIR") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir).join("interminai"),
+        // The temp dir is shared, so keep users apart
+        _ => std::env::temp_dir().join(format!("{}{}", TEMP_PREFIX, rustix::process::getuid())),
+    }
+}
+
+/// Socket path of the session called `name`
+pub fn named_socket_path(name: &str) -> Result<PathBuf> {
+    if name.is_empty() || name == "." || name == ".." || name.contains('/') {
+        bail!("Invalid session name '{}'", name);
+    }
+    Ok(runtime_dir().join(name).join(SOCKET_NAME))
+}
+
+/// Create the directory for the session called `name`, returning its socket path.
+/// Fails if a daemon is already listening there.
+pub fn create_named(name: &str) -> Result<PathBuf> {
+    let socket = named_socket_path(name)?;
+    if is_live(&socket) {
+        bail!("Session '{}' is already running (socket {})", name, socket.display());
+    }
+    let dir = socket.parent().unwrap_or(Path::new("."));
+    fs::DirBuilder::new()
+        .recursive(true)
+        .mode(0o700)
+        .create(dir)
+        .with_context(|| format!("Failed to create {}", dir.display()))?;
+    Ok(socket)
+}
+
+/// File where `output` run by `client` remembers the screen generation it
+/// last saw of the session at `socket`, so `output --diff` can ask for the
+/// rows changed since. Clients sharing a session keep theirs apart by name.
+pub fn diff_baseline_path(socket: &str, session: Option<&str>, client: &str) -> PathBuf {
+    client_file(BASELINE_PREFIX, socket, session, client)
+}
+
+/// File where `expect --target output` run by `client` remembers the output
+/// offset just past its last match, so the next one only matches newer output
+pub fn expect_offset_path(socket: &str, session: Option<&str>, client: &str) -> PathBuf {
+    client_file(EXPECT_PREFIX, socket, session, client)
+}
+
+fn client_file(prefix: &str, socket: &str, session: Option<&str>, client: &str) -> PathBuf {
+    use std::hash::{Hash, Hasher};
+
+    let socket = fs::canonicalize(socket).unwrap_or_else(|_| socket.into());
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    (socket, session, client).hash(&mut hasher);
+    runtime_dir().join(format!("{}{:016x}", prefix, hasher.finish()))
+}
+
+pub fn read_baseline(path: &Path) -> Option<u64> {
+    fs::read_to_string(path).ok()?.trim().parse().ok()
+}
+
+/// Best effort: without it, the next `output --diff` prints every row (or
+/// the next `expect` matches from the oldest output kept)
+pub fn remember_baseline(path: &Path, value: u64) {
+    if let Some(dir) = path.parent() {
+        let _ = fs::DirBuilder::new().recursive(true).mode(0o700).create(dir);
+    }
+    let _ = fs::write(path, value.to_string());
+}
+
+/// Is a daemon listening on `socket`?
+pub fn is_live(socket: &Path) -> bool {
+    UnixStream::connect(socket).is_ok()
+}
+
+/// A session directory found on disk
+pub struct Found {
+    /// Session name, for sessions started with --name
+    pub name: Option<String>,
+    pub dir: PathBuf,
+    pub socket: PathBuf,
+}
+
+/// Session directories in `parent` owned by us, for which `wanted` accepts the name
+fn scan(parent: &Path, wanted: impl Fn(&str) -> bool, named: bool) -> Vec<Found> {
+    let uid = rustix::process::getuid().as_raw();
+    let Ok(entries) = fs::read_dir(parent) else {
+        return Vec::new();
+    };
+    let mut found: Vec<Found> = entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_name().to_str().is_some_and(&wanted))
+        .filter(|entry| entry.metadata().is_ok_and(|meta| meta.is_dir() && meta.uid() == uid))
+        .map(|entry| Found {
+            name: named.then(|| entry.file_name().to_string_lossy().to_string()),
+            socket: entry.path().join(SOCKET_NAME),
+            dir: entry.path(),
+        })
+        .collect();
+    found.sort_by(|a, b| a.dir.cmp(&b.dir));
+    found
+}
+
+/// All session directories: named ones first, then auto-generated ones
+pub fn find() -> Vec<Found> {
+    let runtime = runtime_dir();
+    let mut found = scan(&runtime, |_| true, true);
+    let temp = scan(&std::env::temp_dir(), |name| name.starts_with(TEMP_PREFIX), false);
+    found.extend(temp.into_iter().filter(|session| session.dir != runtime));
+    found
+}
+
+/// Remove the directories of sessions whose daemon is gone, returning them
+pub fn collect_garbage() -> Vec<PathBuf> {
+    let mut removed = Vec::new();
+    for session in find() {
+        let stale = if session.socket.exists() {
+            !is_live(&session.socket)
+        } else {
+            // Nothing was ever started here, or it is just starting
+            let modified = fs::metadata(&session.dir).and_then(|meta| meta.modified());
+            let age = modified.ok().and_then(|time| SystemTime::now().duration_since(time).ok());
+            age.is_some_and(|age| age >= EMPTY_DIR_AGE)
+        };
+        if !stale {
+            continue;
+        }
+        let _ = fs::remove_file(&session.socket);
+        // Only removes the directory if nothing else is in it
+        if fs::remove_dir(&session.dir).is_ok() {
+            removed.push(session.dir);
+        }
+    }
+
+    // Baselines and expect offsets of sessions nobody has looked at in a while
+    for entry in fs::read_dir(runtime_dir()).into_iter().flatten().filter_map(|entry| entry.ok()) {
+        let is_baseline = entry.file_name().to_str()
+            .is_some_and(|name| name.starts_with(BASELINE_PREFIX) || name.starts_with(EXPECT_PREFIX));
+        let modified = entry.metadata().and_then(|meta| meta.modified());
+        let age = modified.ok().and_then(|time| SystemTime::now().duration_since(time).ok());
+        if is_baseline && age.is_some_and(|age| age >= BASELINE_AGE) {
+            let _ = fs::remove_file(entry.path());
+        }
+    }
+    removed
+}
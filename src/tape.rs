@@ -0,0 +1,486 @@
+// Tape scripts
+//
+// Plays the common subset of VHS `.tape` files against a session of its own:
+// typing and keys, sleeping, waiting for the screen to show something,
+// screenshots and the terminal size. Commands that only shape VHS's video
+// (Output, Hide, FontSize, Theme, ...) are ignored, so the demo tapes run
+// unchanged. A failing step is reported with the screen at that point, which
+// makes tapes usable as end-to-end tests.
+
+use anyhow::{anyhow, bail, Context, Result};
+use regex::{Regex, RegexBuilder};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::screenshot::{CELL_HEIGHT, CELL_WIDTH};
+use crate::{output_log, screenshot, terminal};
+use crate::{DaemonConfig, DaemonState, Emulator};
+
+/// How long Wait waits unless told otherwise (as in VHS)
+const DEFAULT_WAIT_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// What Wait without a pattern waits for unless told otherwise (as in VHS)
+const DEFAULT_WAIT_PATTERN: &str = ">$";
+
+/// Shell the session runs unless the tape says `Set Shell`
+const DEFAULT_SHELL: &str = "sh";
+
+/// What a line of a tape does
+enum Action {
+    /// Send text, pausing between characters (default: TypingSpeed)
+    Type { text: String, delay: Option<Duration> },
+    /// Press a key `count` times, pausing in between (default: TypingSpeed)
+    Key { key: String, count: usize, delay: Option<Duration> },
+    Sleep(Duration),
+    /// Wait until the screen (or its last line) matches a pattern
+    Wait { screen: bool, pattern: Option<Regex>, timeout: Option<Duration> },
+    Screenshot(String),
+    /// Terminal width and height, in pixels as in VHS
+    Width(usize),
+    Height(usize),
+    Shell(String),
+    TypingSpeed(Duration),
+    WaitTimeout(Duration),
+    WaitPattern(Regex),
+    Env(String, String),
+    /// Fail unless a program is installed
+    Require(String),
+    /// Only matters to VHS's video
+    Ignore,
+}
+
+struct Step {
+    line: usize,
+    source: String,
+    action: Action,
+}
+
+/// Parse a tape, reporting errors as FILE:LINE
+fn parse(file: &str, text: &str) -> Result<Vec<Step>> {
+    let mut steps = Vec::new();
+    for (index, line) in text.lines().enumerate() {
+        let source = line.trim();
+        if source.is_empty() || source.starts_with('#') {
+            continue;
+        }
+        let action = parse_line(source).map_err(|e| anyhow!("{}:{}: {}", file, index + 1, e))?;
+        steps.push(Step { line: index + 1, source: source.to_string(), action });
+    }
+    Ok(steps)
+}
+
+fn parse_line(source: &str) -> Result<Action> {
+    let (command, rest) = source.split_once(char::is_whitespace).unwrap_or((source, ""));
+    // Type@100ms, Wait+Screen@10s: the part after @ is a speed or timeout
+    let (command, speed) = match command.split_once('@') {
+        Some((command, speed)) => (command, Some(parse_duration(speed)?)),
+        None => (command, None),
+    };
+    // Only Wait takes /patterns/; elsewhere a / starts a path
+    let args = split_args(rest, command.starts_with("Wait"))?;
+    let one_arg = || match args.as_slice() {
+        [arg] => Ok(arg.clone()),
+        _ => Err(anyhow!("{} takes one argument", command)),
+    };
+
+    let action = match command {
+        "Output" | "Hide" | "Show" => Action::Ignore,
+        "Set" => parse_setting(&args)?,
+        "Env" => match args.as_slice() {
+            [name, value] => Action::Env(name.clone(), value.clone()),
+            _ => bail!("Env takes a name and a value"),
+        },
+        "Require" => Action::Require(one_arg()?),
+        "Type" => {
+            if args.is_empty() {
+                bail!("Type needs the text to type");
+            }
+            Action::Type { text: args.concat(), delay: speed }
+        }
+        "Sleep" => Action::Sleep(parse_duration(&one_arg()?)?),
+        "Screenshot" => Action::Screenshot(one_arg()?),
+        "Wait" | "Wait+Line" | "Wait+Screen" => {
+            let pattern = match args.as_slice() {
+                [] => None,
+                [pattern] => Some(parse_pattern(pattern)?),
+                _ => bail!("Wait takes at most one pattern"),
+            };
+            Action::Wait { screen: command == "Wait+Screen", pattern, timeout: speed }
+        }
+        _ => match key(command) {
+            Some(key) => {
+                let count = match args.as_slice() {
+                    [] => 1,
+                    [count] => count.parse().map_err(|_| anyhow!("Invalid repeat count '{}'", count))?,
+                    _ => bail!("{} takes at most a repeat count", command),
+                };
+                Action::Key { key, count, delay: speed }
+            }
+            None => bail!("Unsupported command '{}'", command),
+        },
+    };
+    Ok(action)
+}
+
+fn parse_setting(args: &[String]) -> Result<Action> {
+    let Some((name, value)) = args.split_first().filter(|(_, value)| !value.is_empty()) else {
+        bail!("Set takes a name and a value");
+    };
+    let value = value.join(" ");
+    let pixels = || value.parse::<usize>().map_err(|_| anyhow!("Invalid {} '{}'", name, value));
+
+    let action = match name.as_str() {
+        "Width" => Action::Width(pixels()?),
+        "Height" => Action::Height(pixels()?),
+        "Shell" => Action::Shell(value),
+        "TypingSpeed" => Action::TypingSpeed(parse_duration(&value)?),
+        "WaitTimeout" => Action::WaitTimeout(parse_duration(&value)?),
+        "WaitPattern" => {
+            let pattern = value.strip_prefix('/').and_then(|p| p.strip_suffix('/')).unwrap_or(&value);
+            Action::WaitPattern(parse_pattern(pattern)?)
+        }
+        "FontSize" | "FontFamily" | "LetterSpacing" | "LineHeight" | "Padding" | "Margin" | "MarginFill"
+        | "Theme" | "Framerate" | "PlaybackSpeed" | "LoopOffset" | "CursorBlink" | "WindowBar"
+        | "WindowBarSize" | "BorderRadius" => Action::Ignore,
+        _ => bail!("Unsupported setting '{}'", name),
+    };
+    Ok(action)
+}
+
+/// Split arguments: "quoted", 'quoted' or `quoted` strings, /regular expressions/
+/// (with `patterns`), or words
+fn split_args(rest: &str, patterns: bool) -> Result<Vec<String>> {
+    let mut args = Vec::new();
+    let mut chars = rest.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+            continue;
+        }
+
+        let mut arg = String::new();
+        if matches!(c, '"' | '\'' | '`') || (patterns && c == '/') {
+            chars.next();
+            loop {
+                match chars.next() {
+                    Some(ch) if ch == c => break,
+                    // Keep escapes in patterns for the regex, including \/
+                    Some('\\') if c == '/' => {
+                        arg.push('\\');
+                        arg.extend(chars.next());
+                    }
+                    Some(ch) => arg.push(ch),
+                    None => bail!("Missing closing {}", c),
+                }
+            }
+        } else {
+            while let Some(&ch) = chars.peek() {
+                if ch.is_whitespace() {
+                    break;
+                }
+                arg.push(ch);
+                chars.next();
+            }
+        }
+        args.push(arg);
+    }
+    Ok(args)
+}
+
+/// Compile a Wait pattern. ^ and $ match at line boundaries, so patterns can
+/// look for whole lines on the screen.
+fn parse_pattern(pattern: &str) -> Result<Regex> {
+    RegexBuilder::new(pattern)
+        .multi_line(true)
+        .build()
+        .with_context(|| format!("Invalid pattern /{}/", pattern))
+}
+
+/// Parse 500ms, 2s, 1.5s or 1m; plain numbers are seconds
+fn parse_duration(text: &str) -> Result<Duration> {
+    let (number, unit) = if let Some(number) = text.strip_suffix("ms") {
+        (number, 0.001)
+    } else if let Some(number) = text.strip_suffix('s') {
+        (number, 1.0)
+    } else if let Some(number) = text.strip_suffix('m') {
+        (number, 60.0)
+    } else {
+        (text, 1.0)
+    };
+    match number.parse::<f64>() {
+        Ok(n) if n >= 0.0 && n.is_finite() => Ok(Duration::from_secs_f64(n * unit)),
+        _ => bail!("Invalid duration '{}'", text),
+    }
+}
+
+/// The key a VHS key name presses (Enter, Escape, Up, Ctrl+C, Alt+F, ...),
+/// named the way `input --keys` names it
+fn key(name: &str) -> Option<String> {
+    let mut modifiers = String::new();
+    let mut rest = name;
+    loop {
+        let (modifier, after) = if let Some(after) = rest.strip_prefix("Ctrl+") {
+            ("C-", after)
+        } else if let Some(after) = rest.strip_prefix("Alt+") {
+            ("M-", after)
+        } else if let Some(after) = rest.strip_prefix("Shift+") {
+            ("S-", after)
+        } else {
+            break;
+        };
+        modifiers.push_str(modifier);
+        rest = after;
+    }
+
+    match rest {
+        "Enter" | "Escape" | "Backspace" | "Delete" | "Insert" | "Tab" | "Space" | "Up" | "Down" | "Right" | "Left"
+        | "Home" | "End" | "PageUp" | "PageDown" => {}
+        // Letters and the like only make keys with a modifier
+        _ if !modifiers.is_empty() && single_char(rest).is_some() => {}
+        _ => return None,
+    }
+    Some(format!("{}{}", modifiers, rest))
+}
+
+/// The character, if `text` is exactly one
+fn single_char(text: &str) -> Option<char> {
+    let mut chars = text.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) => Some(c),
+        _ => None,
+    }
+}
+
+/// Settings and session of a tape being played
+struct Player {
+    emulator: Emulator,
+    shell: String,
+    /// Terminal size in pixels, if set
+    width: Option<usize>,
+    height: Option<usize>,
+    typing_speed: Duration,
+    wait_timeout: Duration,
+    wait_pattern: Regex,
+    /// Set for the shell by Env
+    env: Vec<(String, String)>,
+    /// Started by the first step that needs the terminal
+    session: Option<Arc<Mutex<DaemonState>>>,
+}
+
+impl Player {
+    fn new(emulator: Emulator) -> Self {
+        Player {
+            emulator,
+            shell: DEFAULT_SHELL.to_string(),
+            width: None,
+            height: None,
+            typing_speed: Duration::ZERO,
+            wait_timeout: DEFAULT_WAIT_TIMEOUT,
+            wait_pattern: parse_pattern(DEFAULT_WAIT_PATTERN).unwrap(),
+            env: Vec::new(),
+            session: None,
+        }
+    }
+
+    /// Terminal size in cells
+    fn size(&self) -> (u16, u16) {
+        let rows = self.height.map_or(24, |height| (height / CELL_HEIGHT).max(1));
+        let cols = self.width.map_or(80, |width| (width / CELL_WIDTH).max(1));
+        (rows as u16, cols as u16)
+    }
+
+    fn session(&mut self) -> Result<Arc<Mutex<DaemonState>>> {
+        if let Some(session) = &self.session {
+            return Ok(session.clone());
+        }
+        let (rows, cols) = self.size();
+        let config = DaemonConfig {
+            rows,
+            cols,
+            emulator: self.emulator,
+            scrollback: terminal::DEFAULT_SCROLLBACK,
+            output_buffer: output_log::DEFAULT_CAPACITY,
+            pty_dump: None,
+            record: None,
+            env: self.env.clone(),
+        };
+        let command = self.shell.split_whitespace().map(String::from).collect();
+        let session = crate::spawn_session(config, command)?;
+        self.session = Some(session.clone());
+        Ok(session)
+    }
+
+    /// Settings that go into starting the session can't change afterwards
+    fn before_start(&self, what: &str) -> Result<()> {
+        if self.session.is_some() {
+            bail!("{} must come before the first command that uses the terminal", what);
+        }
+        Ok(())
+    }
+
+    fn send(&mut self, text: &str) -> Result<()> {
+        self.input(serde_json::json!({ "data": text }))
+    }
+
+    /// Press a key, encoded for the application's keyboard modes
+    fn press(&mut self, key: &str) -> Result<()> {
+        self.input(serde_json::json!({ "keys": key }))
+    }
+
+    fn input(&mut self, data: serde_json::Value) -> Result<()> {
+        let session = self.session()?;
+        let input = crate::prepare_input(data, &mut session.lock().unwrap());
+        let response = match input {
+            Ok(input) => tokio::runtime::Handle::current().block_on(crate::send_input(&session, input)),
+            Err(response) => response,
+        };
+        match response.error {
+            Some(error) => Err(anyhow!(error)),
+            None => Ok(()),
+        }
+    }
+
+    fn resize(&mut self) -> Result<()> {
+        let Some(session) = &self.session else {
+            return Ok(());
+        };
+        let (rows, cols) = self.size();
+        let response = crate::handle_resize(serde_json::json!({ "rows": rows, "cols": cols }), &mut session.lock().unwrap());
+        match response.error {
+            Some(error) => Err(anyhow!(error)),
+            None => Ok(()),
+        }
+    }
+
+    fn screen(&self) -> Option<String> {
+        let mut state = self.session.as_ref()?.lock().unwrap();
+        state.read_pty_output();
+        Some(state.terminal.get_screen_content())
+    }
+
+    fn run(&mut self, action: &Action) -> Result<()> {
+        match action {
+            Action::Ignore => {}
+            Action::Shell(shell) => {
+                self.before_start("Set Shell")?;
+                self.shell = shell.clone();
+            }
+            Action::Env(name, value) => {
+                self.before_start("Env")?;
+                self.env.push((name.clone(), value.clone()));
+            }
+            Action::Require(program) => {
+                // The shell's PATH, which Env may have set
+                let path = match self.env.iter().rfind(|(name, _)| name == "PATH") {
+                    Some((_, path)) => Some(path.into()),
+                    None => std::env::var_os("PATH"),
+                };
+                let found = path
+                    .is_some_and(|path| std::env::split_paths(&path).any(|dir| dir.join(program).is_file()));
+                if !found {
+                    bail!("{} is not installed", program);
+                }
+            }
+            Action::TypingSpeed(speed) => self.typing_speed = *speed,
+            Action::WaitTimeout(timeout) => self.wait_timeout = *timeout,
+            Action::WaitPattern(pattern) => self.wait_pattern = pattern.clone(),
+            Action::Width(width) => {
+                self.width = Some(*width);
+                self.resize()?;
+            }
+            Action::Height(height) => {
+                self.height = Some(*height);
+                self.resize()?;
+            }
+            Action::Type { text, delay } => {
+                let delay = delay.unwrap_or(self.typing_speed);
+                if delay.is_zero() {
+                    self.send(text)?;
+                } else {
+                    for c in text.chars() {
+                        self.send(c.encode_utf8(&mut [0; 4]))?;
+                        thread::sleep(delay);
+                    }
+                }
+            }
+            Action::Key { key, count, delay } => {
+                let delay = delay.unwrap_or(self.typing_speed);
+                for i in 0..*count {
+                    if i > 0 {
+                        thread::sleep(delay);
+                    }
+                    self.press(key)?;
+                }
+            }
+            Action::Sleep(duration) => {
+                self.session()?;
+                thread::sleep(*duration);
+            }
+            Action::Wait { screen, pattern, timeout } => {
+                self.session()?;
+                let pattern = pattern.as_ref().unwrap_or(&self.wait_pattern);
+                let timeout = timeout.unwrap_or(self.wait_timeout);
+                let deadline = Instant::now() + timeout;
+                loop {
+                    let text = self.screen().unwrap_or_default();
+                    let text = if *screen {
+                        text.as_str()
+                    } else {
+                        text.lines().rev().find(|line| !line.trim().is_empty()).unwrap_or_default()
+                    };
+                    if pattern.is_match(text) {
+                        break;
+                    }
+                    if Instant::now() >= deadline {
+                        let place = if *screen { "screen" } else { "last line" };
+                        bail!("/{}/ did not show up on the {} within {:?}", pattern, place, timeout);
+                    }
+                    thread::sleep(Duration::from_millis(50));
+                }
+            }
+            Action::Screenshot(path) => {
+                let session = self.session()?;
+                let mut state = session.lock().unwrap();
+                state.read_pty_output();
+                let (_, cols) = state.terminal.dimensions();
+                let (image, width, height) = screenshot::png(&state.terminal.get_cells(), state.terminal.cursor_position(), cols)?;
+                std::fs::write(path, image).with_context(|| format!("Failed to write {}", path))?;
+                println!("Screenshot: {} ({}x{})", path, width, height);
+            }
+        }
+        Ok(())
+    }
+
+    fn stop(&self) {
+        if let Some(session) = &self.session {
+            crate::handle_stop(session);
+        }
+    }
+}
+
+/// Play the tape in `file`. On a failing step, report it with the screen and exit.
+pub fn play(file: &str, emulator: Emulator) -> Result<()> {
+    let text = std::fs::read_to_string(file).with_context(|| format!("Failed to read {}", file))?;
+    let steps = parse(file, &text)?;
+
+    // The session reads its terminal in the background on this runtime
+    let runtime = tokio::runtime::Runtime::new()?;
+    let _guard = runtime.enter();
+
+    let mut player = Player::new(emulator);
+    for step in &steps {
+        if let Err(e) = player.run(&step.action) {
+            eprintln!("Error: {}:{}: {}: {:#}", file, step.line, step.source, e);
+            if let Some(screen) = player.screen() {
+                eprintln!("Screen:");
+                eprintln!("{}", screen.trim_end_matches('\n'));
+            }
+            player.stop();
+            std::process::exit(1);
+        }
+    }
+
+    player.stop();
+    Ok(())
+}
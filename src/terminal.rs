@@ -3,6 +3,9 @@
 // This module defines the TerminalEmulator trait that abstracts terminal
 // emulation implementations, allowing different backends (custom, alacritty).
 
+/// Default number of scrollback lines kept per session
+pub const DEFAULT_SCROLLBACK: usize = 1000;
+
 /// Entry in the unhandled escape sequence debug buffer
 #[derive(Clone, serde::Serialize)]
 pub struct UnhandledSequence {
@@ -29,6 +32,28 @@ pub trait TerminalEmulator: Send {
         self.get_screen_content()
     }
 
+    /// Get the screen as rows of cells with their colors and attributes.
+    /// Each row holds one cell per column; the column after a wide character
+    /// has no cell of its own.
+    fn get_cells(&self) -> Vec<Vec<StyledCell>>;
+
+    /// Number of lines that scrolled off the top of the screen and are still kept
+    fn history_size(&self) -> usize {
+        0
+    }
+
+    /// Get the most recent `lines` lines of scrollback history, oldest first
+    /// (each line followed by a newline). Trailing whitespace on each line is trimmed.
+    fn get_history(&self, _lines: usize) -> String {
+        String::new()
+    }
+
+    /// Get scrollback history with ANSI color codes embedded.
+    /// Default implementation returns plain text (same as get_history).
+    fn get_history_ansi(&self, lines: usize) -> String {
+        self.get_history(lines)
+    }
+
     /// Get cursor position (row, col) - 0-indexed
     fn cursor_position(&self) -> (usize, usize);
 
@@ -49,4 +74,209 @@ pub trait TerminalEmulator: Send {
 
     /// Get count of dropped debug entries (due to buffer overflow)
     fn get_debug_dropped(&self) -> usize;
+
+    /// Keyboard modes the application has turned on, which decide what
+    /// bytes a key sends
+    fn keyboard_modes(&self) -> KeyboardModes {
+        KeyboardModes::default()
+    }
+
+    /// Whether the application has turned on bracketed paste (mode 2004)
+    fn bracketed_paste(&self) -> bool {
+        false
+    }
+
+    /// Mouse reporting the application has turned on
+    fn mouse_mode(&self) -> MouseMode {
+        MouseMode::default()
+    }
+}
+
+/// Keyboard modes set by the application
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct KeyboardModes {
+    /// DECCKM: cursor keys send \eOA instead of \e[A
+    pub app_cursor: bool,
+    /// DECKPAM: the keypad sends \eOp and so on instead of digits
+    pub app_keypad: bool,
+    /// Kitty keyboard protocol flags, 0 when the protocol is off
+    pub kitty: u8,
+}
+
+/// Which mouse events the application wants to hear about
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MouseTracking {
+    #[default]
+    Off,
+    /// Only presses (X10 mode 9)
+    Press,
+    /// Presses and releases (mode 1000)
+    Click,
+    /// Also motion while a button is held (mode 1002)
+    Drag,
+    /// Also motion without a button (mode 1003)
+    Motion,
+}
+
+/// How mouse reports are encoded
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MouseEncoding {
+    /// Positions as single bytes, up to 223
+    #[default]
+    X10,
+    /// Positions as UTF-8 characters (mode 1005)
+    Utf8,
+    /// Decimal parameters, \e[<b;x;yM (mode 1006)
+    Sgr,
+}
+
+/// Mouse reporting set by the application
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MouseMode {
+    pub tracking: MouseTracking,
+    pub encoding: MouseEncoding,
+}
+
+/// Foreground or background color of a cell
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CellColor {
+    /// The terminal's default color
+    #[default]
+    Default,
+    /// One of the 16 standard ANSI colors (0-7 normal, 8-15 bright)
+    Named(u8),
+    /// A color from the 256-color palette
+    Indexed(u8),
+    /// A 24-bit color
+    Rgb(u8, u8, u8),
+}
+
+/// Names of the 16 standard ANSI colors, by palette index
+pub const COLOR_NAMES: [&str; 16] = [
+    "black", "red", "green", "yellow", "blue", "magenta", "cyan", "white",
+    "bright_black", "bright_red", "bright_green", "bright_yellow",
+    "bright_blue", "bright_magenta", "bright_cyan", "bright_white",
+];
+
+/// Serialized as "default", a color name, a palette index or "#rrggbb"
+impl serde::Serialize for CellColor {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            CellColor::Default => serializer.serialize_str("default"),
+            CellColor::Named(index) => serializer.serialize_str(COLOR_NAMES[*index as usize % 16]),
+            CellColor::Indexed(index) => serializer.serialize_u8(*index),
+            CellColor::Rgb(r, g, b) => serializer.serialize_str(&format!("#{:02x}{:02x}{:02x}", r, g, b)),
+        }
+    }
+}
+
+/// Text attributes of a cell
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CellFlags {
+    pub bold: bool,
+    pub dim: bool,
+    pub italic: bool,
+    pub underline: bool,
+    pub inverse: bool,
+    pub hidden: bool,
+    pub strikethrough: bool,
+}
+
+impl CellFlags {
+    pub fn is_empty(&self) -> bool {
+        *self == CellFlags::default()
+    }
+}
+
+/// Serialized as the list of attributes that are set, e.g. ["bold", "inverse"]
+impl serde::Serialize for CellFlags {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let names = [
+            (self.bold, "bold"),
+            (self.dim, "dim"),
+            (self.italic, "italic"),
+            (self.underline, "underline"),
+            (self.inverse, "inverse"),
+            (self.hidden, "hidden"),
+            (self.strikethrough, "strikethrough"),
+        ];
+        serializer.collect_seq(names.iter().filter(|(set, _)| *set).map(|(_, name)| name))
+    }
+}
+
+/// A screen cell with its style. Defaults are left out when serialized.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Serialize)]
+pub struct StyledCell {
+    pub c: char,
+    #[serde(skip_serializing_if = "is_default_color")]
+    pub fg: CellColor,
+    #[serde(skip_serializing_if = "is_default_color")]
+    pub bg: CellColor,
+    #[serde(skip_serializing_if = "CellFlags::is_empty")]
+    pub flags: CellFlags,
+    /// Double-width character (takes up two columns)
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub wide: bool,
+}
+
+impl StyledCell {
+    /// A cell with the default style
+    pub fn plain(c: char) -> Self {
+        StyledCell {
+            c,
+            fg: CellColor::Default,
+            bg: CellColor::Default,
+            flags: CellFlags::default(),
+            wide: false,
+        }
+    }
+
+    fn same_style(&self, other: &StyledCell) -> bool {
+        self.fg == other.fg && self.bg == other.bg && self.flags == other.flags
+    }
+}
+
+fn is_default_color(color: &CellColor) -> bool {
+    *color == CellColor::Default
+}
+
+/// A run of consecutive cells sharing the same style
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize)]
+pub struct Span {
+    pub text: String,
+    #[serde(skip_serializing_if = "is_default_color")]
+    pub fg: CellColor,
+    #[serde(skip_serializing_if = "is_default_color")]
+    pub bg: CellColor,
+    #[serde(skip_serializing_if = "CellFlags::is_empty")]
+    pub flags: CellFlags,
+}
+
+/// Drop trailing blank cells with the default style
+pub fn trim_row(row: &[StyledCell]) -> &[StyledCell] {
+    let blank = StyledCell::plain(' ');
+    let end = row.iter().rposition(|cell| *cell != blank).map(|i| i + 1).unwrap_or(0);
+    &row[..end]
+}
+
+/// Group a row into spans of identically styled cells.
+/// Trailing blank cells with the default style are left out.
+pub fn row_spans(row: &[StyledCell]) -> Vec<Span> {
+    let mut spans: Vec<Span> = Vec::new();
+    let mut last: Option<&StyledCell> = None;
+    for cell in trim_row(row) {
+        match (spans.last_mut(), last) {
+            (Some(span), Some(prev)) if prev.same_style(cell) => span.text.push(cell.c),
+            _ => spans.push(Span {
+                text: cell.c.to_string(),
+                fg: cell.fg,
+                bg: cell.bg,
+                flags: cell.flags,
+            }),
+        }
+        last = Some(cell);
+    }
+    spans
 }
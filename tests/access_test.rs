@@ -0,0 +1,153 @@
+mod common;
+use common::{interminai_bin, emulator_args};
+
+use assert_cmd::Command;
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::fs::PermissionsExt;
+use std::os::unix::net::UnixStream;
+use std::path::PathBuf;
+use std::time::Duration;
+use tempfile::TempDir;
+
+struct DaemonHandle {
+    socket_path: String,
+    log_path: PathBuf,
+    _temp_dir: TempDir,
+}
+
+impl DaemonHandle {
+    /// Start a daemon running `cat` with the given extra `start` arguments
+    fn spawn(args: &[&str]) -> Self {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let socket_path = temp_dir.path().join("test.sock").to_string_lossy().to_string();
+        let log_path = temp_dir.path().join("access.log");
+
+        let output = Command::new(interminai_bin())
+            .arg("start")
+            .args(emulator_args())
+            .args(["--socket", &socket_path])
+            .arg("--log")
+            .arg(&log_path)
+            .args(args)
+            .args(["--", "cat"])
+            .env_remove("INTERMINAI_TOKEN")
+            .timeout(Duration::from_secs(5))
+            .output()
+            .expect("Failed to start");
+        // start returns once the daemon is listening
+        assert!(output.status.success(), "start failed: {}", String::from_utf8_lossy(&output.stderr));
+
+        DaemonHandle {
+            socket_path,
+            log_path,
+            _temp_dir: temp_dir,
+        }
+    }
+
+    fn client(&self, args: &[&str]) -> std::process::Output {
+        Command::new(interminai_bin())
+            .args(args)
+            .args(["--socket", &self.socket_path])
+            .env_remove("INTERMINAI_TOKEN")
+            .timeout(Duration::from_secs(5))
+            .output()
+            .expect("Failed to run client")
+    }
+
+    fn log(&self) -> String {
+        std::fs::read_to_string(&self.log_path).unwrap_or_default()
+    }
+}
+
+impl Drop for DaemonHandle {
+    fn drop(&mut self) {
+        let _ = Command::new(interminai_bin())
+            .args(["stop", "--socket", &self.socket_path, "--token", "secret"])
+            .output();
+    }
+}
+
+fn request(socket: &str, request: serde_json::Value) -> serde_json::Value {
+    let mut stream = UnixStream::connect(socket).expect("Failed to connect");
+    stream.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+    stream.write_all(format!("{}\n", request).as_bytes()).unwrap();
+    let mut line = String::new();
+    BufReader::new(stream).read_line(&mut line).expect("No response");
+    serde_json::from_str(&line).expect("Response is not valid JSON")
+}
+
+#[test]
+fn test_socket_is_private() {
+    let daemon = DaemonHandle::spawn(&[]);
+
+    let mode = std::fs::metadata(&daemon.socket_path).unwrap().permissions().mode();
+    assert_eq!(mode & 0o777, 0o600, "socket mode is {:o}", mode & 0o777);
+
+    // Our own user is always allowed
+    assert!(daemon.client(&["status"]).status.success());
+    assert!(daemon.log().is_empty(), "got: {}", daemon.log());
+}
+
+#[test]
+fn test_allow_uid_opens_socket() {
+    let output = Command::new(interminai_bin())
+        .args(["start", "--json", "--allow-uid", "65534"])
+        .args(emulator_args())
+        .args(["--", "cat"])
+        .env_remove("INTERMINAI_TOKEN")
+        .timeout(Duration::from_secs(5))
+        .output()
+        .expect("Failed to start");
+    assert!(output.status.success(), "start failed: {}", String::from_utf8_lossy(&output.stderr));
+    let started: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let socket = started["socket"].as_str().unwrap();
+
+    // Other users can reach the socket; the peer check decides who gets in
+    let mode = std::fs::metadata(socket).unwrap().permissions().mode();
+    let dir_mode = std::fs::metadata(PathBuf::from(socket).parent().unwrap()).unwrap().permissions().mode();
+    let stop = Command::new(interminai_bin())
+        .args(["stop", "--socket", socket])
+        .env_remove("INTERMINAI_TOKEN")
+        .timeout(Duration::from_secs(5))
+        .output()
+        .unwrap();
+    assert_eq!(mode & 0o777, 0o666, "socket mode is {:o}", mode & 0o777);
+    assert_eq!(dir_mode & 0o777, 0o711, "directory mode is {:o}", dir_mode & 0o777);
+    assert!(stop.status.success());
+
+    // Named sessions live where other users can't reach them
+    let output = Command::new(interminai_bin())
+        .args(["start", "--name", "access-test-shared", "--allow-uid", "65534", "--", "cat"])
+        .timeout(Duration::from_secs(5))
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("doesn't work with --name"));
+}
+
+#[test]
+fn test_token_is_required() {
+    let daemon = DaemonHandle::spawn(&["--token", "secret"]);
+
+    let output = daemon.client(&["status"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Invalid or missing token"));
+
+    let response = request(&daemon.socket_path, serde_json::json!({"type": "STATUS", "token": "guess"}));
+    assert_eq!(response["status"], "error");
+
+    let output = daemon.client(&["status", "--token", "secret"]);
+    assert!(output.status.success(), "status failed: {}", String::from_utf8_lossy(&output.stderr));
+
+    let output = Command::new(interminai_bin())
+        .args(["status", "--socket", &daemon.socket_path])
+        .env("INTERMINAI_TOKEN", "secret")
+        .timeout(Duration::from_secs(5))
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "status failed: {}", String::from_utf8_lossy(&output.stderr));
+
+    let log = daemon.log();
+    assert!(log.contains("missing token"), "got: {}", log);
+    assert!(log.contains("wrong token"), "got: {}", log);
+}
@@ -250,3 +250,96 @@ fn test_status_activity_clears_after_read() {
     let stdout = String::from_utf8_lossy(&output.stdout).trim().to_string();
     assert!(stdout.contains("Activity: true"), "Third status should report true after new output: got '{}'", stdout);
 }
+
+// Test wait --settle returns only after output has been quiet for the settle period
+#[test]
+fn test_wait_settle_after_bursts() {
+    let env = TestEnv::new();
+
+    // Output in several bursts over ~800ms, then go quiet
+    let daemon = DaemonHandle::spawn_with_socket(&env.socket(), &["bash", "-c", "for i in 1 2 3 4; do echo burst $i; sleep 0.2; done; sleep 10"]);
+
+    let start = std::time::Instant::now();
+    let output = Command::new(interminai_bin())
+        .arg("wait")
+        .arg("--socket")
+        .arg(&daemon.socket_path)
+        .arg("--settle")
+        .arg("400")
+        .timeout(Duration::from_secs(10))
+        .output()
+        .expect("Failed to wait for settle");
+    let elapsed = start.elapsed();
+
+    assert!(output.status.success(), "Wait --settle should succeed");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Settled: true"), "Should report settled: got '{}'", stdout);
+    assert!(elapsed >= Duration::from_millis(600), "Returned before bursts ended: {:?}", elapsed);
+
+    // All bursts are on screen by now
+    let screen = Command::new(interminai_bin())
+        .arg("output")
+        .arg("--socket")
+        .arg(&daemon.socket_path)
+        .arg("--no-color")
+        .output()
+        .expect("Failed to get output");
+    assert!(String::from_utf8_lossy(&screen.stdout).contains("burst 4"));
+}
+
+// Test wait --settle gives up after --timeout when output never stops
+#[test]
+fn test_wait_settle_timeout() {
+    let env = TestEnv::new();
+
+    let daemon = DaemonHandle::spawn_with_socket(&env.socket(), &["bash", "-c", "while true; do echo tick; sleep 0.05; done"]);
+
+    let start = std::time::Instant::now();
+    let output = Command::new(interminai_bin())
+        .arg("wait")
+        .arg("--socket")
+        .arg(&daemon.socket_path)
+        .arg("--settle")
+        .arg("500")
+        .arg("--timeout")
+        .arg("800")
+        .timeout(Duration::from_secs(10))
+        .output()
+        .expect("Failed to wait for settle");
+
+    assert_eq!(output.status.code(), Some(124), "Wait --settle should fail on timeout");
+    assert!(start.elapsed() < Duration::from_secs(3), "Timeout not honored");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Settled: false"), "Should report not settled: got '{}'", stdout);
+}
+
+// Test wait --settle returns promptly when the application exits
+#[test]
+fn test_wait_settle_on_exit() {
+    let env = TestEnv::new();
+
+    let daemon = DaemonHandle::spawn_with_socket(&env.socket(), &["bash", "-c", "while true; do echo tick; sleep 0.05; done"]);
+
+    Command::new(interminai_bin())
+        .arg("kill")
+        .arg("--socket")
+        .arg(&daemon.socket_path)
+        .arg("--signal")
+        .arg("SIGKILL")
+        .output()
+        .expect("Failed to kill");
+
+    let output = Command::new(interminai_bin())
+        .arg("wait")
+        .arg("--socket")
+        .arg(&daemon.socket_path)
+        .arg("--settle")
+        .arg("5000")
+        .timeout(Duration::from_secs(3))
+        .output()
+        .expect("Failed to wait for settle");
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Application exited: true"), "Should report exit: got '{}'", stdout);
+}
@@ -0,0 +1,99 @@
+mod common;
+use common::{interminai_bin, emulator};
+
+use interminai::{AsyncClient, DaemonError, ExpectTarget, Session, StartOptions, WaitFor};
+use std::time::{Duration, Instant};
+use tokio::task::JoinSet;
+
+const SESSIONS: usize = 20;
+
+fn start(command: &[&str]) -> Session {
+    let options = StartOptions {
+        size: Some((40, 10)),
+        emulator: Some(emulator()),
+        program: Some(interminai_bin().into()),
+        ..Default::default()
+    };
+    Session::start(command, &options).expect("Failed to start session")
+}
+
+fn async_client(session: &Session) -> AsyncClient {
+    AsyncClient::from(session.client().clone()).timeout(Duration::from_secs(10))
+}
+
+#[tokio::test]
+async fn test_async_client_drives_many_sessions() {
+    let sessions: Vec<Session> = (0..SESSIONS).map(|_| start(&["cat"])).collect();
+    let clients: Vec<AsyncClient> = sessions.iter().map(async_client).collect();
+
+    // Every session waits for output at once, all on this one thread
+    let mut waits = JoinSet::new();
+    for client in &clients {
+        let client = client.clone();
+        waits.spawn(async move { client.wait(WaitFor::Activity).await });
+    }
+    for (i, client) in clients.iter().enumerate() {
+        client.input(&format!("hello {}\r", i)).await.unwrap();
+    }
+    while let Some(result) = waits.join_next().await {
+        let result = result.unwrap().unwrap();
+        assert!(result.activity, "got: {:?}", result);
+    }
+
+    let mut expects = JoinSet::new();
+    for (i, client) in clients.iter().enumerate() {
+        let client = client.clone();
+        expects.spawn(async move {
+            let result = client.expect(&[format!("hello {0}\\s+hello {0}", i)], ExpectTarget::Screen).await.unwrap();
+            (i, result)
+        });
+    }
+    while let Some(result) = expects.join_next().await {
+        let (i, result) = result.unwrap();
+        assert!(result.matched, "session {}: {:?}", i, result);
+    }
+
+    // Waiting for all of them to settle takes about as long as for one
+    let start = Instant::now();
+    let mut settles = JoinSet::new();
+    for client in &clients {
+        let client = client.clone();
+        settles.spawn(async move { client.wait(WaitFor::Settle(Duration::from_millis(300))).await });
+    }
+    while let Some(result) = settles.join_next().await {
+        assert!(result.unwrap().unwrap().settled);
+    }
+    assert!(start.elapsed() < Duration::from_secs(3), "settling took {:?}", start.elapsed());
+
+    let screen = clients[3].output().await.unwrap();
+    assert_eq!(screen.lines().next(), Some("hello 3"));
+}
+
+#[tokio::test]
+async fn test_async_client_events_and_errors() {
+    let session = start(&["cat"]);
+    let client = async_client(&session);
+
+    let mut events = client.subscribe().await.unwrap();
+    client.input("hi\r").await.unwrap();
+    let event = events.next().await.unwrap().unwrap();
+    assert_eq!(event["event"], "output", "got: {}", event);
+
+    let err = client.kill("SIGNOPE").await.unwrap_err();
+    assert!(err.downcast_ref::<DaemonError>().is_some(), "got: {:?}", err);
+
+    let result = client.clone().timeout(Duration::from_millis(300))
+        .expect(&["never shows up"], ExpectTarget::Screen).await.unwrap();
+    assert!(!result.matched);
+    assert!(result.timed_out);
+
+    let err = AsyncClient::new("/nonexistent/interminai.sock").status(false).await.unwrap_err();
+    assert!(err.downcast_ref::<DaemonError>().is_none());
+
+    client.kill("SIGTERM").await.unwrap();
+    let result = client.wait(WaitFor::Exit).await.unwrap();
+    assert!(result.exited);
+    while let Some(event) = events.next().await {
+        event.unwrap();
+    }
+}
@@ -0,0 +1,195 @@
+mod common;
+use common::{interminai_bin, emulator_args};
+
+use assert_cmd::Command;
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::net::UnixStream;
+use std::thread;
+use std::time::{Duration, Instant};
+use tempfile::TempDir;
+
+struct DaemonHandle {
+    socket_path: String,
+    _temp_dir: TempDir,
+}
+
+impl DaemonHandle {
+    fn spawn(command: &[&str]) -> Self {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let socket_path = temp_dir.path().join("test.sock").to_string_lossy().to_string();
+
+        Command::new(interminai_bin())
+            .arg("start")
+            .args(emulator_args())
+            .args(["--socket", &socket_path, "--"])
+            .args(command)
+            .timeout(Duration::from_secs(5))
+            .assert()
+            .success();
+        thread::sleep(Duration::from_millis(300));
+
+        DaemonHandle {
+            socket_path,
+            _temp_dir: temp_dir,
+        }
+    }
+
+    fn batch(&self, requests: serde_json::Value, stop_on_error: bool) -> serde_json::Value {
+        let response = request(&self.socket_path, serde_json::json!({
+            "type": "BATCH",
+            "requests": requests,
+            "stop_on_error": stop_on_error
+        }));
+        assert_eq!(response["status"], "ok", "got: {}", response);
+        response["data"].clone()
+    }
+}
+
+impl Drop for DaemonHandle {
+    fn drop(&mut self) {
+        let _ = Command::new(interminai_bin())
+            .args(["stop", "--socket", &self.socket_path])
+            .output();
+    }
+}
+
+fn request(socket: &str, request: serde_json::Value) -> serde_json::Value {
+    let mut stream = UnixStream::connect(socket).expect("Failed to connect");
+    stream.set_read_timeout(Some(Duration::from_secs(10))).unwrap();
+    stream.write_all(format!("{}\n", request).as_bytes()).unwrap();
+    let mut line = String::new();
+    BufReader::new(stream).read_line(&mut line).expect("No response");
+    serde_json::from_str(&line).expect("Response is not valid JSON")
+}
+
+#[test]
+fn test_batch_runs_requests_in_order() {
+    let daemon = DaemonHandle::spawn(&["cat"]);
+
+    let data = daemon.batch(serde_json::json!([
+        {"type": "INPUT", "data": "hello\r", "id": "type"},
+        {"type": "SETTLE", "settle_ms": 200},
+        {"type": "OUTPUT", "id": "look"}
+    ]), false);
+
+    let responses = data["responses"].as_array().unwrap();
+    assert_eq!(responses.len(), 3);
+    assert_eq!(data["stopped"], false);
+    assert_eq!(responses[0]["id"], "type");
+    assert_eq!(responses[1]["data"]["settled"], true);
+    assert_eq!(responses[2]["id"], "look");
+    let screen = responses[2]["data"]["screen"].as_str().unwrap();
+    assert!(screen.contains("hello\nhello"), "input not echoed by the time of OUTPUT: {}", screen);
+}
+
+#[test]
+fn test_batch_is_atomic() {
+    let daemon = DaemonHandle::spawn(&["cat"]);
+
+    let socket = daemon.socket_path.clone();
+    let batch = thread::spawn(move || {
+        request(&socket, serde_json::json!({
+            "type": "BATCH",
+            "requests": [
+                {"type": "INPUT", "data": "a"},
+                {"type": "SLEEP", "ms": 600},
+                {"type": "INPUT", "data": "b"}
+            ]
+        }))
+    });
+
+    // Other clients can still look at the session
+    thread::sleep(Duration::from_millis(150));
+    let start = Instant::now();
+    let output = request(&daemon.socket_path, serde_json::json!({"type": "OUTPUT"}));
+    assert_eq!(output["status"], "ok");
+    assert!(start.elapsed() < Duration::from_millis(300), "OUTPUT took {:?}", start.elapsed());
+
+    // But their input has to wait for the whole batch
+    let start = Instant::now();
+    let response = request(&daemon.socket_path, serde_json::json!({"type": "INPUT", "data": "X\r"}));
+    assert_eq!(response["status"], "ok");
+    assert!(start.elapsed() >= Duration::from_millis(300), "input got in after {:?}", start.elapsed());
+    assert_eq!(batch.join().unwrap()["status"], "ok");
+
+    thread::sleep(Duration::from_millis(200));
+    let output = request(&daemon.socket_path, serde_json::json!({"type": "OUTPUT"}));
+    let screen = output["data"]["screen"].as_str().unwrap();
+    assert!(screen.contains("abX"), "got: {}", screen);
+}
+
+#[test]
+fn test_batch_errors() {
+    let daemon = DaemonHandle::spawn(&["cat"]);
+
+    let steps = serde_json::json!([
+        {"type": "STATUS"},
+        {"type": "WAIT"},
+        {"type": "SLEEP", "ms": 60000},
+        {"type": "NOPE"},
+        {"type": "STATUS"}
+    ]);
+
+    // Without stop_on_error every step runs and reports on its own
+    let data = daemon.batch(steps.clone(), false);
+    let responses = data["responses"].as_array().unwrap();
+    assert_eq!(responses.len(), 5);
+    assert_eq!(responses[0]["status"], "ok");
+    assert!(responses[1]["error"].as_str().unwrap().contains("can't be used in a BATCH"));
+    assert_eq!(responses[2]["status"], "error");
+    assert_eq!(responses[3]["error"], "Unknown command: NOPE");
+    assert_eq!(responses[4]["status"], "ok");
+
+    let data = daemon.batch(steps, true);
+    assert_eq!(data["stopped"], true);
+    assert_eq!(data["responses"].as_array().unwrap().len(), 2);
+
+    let response = request(&daemon.socket_path, serde_json::json!({"type": "BATCH"}));
+    assert_eq!(response["status"], "error");
+}
+
+#[test]
+fn test_batch_wait_is_limited_in_all() {
+    let daemon = DaemonHandle::spawn(&["cat"]);
+
+    // Each step is short enough, but not both together
+    let data = daemon.batch(serde_json::json!([
+        {"type": "SLEEP", "ms": 600},
+        {"type": "SLEEP", "ms": 9600},
+        {"type": "SETTLE", "settle_ms": 100}
+    ]), false);
+    let responses = data["responses"].as_array().unwrap();
+    assert_eq!(responses[0]["status"], "ok");
+    let error = responses[1]["error"].as_str().unwrap();
+    assert!(error.contains("this BATCH has left"), "got: {}", error);
+    assert_eq!(responses[2]["data"]["settled"], true);
+}
+
+#[test]
+fn test_batch_command() {
+    let daemon = DaemonHandle::spawn(&["cat"]);
+
+    let output = Command::new(interminai_bin())
+        .args(["batch", "--socket", &daemon.socket_path])
+        .write_stdin("{\"type\": \"INPUT\", \"data\": \"hi\\r\"}\n{\"type\": \"SETTLE\", \"settle_ms\": 100}\n{\"type\": \"OUTPUT\"}\n")
+        .timeout(Duration::from_secs(5))
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "batch failed: {}", String::from_utf8_lossy(&output.stderr));
+    let lines: Vec<serde_json::Value> = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(lines.len(), 3);
+    assert!(lines[2]["data"]["screen"].as_str().unwrap().contains("hi"));
+
+    let output = Command::new(interminai_bin())
+        .args(["batch", "--socket", &daemon.socket_path, "--stop-on-error"])
+        .write_stdin("[{\"type\": \"KILL\"}, {\"type\": \"STATUS\"}]")
+        .timeout(Duration::from_secs(5))
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout).lines().count(), 1);
+    assert!(String::from_utf8_lossy(&output.stderr).contains("1 request(s) not run"));
+}
@@ -0,0 +1,176 @@
+mod common;
+use common::{interminai_bin, emulator, emulator_args};
+
+use assert_cmd::Command;
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::net::UnixStream;
+use std::thread;
+use std::time::Duration;
+use tempfile::TempDir;
+
+struct TestEnv {
+    _temp_dir: TempDir,
+}
+
+impl TestEnv {
+    fn new() -> Self {
+        Self {
+            _temp_dir: TempDir::new().expect("Failed to create temp dir"),
+        }
+    }
+
+    fn socket(&self) -> String {
+        self._temp_dir.path().join("test.sock").to_string_lossy().to_string()
+    }
+}
+
+struct DaemonHandle {
+    _child: std::process::Child,
+    socket_path: String,
+}
+
+impl DaemonHandle {
+    fn spawn_with_socket(socket: &str, command_args: &[&str]) -> Self {
+        Self::spawn_with_args(socket, &[], command_args)
+    }
+
+    fn spawn_with_args(socket: &str, start_args: &[&str], command_args: &[&str]) -> Self {
+        use std::process::Stdio;
+
+        let mut cmd = std::process::Command::new(interminai_bin());
+        cmd.arg("start")
+            .args(emulator_args())
+            .args(start_args)
+            .arg("--socket")
+            .arg(socket)
+            .arg("--no-daemon")
+            .arg("--");
+
+        for arg in command_args {
+            cmd.arg(arg);
+        }
+
+        let mut child = cmd
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .expect("Failed to spawn daemon");
+
+        let stdout = child.stdout.take().unwrap();
+        let reader = BufReader::new(stdout);
+        let _lines: Vec<String> = reader.lines().take(3).map(|l| l.unwrap()).collect();
+
+        thread::sleep(Duration::from_millis(300));
+
+        DaemonHandle {
+            _child: child,
+            socket_path: socket.to_string(),
+        }
+    }
+}
+
+impl Drop for DaemonHandle {
+    fn drop(&mut self) {
+        let _ = Command::new(interminai_bin())
+            .arg("stop")
+            .arg("--socket")
+            .arg(&self.socket_path)
+            .output();
+    }
+}
+
+fn request(socket: &str, request: serde_json::Value) -> serde_json::Value {
+    let mut stream = UnixStream::connect(socket).expect("Failed to connect");
+    stream.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+    stream.write_all(format!("{}\n", request).as_bytes()).unwrap();
+    let mut line = String::new();
+    BufReader::new(stream).read_line(&mut line).expect("No response");
+    serde_json::from_str(&line).expect("Response is not valid JSON")
+}
+
+// Test spans group text by style and report colors and attributes
+#[test]
+fn test_output_spans() {
+    if emulator() == "custom" {
+        // Custom backend doesn't support colors
+        return;
+    }
+
+    let env = TestEnv::new();
+    let daemon = DaemonHandle::spawn_with_socket(&env.socket(), &["bash", "-c",
+        "printf 'plain \\033[1;31mred\\033[0m \\033[38;5;196mindexed\\033[0m \\033[48;2;1;2;3mrgb\\033[0m'; sleep 10"]);
+
+    let response = request(&daemon.socket_path, serde_json::json!({"type": "OUTPUT", "format": "spans"}));
+    assert_eq!(response["status"], "ok");
+    assert!(response["data"].get("screen").is_none());
+
+    let spans = &response["data"]["spans"];
+    assert_eq!(spans.as_array().unwrap().len(), 24);
+    assert_eq!(spans[0], serde_json::json!([
+        {"text": "plain "},
+        {"text": "red", "fg": "red", "flags": ["bold"]},
+        {"text": " "},
+        {"text": "indexed", "fg": 196},
+        {"text": " "},
+        {"text": "rgb", "bg": "#010203"},
+    ]));
+    // Blank rows have no spans
+    assert_eq!(spans[1], serde_json::json!([]));
+}
+
+// Test cells report one entry per column, with wide characters flagged
+#[test]
+fn test_output_cells() {
+    let env = TestEnv::new();
+    let daemon = DaemonHandle::spawn_with_socket(&env.socket(), &["bash", "-c", "printf 'a\\033[7mb\\033[0m'; sleep 10"]);
+
+    let response = request(&daemon.socket_path, serde_json::json!({"type": "OUTPUT", "format": "cells"}));
+    assert_eq!(response["status"], "ok");
+
+    let cells = &response["data"]["cells"];
+    assert_eq!(cells[0][0], serde_json::json!({"c": "a"}));
+    assert_eq!(cells[0][1]["c"], "b");
+    if emulator() != "custom" {
+        assert_eq!(cells[0][1]["flags"], serde_json::json!(["inverse"]));
+    }
+    assert_eq!(cells[0].as_array().unwrap().len(), 2);
+}
+
+// Test structured rows work with incremental output
+#[test]
+fn test_output_spans_since() {
+    let env = TestEnv::new();
+    let daemon = DaemonHandle::spawn_with_socket(&env.socket(), &["bash", "-c", "echo first; read line; echo second; sleep 10"]);
+
+    let response = request(&daemon.socket_path, serde_json::json!({"type": "OUTPUT", "format": "spans"}));
+    let generation = response["data"]["generation"].as_u64().unwrap();
+
+    request(&daemon.socket_path, serde_json::json!({"type": "INPUT", "data": "\r"}));
+    thread::sleep(Duration::from_millis(300));
+
+    let response = request(&daemon.socket_path, serde_json::json!({"type": "OUTPUT", "format": "spans", "since": generation}));
+    let changed = response["data"]["changed_rows"].as_array().unwrap();
+    assert!(!changed.is_empty());
+    assert!(changed.iter().any(|entry| entry["spans"] == serde_json::json!([{"text": "second"}])),
+        "changed rows: {:?}", changed);
+    assert!(changed.iter().all(|entry| entry.get("text").is_none()));
+}
+
+// Test output --format prints one JSON array per row
+#[test]
+fn test_output_format_cli() {
+    let env = TestEnv::new();
+    let daemon = DaemonHandle::spawn_with_socket(&env.socket(), &["bash", "-c", "echo hello; sleep 10"]);
+
+    let output = Command::new(interminai_bin())
+        .args(["output", "--socket", &daemon.socket_path, "--format", "spans"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert_eq!(lines.len(), 24);
+    let first: serde_json::Value = serde_json::from_str(lines[0]).expect("Row is not valid JSON");
+    assert_eq!(first, serde_json::json!([{"text": "hello"}]));
+}
@@ -0,0 +1,65 @@
+mod common;
+use common::{interminai_bin, emulator};
+
+use interminai::{Client, DaemonError, ExpectTarget, OutputOptions, Session, StartOptions, WaitFor};
+use std::time::Duration;
+
+fn start(command: &[&str]) -> Session {
+    let options = StartOptions {
+        size: Some((40, 10)),
+        emulator: Some(emulator()),
+        program: Some(interminai_bin().into()),
+        ..Default::default()
+    };
+    Session::start(command, &options).expect("Failed to start session")
+}
+
+#[test]
+fn test_client_drives_session() {
+    let session = start(&["cat"]);
+    let client = session.client().clone().timeout(Duration::from_secs(5));
+
+    client.input("hello\r").unwrap();
+    let result = client.expect(&["hello\\s+hello"], ExpectTarget::Screen).unwrap();
+    assert!(result.matched, "got: {:?}", result);
+
+    let screen = client.output().unwrap();
+    assert_eq!(screen.size.cols, 40);
+    assert_eq!(screen.size.rows, 10);
+    assert_eq!(screen.lines().next(), Some("hello"));
+    assert_eq!(screen.cursor.row, 2);
+    assert_eq!(screen.cursor_line(), Some(""));
+
+    // Nothing changed since that look at the screen
+    let diff = client.output_with(&OutputOptions { since: Some(screen.generation), ..Default::default() }).unwrap();
+    assert_eq!(diff.changed_rows.map(|rows| rows.len()), Some(0));
+
+    client.resize(60, 12).unwrap();
+    let status = client.status(false).unwrap();
+    assert!(status.running);
+    assert_eq!((status.cols, status.rows), (60, 12));
+    assert_eq!(status.command, "cat");
+
+    let debug = client.debug(false).unwrap();
+    assert!(debug.termios.error.is_none());
+
+    client.kill("SIGTERM").unwrap();
+    let result = client.wait(WaitFor::Exit).unwrap();
+    assert!(result.exited);
+    assert!(!result.timed_out);
+}
+
+#[test]
+fn test_client_errors() {
+    let session = start(&["cat"]);
+
+    let err = session.kill("SIGNOPE").unwrap_err();
+    assert!(err.downcast_ref::<DaemonError>().is_some(), "got: {:?}", err);
+
+    let err = Client::new("/nonexistent/interminai.sock").status(false).unwrap_err();
+    assert!(err.downcast_ref::<DaemonError>().is_none());
+
+    let socket = session.socket().to_path_buf();
+    drop(session);
+    assert!(Client::new(socket).output().is_err(), "session still running after drop");
+}
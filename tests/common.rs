@@ -1,3 +1,6 @@
+use interminai::{ExpectTarget, Session, StartOptions};
+use std::time::Duration;
+
 /// Get the interminai binary path to use for testing.
 ///
 /// This allows testing alternative implementations (e.g., Python) by setting
@@ -74,3 +77,33 @@ pub fn emulator() -> String {
 pub fn emulator_args() -> Vec<String> {
     vec!["--emulator".to_string(), emulator()]
 }
+
+/// Start `sh -c script` in raw mode through the library, once it prints "ready"
+#[allow(dead_code)]
+pub fn start_raw(script: &str, size: (u16, u16)) -> Session {
+    let options = StartOptions {
+        size: Some(size),
+        emulator: Some(emulator()),
+        program: Some(interminai_bin().into()),
+        ..Default::default()
+    };
+    let script = format!("stty raw -echo; {}", script);
+    let session = Session::start(&["sh", "-c", &script], &options).expect("Failed to start session");
+    expect_screen(&session, "ready");
+    session
+}
+
+/// Start `cat -v` in raw mode after printing `setup`, so the screen shows
+/// the bytes the application is sent (ESC as ^[)
+#[allow(dead_code)]
+pub fn start_cat(setup: &str, size: (u16, u16)) -> Session {
+    start_raw(&format!("printf '{}ready\\r\\n'; exec cat -v", setup), size)
+}
+
+/// Wait up to 5 seconds for `text` to appear on the screen
+#[allow(dead_code)]
+pub fn expect_screen(session: &Session, text: &str) {
+    let client = session.client().clone().timeout(Duration::from_secs(5));
+    let result = client.expect(&[regex::escape(text)], ExpectTarget::Screen).unwrap();
+    assert!(result.matched, "{:?} not on screen:\n{}", text, result.screen);
+}
@@ -0,0 +1,190 @@
+mod common;
+use common::{interminai_bin, emulator_args};
+
+use assert_cmd::Command;
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::net::UnixStream;
+use std::thread;
+use std::time::{Duration, Instant};
+use tempfile::TempDir;
+
+struct TestEnv {
+    _temp_dir: TempDir,
+}
+
+impl TestEnv {
+    fn new() -> Self {
+        Self {
+            _temp_dir: TempDir::new().expect("Failed to create temp dir"),
+        }
+    }
+
+    fn socket(&self) -> String {
+        self._temp_dir.path().join("test.sock").to_string_lossy().to_string()
+    }
+}
+
+struct DaemonHandle {
+    _child: std::process::Child,
+    socket_path: String,
+}
+
+impl DaemonHandle {
+    fn spawn_with_socket(socket: &str, command_args: &[&str]) -> Self {
+        use std::process::Stdio;
+
+        let mut cmd = std::process::Command::new(interminai_bin());
+        cmd.arg("start")
+            .args(emulator_args())
+            .arg("--socket")
+            .arg(socket)
+            .arg("--no-daemon")
+            .arg("--");
+
+        for arg in command_args {
+            cmd.arg(arg);
+        }
+
+        let mut child = cmd
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .expect("Failed to spawn daemon");
+
+        let stdout = child.stdout.take().unwrap();
+        let reader = BufReader::new(stdout);
+        let _lines: Vec<String> = reader.lines().take(3).map(|l| l.unwrap()).collect();
+
+        thread::sleep(Duration::from_millis(300));
+
+        DaemonHandle {
+            _child: child,
+            socket_path: socket.to_string(),
+        }
+    }
+}
+
+impl Drop for DaemonHandle {
+    fn drop(&mut self) {
+        let _ = Command::new(interminai_bin())
+            .arg("stop")
+            .arg("--socket")
+            .arg(&self.socket_path)
+            .output();
+    }
+}
+
+// Test a pending WAIT doesn't block input/output from other clients
+#[test]
+fn test_wait_does_not_block_other_clients() {
+    let env = TestEnv::new();
+    let daemon = DaemonHandle::spawn_with_socket(&env.socket(), &["cat"]);
+
+    // Park a client on WAIT until the process exits
+    let socket = daemon.socket_path.clone();
+    let waiter = thread::spawn(move || {
+        std::process::Command::new(interminai_bin())
+            .arg("wait")
+            .arg("--socket")
+            .arg(&socket)
+            .arg("--quiet")
+            .output()
+            .expect("Failed to run wait")
+    });
+    thread::sleep(Duration::from_millis(300));
+
+    // Other clients are served while WAIT is pending
+    Command::new(interminai_bin())
+        .arg("input")
+        .arg("--socket")
+        .arg(&daemon.socket_path)
+        .arg("--text")
+        .arg("concurrent\\r")
+        .timeout(Duration::from_secs(2))
+        .assert()
+        .success();
+
+    thread::sleep(Duration::from_millis(200));
+
+    let output = Command::new(interminai_bin())
+        .arg("output")
+        .arg("--socket")
+        .arg(&daemon.socket_path)
+        .arg("--no-color")
+        .timeout(Duration::from_secs(2))
+        .output()
+        .expect("Failed to get output");
+    assert!(output.status.success(), "OUTPUT should not be blocked by WAIT");
+    assert!(String::from_utf8_lossy(&output.stdout).contains("concurrent"));
+
+    Command::new(interminai_bin())
+        .arg("status")
+        .arg("--socket")
+        .arg(&daemon.socket_path)
+        .timeout(Duration::from_secs(2))
+        .assert()
+        .success();
+
+    // Ending the process releases the waiter
+    Command::new(interminai_bin())
+        .arg("kill")
+        .arg("--socket")
+        .arg(&daemon.socket_path)
+        .arg("--signal")
+        .arg("SIGKILL")
+        .timeout(Duration::from_secs(2))
+        .assert()
+        .success();
+
+    let wait_output = waiter.join().expect("Wait thread panicked");
+    assert!(wait_output.status.success());
+    assert_eq!(String::from_utf8_lossy(&wait_output.stdout).trim(), "137");
+}
+
+// Test an idle persistent connection doesn't stall other clients
+#[test]
+fn test_idle_connection_does_not_block_others() {
+    let env = TestEnv::new();
+    let daemon = DaemonHandle::spawn_with_socket(&env.socket(), &["sleep", "10"]);
+
+    let mut idle = UnixStream::connect(&daemon.socket_path).expect("Failed to connect");
+
+    let start = Instant::now();
+    Command::new(interminai_bin())
+        .arg("status")
+        .arg("--socket")
+        .arg(&daemon.socket_path)
+        .timeout(Duration::from_secs(2))
+        .assert()
+        .success();
+    assert!(start.elapsed() < Duration::from_secs(2));
+
+    // The idle connection is still usable afterwards
+    idle.write_all(b"{\"type\":\"STATUS\",\"id\":1}\n").unwrap();
+    idle.set_read_timeout(Some(Duration::from_secs(2))).unwrap();
+    let mut line = String::new();
+    BufReader::new(idle).read_line(&mut line).unwrap();
+    assert!(line.contains("\"id\":1"), "Unexpected response: {}", line);
+}
+
+// Test requests on one connection stay ordered while another connection waits
+#[test]
+fn test_connection_order_preserved_during_wait() {
+    let env = TestEnv::new();
+    let daemon = DaemonHandle::spawn_with_socket(&env.socket(), &["cat"]);
+
+    let mut waiter = UnixStream::connect(&daemon.socket_path).expect("Failed to connect");
+    waiter.write_all(b"{\"type\":\"WAIT\",\"id\":\"w\"}\n").unwrap();
+
+    let mut stream = UnixStream::connect(&daemon.socket_path).expect("Failed to connect");
+    stream.set_read_timeout(Some(Duration::from_secs(2))).unwrap();
+    stream.write_all(b"{\"type\":\"INPUT\",\"id\":1,\"data\":\"abc\\r\"}\n").unwrap();
+    stream.write_all(b"{\"type\":\"STATUS\",\"id\":2}\n").unwrap();
+
+    let mut reader = BufReader::new(stream);
+    for expected in ["\"id\":1", "\"id\":2"] {
+        let mut line = String::new();
+        reader.read_line(&mut line).expect("Request blocked by WAIT on another connection");
+        assert!(line.contains(expected), "Expected {} in {}", expected, line);
+    }
+}
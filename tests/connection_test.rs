@@ -0,0 +1,200 @@
+mod common;
+use common::{interminai_bin, emulator_args};
+
+use assert_cmd::Command;
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::net::UnixStream;
+use std::thread;
+use std::time::Duration;
+use tempfile::TempDir;
+
+struct TestEnv {
+    _temp_dir: TempDir,
+}
+
+impl TestEnv {
+    fn new() -> Self {
+        Self {
+            _temp_dir: TempDir::new().expect("Failed to create temp dir"),
+        }
+    }
+
+    fn socket(&self) -> String {
+        self._temp_dir.path().join("test.sock").to_string_lossy().to_string()
+    }
+}
+
+struct DaemonHandle {
+    _child: std::process::Child,
+    socket_path: String,
+}
+
+impl DaemonHandle {
+    fn spawn_with_socket(socket: &str, command_args: &[&str]) -> Self {
+        use std::process::Stdio;
+
+        let mut cmd = std::process::Command::new(interminai_bin());
+        cmd.arg("start")
+            .args(emulator_args())
+            .arg("--socket")
+            .arg(socket)
+            .arg("--no-daemon")
+            .arg("--");
+
+        for arg in command_args {
+            cmd.arg(arg);
+        }
+
+        let mut child = cmd
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .expect("Failed to spawn daemon");
+
+        let stdout = child.stdout.take().unwrap();
+        let reader = BufReader::new(stdout);
+        let _lines: Vec<String> = reader.lines().take(3).map(|l| l.unwrap()).collect();
+
+        thread::sleep(Duration::from_millis(300));
+
+        DaemonHandle {
+            _child: child,
+            socket_path: socket.to_string(),
+        }
+    }
+
+    fn connect(&self) -> (UnixStream, BufReader<UnixStream>) {
+        let stream = UnixStream::connect(&self.socket_path).expect("Failed to connect");
+        stream.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+        let reader = BufReader::new(stream.try_clone().unwrap());
+        (stream, reader)
+    }
+}
+
+impl Drop for DaemonHandle {
+    fn drop(&mut self) {
+        let _ = Command::new(interminai_bin())
+            .arg("stop")
+            .arg("--socket")
+            .arg(&self.socket_path)
+            .output();
+    }
+}
+
+fn send(stream: &mut UnixStream, request: serde_json::Value) {
+    let json = serde_json::to_string(&request).unwrap();
+    stream.write_all(json.as_bytes()).unwrap();
+    stream.write_all(b"\n").unwrap();
+    stream.flush().unwrap();
+}
+
+fn receive(reader: &mut BufReader<UnixStream>) -> serde_json::Value {
+    let mut line = String::new();
+    reader.read_line(&mut line).expect("Failed to read response");
+    serde_json::from_str(&line).expect("Response is not valid JSON")
+}
+
+// Test several requests served on one connection, with IDs echoed back
+#[test]
+fn test_multiple_requests_on_one_connection() {
+    let env = TestEnv::new();
+    let daemon = DaemonHandle::spawn_with_socket(&env.socket(), &["bash", "-c", "echo hello; sleep 10"]);
+    let (mut stream, mut reader) = daemon.connect();
+
+    send(&mut stream, serde_json::json!({"type": "STATUS", "id": 1}));
+    let response = receive(&mut reader);
+    assert_eq!(response["id"], 1);
+    assert_eq!(response["status"], "ok");
+    assert_eq!(response["data"]["running"], true);
+
+    send(&mut stream, serde_json::json!({"type": "OUTPUT", "id": "screen"}));
+    let response = receive(&mut reader);
+    assert_eq!(response["id"], "screen");
+    assert!(response["data"]["screen"].as_str().unwrap().contains("hello"));
+}
+
+// Test pipelined requests are answered in order
+#[test]
+fn test_pipelined_requests_answered_in_order() {
+    let env = TestEnv::new();
+    let daemon = DaemonHandle::spawn_with_socket(&env.socket(), &["cat"]);
+    let (mut stream, mut reader) = daemon.connect();
+
+    send(&mut stream, serde_json::json!({"type": "INPUT", "id": 1, "data": "pipelined\r"}));
+    send(&mut stream, serde_json::json!({"type": "STATUS", "id": 2}));
+    send(&mut stream, serde_json::json!({"type": "DEBUG", "id": 3}));
+
+    for expected_id in 1..=3 {
+        let response = receive(&mut reader);
+        assert_eq!(response["id"], expected_id, "Responses out of order: {}", response);
+        assert_eq!(response["status"], "ok");
+    }
+}
+
+// Test responses omit the id field when the request has none
+#[test]
+fn test_response_without_id() {
+    let env = TestEnv::new();
+    let daemon = DaemonHandle::spawn_with_socket(&env.socket(), &["sleep", "10"]);
+    let (mut stream, mut reader) = daemon.connect();
+
+    send(&mut stream, serde_json::json!({"type": "STATUS"}));
+    let response = receive(&mut reader);
+    assert_eq!(response["status"], "ok");
+    assert!(response.get("id").is_none(), "Unexpected id: {}", response);
+}
+
+// Test a failing request doesn't close the connection
+#[test]
+fn test_error_response_keeps_connection_open() {
+    let env = TestEnv::new();
+    let daemon = DaemonHandle::spawn_with_socket(&env.socket(), &["sleep", "10"]);
+    let (mut stream, mut reader) = daemon.connect();
+
+    send(&mut stream, serde_json::json!({"type": "NO_SUCH_COMMAND", "id": 6}));
+    let response = receive(&mut reader);
+    assert_eq!(response["id"], 6);
+    assert_eq!(response["status"], "error");
+
+    send(&mut stream, serde_json::json!({"type": "STATUS", "id": 7}));
+    let response = receive(&mut reader);
+    assert_eq!(response["id"], 7);
+    assert_eq!(response["status"], "ok");
+}
+
+// Test a malformed request gets an error and the connection is closed
+#[test]
+fn test_invalid_json_closes_connection() {
+    let env = TestEnv::new();
+    let daemon = DaemonHandle::spawn_with_socket(&env.socket(), &["sleep", "10"]);
+    let (mut stream, mut reader) = daemon.connect();
+
+    stream.write_all(b"this is not json\n").unwrap();
+    let response = receive(&mut reader);
+    assert_eq!(response["status"], "error");
+
+    let mut line = String::new();
+    let bytes_read = reader.read_line(&mut line).expect("Failed to read");
+    assert_eq!(bytes_read, 0, "Connection should be closed, got: {}", line);
+}
+
+// Test one-shot clients still work after a persistent connection is closed
+#[test]
+fn test_one_shot_client_after_persistent_connection() {
+    let env = TestEnv::new();
+    let daemon = DaemonHandle::spawn_with_socket(&env.socket(), &["sleep", "10"]);
+
+    {
+        let (mut stream, mut reader) = daemon.connect();
+        send(&mut stream, serde_json::json!({"type": "STATUS", "id": 1}));
+        assert_eq!(receive(&mut reader)["status"], "ok");
+    }
+
+    Command::new(interminai_bin())
+        .arg("status")
+        .arg("--socket")
+        .arg(&daemon.socket_path)
+        .timeout(Duration::from_secs(5))
+        .assert()
+        .success();
+}
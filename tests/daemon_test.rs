@@ -216,6 +216,46 @@ fn test_auto_generated_socket_from_output() {
         "Auto-generated socket directory should be removed after stop");
 }
 
+#[test]
+fn test_start_json_when_listening() {
+    let output = Command::new(interminai_server_bin())
+        .args(["start", "--json"])
+        .args(emulator_args())
+        .args(["--", "sleep", "10"])
+        .output()
+        .expect("Failed to execute interminai");
+    assert!(output.status.success(), "Command failed: {}", String::from_utf8_lossy(&output.stderr));
+
+    let started: serde_json::Value = serde_json::from_slice(&output.stdout).expect("Output isn't JSON");
+    let socket_path = started["socket"].as_str().expect("No socket");
+    assert!(started["pid"].as_u64().is_some(), "got: {}", started);
+    assert_eq!(started["auto_generated"], true);
+
+    // The daemon is listening as soon as start returns
+    Command::new(interminai_client_bin())
+        .args(["stop", "--socket", socket_path])
+        .assert()
+        .success();
+}
+
+#[test]
+fn test_start_reports_daemon_failure() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let socket_path = temp_dir.path().join("missing").join("daemon.sock");
+
+    let output = Command::new(interminai_server_bin())
+        .arg("start")
+        .args(emulator_args())
+        .arg("--socket")
+        .arg(&socket_path)
+        .args(["--", "sleep", "10"])
+        .output()
+        .expect("Failed to execute interminai");
+    assert!(!output.status.success(), "start succeeded without a socket");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("Failed to listen on") && stderr.contains("No such file or directory"), "got: {}", stderr);
+}
+
 #[test]
 fn test_pty_dump_captures_output() {
     let temp_dir = TempDir::new().expect("Failed to create temp dir");
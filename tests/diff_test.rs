@@ -0,0 +1,223 @@
+mod common;
+use common::{interminai_bin, emulator_args};
+
+use assert_cmd::Command;
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::net::UnixStream;
+use std::thread;
+use std::time::Duration;
+use tempfile::TempDir;
+
+struct TestEnv {
+    _temp_dir: TempDir,
+}
+
+impl TestEnv {
+    fn new() -> Self {
+        Self {
+            _temp_dir: TempDir::new().expect("Failed to create temp dir"),
+        }
+    }
+
+    fn socket(&self) -> String {
+        self._temp_dir.path().join("test.sock").to_string_lossy().to_string()
+    }
+}
+
+struct DaemonHandle {
+    _child: std::process::Child,
+    socket_path: String,
+}
+
+impl DaemonHandle {
+    fn spawn_with_socket(socket: &str, command_args: &[&str]) -> Self {
+        use std::process::Stdio;
+
+        let mut cmd = std::process::Command::new(interminai_bin());
+        cmd.arg("start")
+            .args(emulator_args())
+            .arg("--socket")
+            .arg(socket)
+            .arg("--no-daemon")
+            .arg("--");
+
+        for arg in command_args {
+            cmd.arg(arg);
+        }
+
+        let mut child = cmd
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .expect("Failed to spawn daemon");
+
+        let stdout = child.stdout.take().unwrap();
+        let reader = BufReader::new(stdout);
+        let _lines: Vec<String> = reader.lines().take(3).map(|l| l.unwrap()).collect();
+
+        thread::sleep(Duration::from_millis(300));
+
+        DaemonHandle {
+            _child: child,
+            socket_path: socket.to_string(),
+        }
+    }
+}
+
+impl Drop for DaemonHandle {
+    fn drop(&mut self) {
+        let _ = Command::new(interminai_bin())
+            .arg("stop")
+            .arg("--socket")
+            .arg(&self.socket_path)
+            .output();
+    }
+}
+
+fn request(socket: &str, request: serde_json::Value) -> serde_json::Value {
+    let mut stream = UnixStream::connect(socket).expect("Failed to connect");
+    stream.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+    stream.write_all(format!("{}\n", request).as_bytes()).unwrap();
+    let mut line = String::new();
+    BufReader::new(stream).read_line(&mut line).expect("No response");
+    serde_json::from_str(&line).expect("Response is not valid JSON")
+}
+
+// Test the generation only moves when the screen changes
+#[test]
+fn test_generation_tracks_screen_changes() {
+    let env = TestEnv::new();
+    let daemon = DaemonHandle::spawn_with_socket(&env.socket(), &["cat"]);
+
+    let first = request(&daemon.socket_path, serde_json::json!({"type": "OUTPUT"}));
+    let again = request(&daemon.socket_path, serde_json::json!({"type": "OUTPUT"}));
+    assert_eq!(first["data"]["generation"], again["data"]["generation"]);
+
+    Command::new(interminai_bin())
+        .args(["input", "--socket", &daemon.socket_path, "--text", "hello\\r"])
+        .assert()
+        .success();
+    thread::sleep(Duration::from_millis(300));
+
+    let changed = request(&daemon.socket_path, serde_json::json!({"type": "OUTPUT"}));
+    assert!(changed["data"]["generation"].as_u64().unwrap() > first["data"]["generation"].as_u64().unwrap());
+}
+
+// Test OUTPUT with since returns only the rows that changed
+#[test]
+fn test_output_since_returns_changed_rows() {
+    let env = TestEnv::new();
+    let daemon = DaemonHandle::spawn_with_socket(&env.socket(), &["bash", "-c", "printf 'one\\ntwo\\nthree\\n'; read x; printf '\\e[2;1HTWO'; sleep 10"]);
+
+    let before = request(&daemon.socket_path, serde_json::json!({"type": "OUTPUT"}));
+    let generation = before["data"]["generation"].clone();
+
+    let unchanged = request(&daemon.socket_path, serde_json::json!({"type": "OUTPUT", "since": generation}));
+    assert_eq!(unchanged["data"]["unchanged"], true);
+    assert_eq!(unchanged["data"]["changed_rows"], serde_json::json!([]));
+    assert!(unchanged["data"].get("screen").is_none());
+
+    Command::new(interminai_bin())
+        .args(["input", "--socket", &daemon.socket_path, "--text", "\\r"])
+        .assert()
+        .success();
+    thread::sleep(Duration::from_millis(300));
+
+    let diff = request(&daemon.socket_path, serde_json::json!({"type": "OUTPUT", "since": generation}));
+    assert_eq!(diff["data"]["unchanged"], false);
+    let rows = diff["data"]["changed_rows"].as_array().unwrap();
+    let row_numbers: Vec<u64> = rows.iter().map(|r| r["row"].as_u64().unwrap()).collect();
+    assert!(row_numbers.contains(&1), "Row 1 should have changed: {:?}", rows);
+    assert!(!row_numbers.contains(&0), "Row 0 didn't change: {:?}", rows);
+    assert!(!row_numbers.contains(&2), "Row 2 didn't change: {:?}", rows);
+    let row = rows.iter().find(|r| r["row"] == 1).unwrap();
+    assert_eq!(row["text"], "TWO");
+}
+
+// Test output --diff prints only lines changed since the previous output
+#[test]
+fn test_output_diff_cli() {
+    let env = TestEnv::new();
+    let daemon = DaemonHandle::spawn_with_socket(&env.socket(), &["cat"]);
+
+    Command::new(interminai_bin())
+        .args(["output", "--socket", &daemon.socket_path])
+        .assert()
+        .success();
+
+    let output = Command::new(interminai_bin())
+        .args(["output", "--socket", &daemon.socket_path, "--diff"])
+        .output()
+        .unwrap();
+    assert!(String::from_utf8_lossy(&output.stdout).starts_with("No changes"));
+
+    Command::new(interminai_bin())
+        .args(["input", "--socket", &daemon.socket_path, "--text", "hello\\r"])
+        .assert()
+        .success();
+    thread::sleep(Duration::from_millis(300));
+
+    let output = Command::new(interminai_bin())
+        .args(["output", "--socket", &daemon.socket_path, "--diff", "--no-color"])
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("1: hello"), "Expected changed first row: {}", stdout);
+    assert!(!stdout.contains("\n3: "), "Unchanged rows printed: {}", stdout);
+}
+
+// Test clients sharing a session each get the changes since their own last output
+#[test]
+fn test_output_diff_per_client() {
+    let env = TestEnv::new();
+    let daemon = DaemonHandle::spawn_with_socket(&env.socket(), &["cat"]);
+    let output = |client: &str, args: &[&str]| {
+        let output = Command::new(interminai_bin())
+            .args(["output", "--socket", &daemon.socket_path, "--no-color"])
+            .args(args)
+            .args(["--client", client])
+            .env_remove("INTERMINAI_CLIENT")
+            .output()
+            .unwrap();
+        String::from_utf8_lossy(&output.stdout).to_string()
+    };
+
+    output("first", &[]);
+    output("second", &[]);
+    Command::new(interminai_bin())
+        .args(["input", "--socket", &daemon.socket_path, "--text", "hello\\r"])
+        .assert()
+        .success();
+    thread::sleep(Duration::from_millis(300));
+
+    let first = output("first", &["--diff"]);
+    assert!(first.contains("1: hello"), "first: {}", first);
+    // The first client looking doesn't hide the change from the second
+    let second = output("second", &["--diff"]);
+    assert!(second.contains("1: hello"), "second: {}", second);
+    let first = output("first", &["--diff"]);
+    assert!(first.starts_with("No changes"), "first: {}", first);
+
+    // $INTERMINAI_CLIENT names the client too
+    let output = Command::new(interminai_bin())
+        .args(["output", "--socket", &daemon.socket_path, "--no-color", "--diff"])
+        .env("INTERMINAI_CLIENT", "second")
+        .output()
+        .unwrap();
+    let second = String::from_utf8_lossy(&output.stdout);
+    assert!(second.starts_with("No changes"), "second: {}", second);
+}
+
+// Test an invalid since value is rejected
+#[test]
+fn test_output_since_invalid() {
+    let env = TestEnv::new();
+    let daemon = DaemonHandle::spawn_with_socket(&env.socket(), &["sleep", "10"]);
+
+    let response = request(&daemon.socket_path, serde_json::json!({"type": "OUTPUT", "since": "yesterday"}));
+    assert_eq!(response["status"], "error");
+
+    // Daemons don't keep a baseline for everyone, clients keep their own
+    let response = request(&daemon.socket_path, serde_json::json!({"type": "OUTPUT", "since": "last"}));
+    assert_eq!(response["status"], "error");
+}
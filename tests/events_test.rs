@@ -0,0 +1,197 @@
+mod common;
+use common::{interminai_bin, emulator_args};
+
+use assert_cmd::Command;
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::net::UnixStream;
+use std::thread;
+use std::time::Duration;
+use tempfile::TempDir;
+
+struct TestEnv {
+    _temp_dir: TempDir,
+}
+
+impl TestEnv {
+    fn new() -> Self {
+        Self {
+            _temp_dir: TempDir::new().expect("Failed to create temp dir"),
+        }
+    }
+
+    fn socket(&self) -> String {
+        self._temp_dir.path().join("test.sock").to_string_lossy().to_string()
+    }
+}
+
+struct DaemonHandle {
+    _child: std::process::Child,
+    socket_path: String,
+}
+
+impl DaemonHandle {
+    fn spawn_with_socket(socket: &str, command_args: &[&str]) -> Self {
+        use std::process::Stdio;
+
+        let mut cmd = std::process::Command::new(interminai_bin());
+        cmd.arg("start")
+            .args(emulator_args())
+            .arg("--socket")
+            .arg(socket)
+            .arg("--no-daemon")
+            .arg("--");
+
+        for arg in command_args {
+            cmd.arg(arg);
+        }
+
+        let mut child = cmd
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .expect("Failed to spawn daemon");
+
+        let stdout = child.stdout.take().unwrap();
+        let reader = BufReader::new(stdout);
+        let _lines: Vec<String> = reader.lines().take(3).map(|l| l.unwrap()).collect();
+
+        thread::sleep(Duration::from_millis(300));
+
+        DaemonHandle {
+            _child: child,
+            socket_path: socket.to_string(),
+        }
+    }
+}
+
+impl Drop for DaemonHandle {
+    fn drop(&mut self) {
+        let _ = Command::new(interminai_bin())
+            .arg("stop")
+            .arg("--socket")
+            .arg(&self.socket_path)
+            .output();
+    }
+}
+
+fn parse_events(stdout: &[u8]) -> Vec<serde_json::Value> {
+    String::from_utf8_lossy(stdout)
+        .lines()
+        .map(|line| serde_json::from_str(line).expect("Event is not valid JSON"))
+        .collect()
+}
+
+// Test events report output, screen changes and exit, then the stream ends
+#[test]
+fn test_events_output_and_exit() {
+    let env = TestEnv::new();
+    let daemon = DaemonHandle::spawn_with_socket(&env.socket(), &["bash", "-c", "sleep 0.5; echo hello; sleep 0.3; exit 3"]);
+
+    let output = Command::new(interminai_bin())
+        .arg("events")
+        .arg("--socket")
+        .arg(&daemon.socket_path)
+        .timeout(Duration::from_secs(10))
+        .output()
+        .expect("Failed to run events");
+
+    assert!(output.status.success(), "stderr: {}", String::from_utf8_lossy(&output.stderr));
+    let events = parse_events(&output.stdout);
+    let names: Vec<&str> = events.iter().map(|e| e["event"].as_str().unwrap()).collect();
+
+    assert!(names.contains(&"output"), "No output event: {:?}", names);
+    assert!(names.contains(&"cursor_moved"), "No cursor_moved event: {:?}", names);
+    let changed = events.iter().find(|e| e["event"] == "screen_changed").expect("No screen_changed event");
+    assert!(changed["generation"].as_u64().unwrap() > 0);
+
+    let last = events.last().unwrap();
+    assert_eq!(last["event"], "exit");
+    assert_eq!(last["exit_code"], 3);
+}
+
+// Test resize and termios mode changes are reported
+#[test]
+fn test_events_resize_and_mode() {
+    let env = TestEnv::new();
+    let daemon = DaemonHandle::spawn_with_socket(&env.socket(), &["bash", "-c", "read line; stty raw -echo; echo ready; sleep 10"]);
+
+    let mut stream = UnixStream::connect(&daemon.socket_path).expect("Failed to connect");
+    stream.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+    stream.write_all(b"{\"type\":\"SUBSCRIBE\",\"id\":\"sub\"}\n").unwrap();
+    let mut reader = BufReader::new(stream);
+
+    let mut line = String::new();
+    reader.read_line(&mut line).unwrap();
+    let ack: serde_json::Value = serde_json::from_str(&line).unwrap();
+    assert_eq!(ack["id"], "sub");
+    assert_eq!(ack["status"], "ok");
+    assert_eq!(ack["data"]["running"], true);
+
+    Command::new(interminai_bin())
+        .args(["resize", "--socket", &daemon.socket_path, "--size", "100x30"])
+        .assert()
+        .success();
+    Command::new(interminai_bin())
+        .args(["input", "--socket", &daemon.socket_path, "--text", "go\\r"])
+        .assert()
+        .success();
+
+    let mut seen_resize = false;
+    let mut seen_mode = false;
+    while !(seen_resize && seen_mode) {
+        let mut line = String::new();
+        reader.read_line(&mut line).expect("Timed out waiting for events");
+        let event: serde_json::Value = serde_json::from_str(&line).unwrap();
+        if event["event"] == "resize" {
+            assert_eq!(event["rows"], 30);
+            assert_eq!(event["cols"], 100);
+            seen_resize = true;
+        }
+        if event["event"] == "mode_changed" && event["mode"] == "raw" {
+            assert_eq!(event["echo"], false);
+            seen_mode = true;
+        }
+    }
+}
+
+// Test subscribing after the application exited reports the exit right away
+#[test]
+fn test_events_after_exit() {
+    let env = TestEnv::new();
+    let daemon = DaemonHandle::spawn_with_socket(&env.socket(), &["bash", "-c", "exit 5"]);
+
+    thread::sleep(Duration::from_millis(300));
+
+    let output = Command::new(interminai_bin())
+        .arg("events")
+        .arg("--socket")
+        .arg(&daemon.socket_path)
+        .timeout(Duration::from_secs(5))
+        .output()
+        .expect("Failed to run events");
+
+    assert!(output.status.success());
+    let events = parse_events(&output.stdout);
+    assert_eq!(events.len(), 1, "Unexpected events: {:?}", events);
+    assert_eq!(events[0]["event"], "exit");
+    assert_eq!(events[0]["exit_code"], 5);
+}
+
+// Test events --timeout stops listening with the timeout exit status
+#[test]
+fn test_events_timeout() {
+    let env = TestEnv::new();
+    let daemon = DaemonHandle::spawn_with_socket(&env.socket(), &["sleep", "10"]);
+
+    let output = Command::new(interminai_bin())
+        .arg("events")
+        .arg("--socket")
+        .arg(&daemon.socket_path)
+        .arg("--timeout")
+        .arg("300")
+        .timeout(Duration::from_secs(5))
+        .output()
+        .expect("Failed to run events");
+
+    assert_eq!(output.status.code(), Some(124));
+}
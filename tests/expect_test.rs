@@ -0,0 +1,210 @@
+mod common;
+use common::{interminai_bin, emulator_args};
+
+use assert_cmd::Command;
+use std::io::{BufRead, BufReader};
+use std::thread;
+use std::time::{Duration, Instant};
+use tempfile::TempDir;
+
+struct TestEnv {
+    _temp_dir: TempDir,
+}
+
+impl TestEnv {
+    fn new() -> Self {
+        Self {
+            _temp_dir: TempDir::new().expect("Failed to create temp dir"),
+        }
+    }
+
+    fn socket(&self) -> String {
+        self._temp_dir.path().join("test.sock").to_string_lossy().to_string()
+    }
+}
+
+struct DaemonHandle {
+    _child: std::process::Child,
+    socket_path: String,
+}
+
+impl DaemonHandle {
+    fn spawn_with_socket(socket: &str, command_args: &[&str]) -> Self {
+        use std::process::Stdio;
+
+        let mut cmd = std::process::Command::new(interminai_bin());
+        cmd.arg("start")
+            .args(emulator_args())
+            .arg("--socket")
+            .arg(socket)
+            .arg("--no-daemon")
+            .arg("--");
+
+        for arg in command_args {
+            cmd.arg(arg);
+        }
+
+        let mut child = cmd
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .expect("Failed to spawn daemon");
+
+        let stdout = child.stdout.take().unwrap();
+        let reader = BufReader::new(stdout);
+        let _lines: Vec<String> = reader.lines().take(3).map(|l| l.unwrap()).collect();
+
+        thread::sleep(Duration::from_millis(300));
+
+        DaemonHandle {
+            _child: child,
+            socket_path: socket.to_string(),
+        }
+    }
+}
+
+impl Drop for DaemonHandle {
+    fn drop(&mut self) {
+        let _ = Command::new(interminai_bin())
+            .arg("stop")
+            .arg("--socket")
+            .arg(&self.socket_path)
+            .output();
+    }
+}
+
+fn expect(socket: &str, args: &[&str]) -> std::process::Output {
+    Command::new(interminai_bin())
+        .arg("expect")
+        .arg("--socket")
+        .arg(socket)
+        .args(args)
+        .timeout(Duration::from_secs(10))
+        .output()
+        .expect("Failed to run expect")
+}
+
+// Test expect returns the matching pattern and its 1-based position
+#[test]
+fn test_expect_screen_match_position() {
+    let env = TestEnv::new();
+    let daemon = DaemonHandle::spawn_with_socket(&env.socket(), &["bash", "-c", "echo first; echo '  Ready> '; sleep 10"]);
+
+    let output = expect(&daemon.socket_path, &["Ready>"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    assert!(output.status.success(), "expect failed: {}", String::from_utf8_lossy(&output.stderr));
+    assert!(stdout.contains("Matched: 0"), "got: {}", stdout);
+    assert!(stdout.contains("Match: Ready>"), "got: {}", stdout);
+    assert!(stdout.contains("Position: row 2, col 3"), "got: {}", stdout);
+}
+
+// Test expect blocks until output appears later
+#[test]
+fn test_expect_waits_for_delayed_output() {
+    let env = TestEnv::new();
+    let daemon = DaemonHandle::spawn_with_socket(&env.socket(), &["bash", "-c", "sleep 1; echo done-now; sleep 10"]);
+
+    let start = Instant::now();
+    let output = expect(&daemon.socket_path, &["done-now"]);
+
+    assert!(output.status.success());
+    assert!(start.elapsed() >= Duration::from_millis(500), "expect returned before output appeared");
+}
+
+// Test the first listed pattern that matches wins
+#[test]
+fn test_expect_multiple_patterns() {
+    let env = TestEnv::new();
+    let daemon = DaemonHandle::spawn_with_socket(&env.socket(), &["bash", "-c", "echo 'Password:'; sleep 10"]);
+
+    let output = expect(&daemon.socket_path, &["\\$ $", "[Pp]assword:"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    assert!(output.status.success());
+    assert!(stdout.contains("Matched: 1"), "got: {}", stdout);
+}
+
+// Test expect fails with exit code 1 on timeout
+#[test]
+fn test_expect_timeout() {
+    let env = TestEnv::new();
+    let daemon = DaemonHandle::spawn_with_socket(&env.socket(), &["sleep", "10"]);
+
+    let start = Instant::now();
+    let output = expect(&daemon.socket_path, &["--timeout", "300", "never-appears"]);
+
+    assert_eq!(output.status.code(), Some(124));
+    assert!(start.elapsed() < Duration::from_secs(3), "timeout not honored");
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Timed out"));
+}
+
+// Test expect reports when the application exits without a match
+#[test]
+fn test_expect_application_exits() {
+    let env = TestEnv::new();
+    let daemon = DaemonHandle::spawn_with_socket(&env.socket(), &["bash", "-c", "echo bye; sleep 0.3"]);
+
+    let output = expect(&daemon.socket_path, &["never-appears"]);
+
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("exited"));
+}
+
+// Test the output target sees text that has scrolled off the screen
+#[test]
+fn test_expect_output_target_scrolled_text() {
+    let env = TestEnv::new();
+    let daemon = DaemonHandle::spawn_with_socket(&env.socket(), &["bash", "-c", "for i in $(seq 1 100); do printf '\\e[1mline %d\\e[0m\\n' $i; done; sleep 10"]);
+
+    thread::sleep(Duration::from_millis(300));
+
+    // Scrolled off the screen, so only the output target finds it
+    let output = expect(&daemon.socket_path, &["--timeout", "300", "line 3\\b"]);
+    assert_eq!(output.status.code(), Some(124));
+
+    let output = expect(&daemon.socket_path, &["--target", "output", "line 3\\b"]);
+    assert!(output.status.success(), "stderr: {}", String::from_utf8_lossy(&output.stderr));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Position: row 3, col 1"));
+
+    // Output up to the match was consumed (line 3's newline is still pending)
+    let output = expect(&daemon.socket_path, &["--target", "output", "--timeout", "300", "line 2\\b"]);
+    assert_eq!(output.status.code(), Some(124));
+    let output = expect(&daemon.socket_path, &["--target", "output", "line 4\\b"]);
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Position: row 2, col 1"));
+}
+
+// Test callers sharing a session don't consume each other's output, and an
+// explicit --offset leaves the caller's place alone
+#[test]
+fn test_expect_output_target_per_client() {
+    let env = TestEnv::new();
+    let daemon = DaemonHandle::spawn_with_socket(&env.socket(), &["bash", "-c", "for i in $(seq 1 5); do echo line $i; done; sleep 10"]);
+
+    let alice = |args: &[&str]| expect(&daemon.socket_path, &[&["--client", "alice", "--target", "output", "--timeout", "300"], args].concat());
+    let bob = |args: &[&str]| expect(&daemon.socket_path, &[&["--client", "bob", "--target", "output", "--timeout", "300"], args].concat());
+
+    assert!(alice(&["line 4"]).status.success());
+    assert_eq!(alice(&["line 2"]).status.code(), Some(124));
+
+    // Bob still sees everything alice matched
+    assert!(bob(&["line 2"]).status.success());
+
+    // Alice looks back without losing her place
+    assert!(alice(&["--offset", "0", "line 1"]).status.success());
+    assert_eq!(alice(&["line 3"]).status.code(), Some(124));
+    assert!(alice(&["line 5"]).status.success());
+}
+
+// Test an invalid regex is reported as an error
+#[test]
+fn test_expect_invalid_pattern() {
+    let env = TestEnv::new();
+    let daemon = DaemonHandle::spawn_with_socket(&env.socket(), &["sleep", "10"]);
+
+    let output = expect(&daemon.socket_path, &["("]);
+
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Invalid pattern"));
+}
@@ -0,0 +1,142 @@
+mod common;
+use common::{interminai_bin, emulator, emulator_args};
+
+use assert_cmd::Command;
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::net::UnixStream;
+use std::thread;
+use std::time::Duration;
+use tempfile::TempDir;
+
+struct DaemonHandle {
+    socket_path: String,
+    _temp_dir: TempDir,
+}
+
+impl DaemonHandle {
+    fn spawn(args: &[&str]) -> Self {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let socket_path = temp_dir.path().join("test.sock").to_string_lossy().to_string();
+
+        Command::new(interminai_bin())
+            .arg("start")
+            .args(emulator_args())
+            .args(["--socket", &socket_path])
+            .args(args)
+            .args(["--", "cat"])
+            .timeout(Duration::from_secs(5))
+            .assert()
+            .success();
+        thread::sleep(Duration::from_millis(300));
+
+        DaemonHandle {
+            socket_path,
+            _temp_dir: temp_dir,
+        }
+    }
+
+    fn hello(&self, args: &[&str]) -> std::process::Output {
+        Command::new(interminai_bin())
+            .args(["hello", "--socket", &self.socket_path])
+            .args(args)
+            .timeout(Duration::from_secs(5))
+            .output()
+            .expect("Failed to run hello")
+    }
+}
+
+impl Drop for DaemonHandle {
+    fn drop(&mut self) {
+        let _ = Command::new(interminai_bin())
+            .args(["stop", "--socket", &self.socket_path])
+            .output();
+    }
+}
+
+fn request(socket: &str, request: serde_json::Value) -> serde_json::Value {
+    let mut stream = UnixStream::connect(socket).expect("Failed to connect");
+    stream.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+    stream.write_all(format!("{}\n", request).as_bytes()).unwrap();
+    let mut line = String::new();
+    BufReader::new(stream).read_line(&mut line).expect("No response");
+    serde_json::from_str(&line).expect("Response is not valid JSON")
+}
+
+#[test]
+fn test_hello_describes_session() {
+    let daemon = DaemonHandle::spawn(&["--scrollback", "50", "--output-buffer", "4096"]);
+
+    let response = request(&daemon.socket_path, serde_json::json!({"type": "HELLO", "id": 1}));
+    assert_eq!(response["status"], "ok");
+    assert_eq!(response["id"], 1);
+    let data = &response["data"];
+    assert_eq!(data["protocol"], 1);
+    assert_eq!(data["daemon"], "interminai");
+    assert!(data["version"].is_string());
+    assert_eq!(data["server"], false);
+    assert_eq!(data["emulator"], emulator());
+    assert_eq!(data["colors"], emulator() == "xterm");
+    assert_eq!(data["limits"]["scrollback"], 50);
+    assert_eq!(data["limits"]["output_buffer"], 4096);
+
+    let requests: Vec<&str> = data["requests"].as_array().unwrap().iter().filter_map(|r| r.as_str()).collect();
+    for expected in ["HELLO", "OUTPUT", "RESIZE", "DEBUG"] {
+        assert!(requests.contains(&expected), "{} missing from {:?}", expected, requests);
+    }
+    assert!(!requests.contains(&"NEW"), "a session doesn't host sessions");
+    assert!(data["formats"]["OUTPUT"].as_array().unwrap().contains(&serde_json::json!("ansi")));
+}
+
+#[test]
+fn test_hello_require() {
+    let daemon = DaemonHandle::spawn(&[]);
+
+    let output = daemon.hello(&[]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Protocol: 1"), "got: {}", stdout);
+    assert!(stdout.contains(&format!("Emulator: {}", emulator())), "got: {}", stdout);
+
+    let output = daemon.hello(&["--require", "resize", "--require", "OUTPUT:svg"]);
+    assert!(output.status.success(), "got: {}", String::from_utf8_lossy(&output.stderr));
+
+    let output = daemon.hello(&["--require", "OUTPUT:pdf"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("does not support OUTPUT:pdf"));
+
+    let output = daemon.hello(&["--require", "TELEPORT"]);
+    assert!(!output.status.success());
+}
+
+#[test]
+fn test_hello_on_server() {
+    use std::process::Stdio;
+
+    let temp_dir = TempDir::new().unwrap();
+    let socket_path = temp_dir.path().join("server.sock").to_string_lossy().to_string();
+    let mut server = std::process::Command::new(interminai_bin())
+        .args(["server", "--socket", &socket_path, "--no-daemon"])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .expect("Failed to spawn server");
+    thread::sleep(Duration::from_millis(300));
+
+    // Without a session, the server describes itself
+    let response = request(&socket_path, serde_json::json!({"type": "HELLO"}));
+    assert_eq!(response["status"], "ok");
+    assert_eq!(response["data"]["server"], true);
+    assert!(response["data"]["requests"].as_array().unwrap().contains(&serde_json::json!("NEW")));
+    assert!(response["data"].get("emulator").is_none());
+
+    request(&socket_path, serde_json::json!({"type": "NEW", "name": "shell", "command": ["cat"], "emulator": emulator()}));
+    let response = request(&socket_path, serde_json::json!({"type": "HELLO", "session": "shell"}));
+    assert_eq!(response["data"]["emulator"], emulator());
+    assert_eq!(response["data"]["limits"]["scrollback"], 1000);
+
+    let response = request(&socket_path, serde_json::json!({"type": "HELLO", "session": "nope"}));
+    assert_eq!(response["status"], "error");
+
+    request(&socket_path, serde_json::json!({"type": "STOP"}));
+    let _ = server.wait();
+}
@@ -0,0 +1,75 @@
+mod common;
+use common::{interminai_bin, emulator, start_cat, expect_screen};
+
+use assert_cmd::Command;
+use interminai::DaemonError;
+use std::time::Duration;
+
+#[test]
+fn test_keys_follow_cursor_and_keypad_modes() {
+    let session = start_cat("", (60, 10));
+    session.input_keys("Up Home F1 C-c KP5").unwrap();
+    expect_screen(&session, "^[[A^[[H^[OP^C5");
+
+    let session = start_cat("\\033[?1h\\033=", (60, 10));
+    session.input_keys("Up Home F1 C-c KP5 KPEnter").unwrap();
+    expect_screen(&session, "^[OA^[OH^[OP^C^[Ou^[OM");
+}
+
+#[test]
+fn test_keys_with_modifiers() {
+    let session = start_cat("", (60, 10));
+    session.input_keys("S-Tab BTab M-x M-X C-Up S-F5 DC PgUp Space C-Space S-a").unwrap();
+    expect_screen(&session, "^[[Z^[[Z^[x^[X^[[1;5A^[[15;2~^[[3~^[[5~ ^@A");
+}
+
+#[test]
+fn test_keys_kitty_protocol() {
+    if emulator() == "custom" {
+        // The custom emulator doesn't implement the kitty keyboard protocol
+        return;
+    }
+    // Push the disambiguate flag
+    let session = start_cat("\\033[>1u", (60, 10));
+    session.input_keys("C-c Escape a Enter C-Enter M-x Up KP5 F3").unwrap();
+    expect_screen(&session, "^[[99;5u^[[27ua^M^[[13;5u^[[120;3u^[[A^[[57404u^[[13~");
+
+    // Report every key as an escape code, with associated text
+    let session = start_cat("\\033[>25u", (60, 10));
+    session.input_keys("a S-b Enter").unwrap();
+    expect_screen(&session, "^[[97;1;97u^[[98;2;66u^[[13u");
+}
+
+#[test]
+fn test_keys_errors() {
+    let session = start_cat("", (60, 10));
+
+    let err = session.input_keys("Up Entr").unwrap_err();
+    assert!(err.downcast_ref::<DaemonError>().is_some(), "got: {:?}", err);
+    assert!(err.to_string().contains("Unknown key 'Entr'"), "got: {}", err);
+
+    let err = session.input_keys("C-1").unwrap_err();
+    assert!(err.to_string().contains("Key 'C-1' can't be sent"), "got: {}", err);
+
+    let err = session.request(serde_json::json!({"type": "INPUT", "data": "x", "keys": "Enter"})).unwrap_err();
+    assert!(err.to_string().contains("not both"), "got: {}", err);
+}
+
+#[test]
+fn test_input_keys_command() {
+    let session = start_cat("\\033[?1h", (60, 10));
+    let socket = session.socket().to_str().unwrap().to_string();
+
+    Command::new(interminai_bin())
+        .args(["input", "--socket", &socket, "--keys", "Down Enter"])
+        .timeout(Duration::from_secs(5))
+        .assert()
+        .success();
+    expect_screen(&session, "^[OB^M");
+
+    Command::new(interminai_bin())
+        .args(["input", "--socket", &socket, "--keys", "Up", "--text", "x"])
+        .timeout(Duration::from_secs(5))
+        .assert()
+        .failure();
+}
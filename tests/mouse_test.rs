@@ -0,0 +1,120 @@
+mod common;
+use common::{interminai_bin, start_cat, expect_screen};
+
+use assert_cmd::Command;
+use interminai::{DaemonError, Mouse, MouseButton};
+use std::time::Duration;
+
+#[test]
+fn test_mouse_sgr() {
+    let session = start_cat("\\033[?1000h\\033[?1006h", (60, 10));
+    session.mouse(Mouse::Click { row: 5, col: 10, button: MouseButton::Right }).unwrap();
+    session.mouse(Mouse::Scroll { row: 0, col: 0, up: false }).unwrap();
+    expect_screen(&session, "^[[<2;11;6M^[[<2;11;6m^[[<65;1;1M");
+}
+
+#[test]
+fn test_mouse_x10() {
+    let session = start_cat("\\033[?1000h", (60, 10));
+    session.mouse(Mouse::Click { row: 5, col: 10, button: MouseButton::Left }).unwrap();
+    session.mouse(Mouse::Scroll { row: 5, col: 10, up: true }).unwrap();
+    expect_screen(&session, "^[[M +&^[[M#+&^[[M`+&");
+}
+
+#[test]
+fn test_mouse_drag() {
+    // Click-only reporting gets the press and the release
+    let session = start_cat("\\033[?1000h\\033[?1006h", (60, 10));
+    session.mouse(Mouse::Drag { from: (1, 2), to: (3, 4), button: MouseButton::Left }).unwrap();
+    expect_screen(&session, "^[[<0;3;2M^[[<0;5;4m");
+
+    // Drag reporting also gets the motion in between
+    let session = start_cat("\\033[?1002h\\033[?1006h", (60, 10));
+    session.mouse(Mouse::Drag { from: (1, 2), to: (3, 4), button: MouseButton::Middle }).unwrap();
+    expect_screen(&session, "^[[<1;3;2M^[[<33;5;4M^[[<1;5;4m");
+}
+
+#[test]
+fn test_mouse_press_only() {
+    // X10 mode reports presses without releases, and replaces click reporting
+    let session = start_cat("\\033[?1000h\\033[?9h", (60, 10));
+    assert_eq!(session.request(serde_json::json!({"type": "MOUSE", "row": 5, "col": 10})).unwrap()["tracking"], "press");
+    session.mouse(Mouse::Drag { from: (1, 2), to: (3, 4), button: MouseButton::Left }).unwrap();
+    session.mouse(Mouse::Scroll { row: 5, col: 10, up: true }).unwrap();
+    expect_screen(&session, "^[[M +&^[[M #\"^[[M`+&");
+
+    // Back to presses and releases
+    let session = start_cat("\\033[?9h\\033[?1000h", (60, 10));
+    session.mouse(Mouse::Click { row: 5, col: 10, button: MouseButton::Left }).unwrap();
+    expect_screen(&session, "^[[M +&^[[M#+&");
+}
+
+#[test]
+fn test_modes_set_with_others() {
+    // Modes set in one sequence with ones that don't change input still count
+    let session = start_cat("\\033[?1;1049h\\033[?1000;1006;25h", (60, 10));
+    session.input_keys("Up").unwrap();
+    session.mouse(Mouse::Click { row: 1, col: 2, button: MouseButton::Left }).unwrap();
+    expect_screen(&session, "^[OA^[[<0;3;2M^[[<0;3;2m");
+}
+
+#[test]
+fn test_mouse_utf8() {
+    let session = start_cat("\\033[?1000h\\033[?1005h", (200, 10));
+    // Column 100 is 100 + 1 + 32 = U+0085, two bytes in UTF-8
+    session.mouse(Mouse::Click { row: 0, col: 100, button: MouseButton::Left }).unwrap();
+    expect_screen(&session, "^[[M M-BM-^E!^[[M#M-BM-^E!");
+}
+
+#[test]
+fn test_mouse_errors() {
+    let session = start_cat("", (60, 10));
+    let err = session.mouse(Mouse::Click { row: 0, col: 0, button: MouseButton::Left }).unwrap_err();
+    assert!(err.downcast_ref::<DaemonError>().is_some(), "got: {:?}", err);
+    assert!(err.to_string().contains("hasn't turned on mouse reporting"), "got: {}", err);
+
+    // Reporting turned off again
+    let session = start_cat("\\033[?1002h\\033[?1002l", (60, 10));
+    let err = session.mouse(Mouse::Click { row: 0, col: 0, button: MouseButton::Left }).unwrap_err();
+    assert!(err.to_string().contains("hasn't turned on mouse reporting"), "got: {}", err);
+
+    let session = start_cat("\\033[?1000h", (250, 10));
+    let err = session.mouse(Mouse::Click { row: 0, col: 230, button: MouseButton::Left }).unwrap_err();
+    assert!(err.to_string().contains("only reach row and column 223"), "got: {}", err);
+    let err = session.mouse(Mouse::Click { row: 10, col: 0, button: MouseButton::Left }).unwrap_err();
+    assert!(err.to_string().contains("outside the 250x10 screen"), "got: {}", err);
+
+    let request = serde_json::json!({"type": "MOUSE", "row": 0, "col": 0, "scroll": "up", "drag_to": {"row": 1, "col": 1}});
+    let err = session.request(request).unwrap_err();
+    assert!(err.to_string().contains("not both"), "got: {}", err);
+}
+
+#[test]
+fn test_mouse_command() {
+    let session = start_cat("\\033[?1000h\\033[?1006h", (60, 10));
+    let socket = session.socket().to_str().unwrap().to_string();
+
+    // Positions count from 1 on the command line
+    Command::new(interminai_bin())
+        .args(["mouse", "--socket", &socket, "--click", "2,3", "--button", "right"])
+        .timeout(Duration::from_secs(5))
+        .assert()
+        .success();
+    Command::new(interminai_bin())
+        .args(["mouse", "--socket", &socket, "--click", "2,3", "--scroll", "up"])
+        .timeout(Duration::from_secs(5))
+        .assert()
+        .success();
+    expect_screen(&session, "^[[<2;3;2M^[[<2;3;2m^[[<64;3;2M");
+
+    Command::new(interminai_bin())
+        .args(["mouse", "--socket", &socket, "--click", "0,3"])
+        .timeout(Duration::from_secs(5))
+        .assert()
+        .failure();
+    Command::new(interminai_bin())
+        .args(["mouse", "--socket", &socket, "--click", "1,1", "--scroll", "up", "--drag-to", "2,2"])
+        .timeout(Duration::from_secs(5))
+        .assert()
+        .failure();
+}
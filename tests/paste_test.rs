@@ -0,0 +1,74 @@
+mod common;
+use common::{interminai_bin, start_raw, start_cat, expect_screen};
+
+use assert_cmd::Command;
+use std::time::{Duration, Instant};
+
+#[test]
+fn test_paste_is_bracketed_when_enabled() {
+    let session = start_cat("\\033[?2004h", (60, 10));
+    // An end marker inside the text must not end the paste early
+    assert!(session.paste("one|two\x1b[201~three|\x1b[20\x1b[201~1~").unwrap());
+    expect_screen(&session, "^[[200~one|twothree|^[[201~");
+}
+
+#[test]
+fn test_paste_falls_back_to_typing() {
+    let session = start_cat("\\033[?2004h\\033[?2004l", (60, 10));
+    assert!(!session.paste("plain\x1b[201~").unwrap());
+    expect_screen(&session, "plain^[[201~");
+}
+
+#[test]
+fn test_paste_large_input() {
+    // More than the PTY buffer holds at once
+    let session = start_raw("printf 'ready\\r\\n'; head -c 100000 | wc -c", (60, 10));
+    assert!(!session.paste(&"x".repeat(100000)).unwrap());
+    expect_screen(&session, "100000");
+}
+
+#[test]
+fn test_session_answers_while_input_waits() {
+    // The application doesn't read its input for a while
+    let session = start_raw("printf 'ready\\r\\n'; sleep 2; head -c 100000 | wc -c", (60, 10));
+    let paster = {
+        let client = session.client().clone();
+        std::thread::spawn(move || client.paste(&"x".repeat(100000)))
+    };
+    std::thread::sleep(Duration::from_millis(300));
+
+    let started = Instant::now();
+    session.client().clone().timeout(Duration::from_secs(1)).output().unwrap();
+    assert!(started.elapsed() < Duration::from_secs(1), "OUTPUT took {:?}", started.elapsed());
+
+    assert!(!paster.join().unwrap().unwrap());
+    expect_screen(&session, "100000");
+}
+
+#[test]
+fn test_input_paste_command() {
+    let session = start_cat("\\033[?2004h", (60, 10));
+    let socket = session.socket().to_str().unwrap().to_string();
+
+    let output = Command::new(interminai_bin())
+        .args(["input", "--socket", &socket, "--paste", "--text", "a\\rb"])
+        .timeout(Duration::from_secs(5))
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert!(output.stderr.is_empty(), "got: {}", String::from_utf8_lossy(&output.stderr));
+    expect_screen(&session, "^[[200~a^Mb^[[201~");
+
+    let session = start_cat("", (60, 10));
+    let socket = session.socket().to_str().unwrap().to_string();
+    let output = Command::new(interminai_bin())
+        .args(["input", "--socket", &socket, "--paste"])
+        .write_stdin("typed")
+        .timeout(Duration::from_secs(5))
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("Warning") && stderr.contains("bracketed paste"), "got: {}", stderr);
+    expect_screen(&session, "typed");
+}
@@ -0,0 +1,115 @@
+mod common;
+use common::{interminai_bin, emulator};
+
+use assert_cmd::Command;
+use std::time::Duration;
+use tempfile::TempDir;
+
+/// Play `tape` from a temp dir, returning success, stdout, stderr and the dir
+fn play(tape: &str) -> (bool, String, String, TempDir) {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let path = temp_dir.path().join("test.tape");
+    std::fs::write(&path, tape).unwrap();
+
+    let output = Command::new(interminai_bin())
+        .args(["play", "--emulator", &emulator()])
+        .arg(&path)
+        .current_dir(temp_dir.path())
+        .timeout(Duration::from_secs(20))
+        .output()
+        .expect("Failed to run play");
+    (
+        output.status.success(),
+        String::from_utf8_lossy(&output.stdout).to_string(),
+        String::from_utf8_lossy(&output.stderr).to_string(),
+        temp_dir,
+    )
+}
+
+#[test]
+fn test_play_tape() {
+    let (ok, stdout, stderr, dir) = play(r#"
+# Settings for the video are ignored
+Output demo.gif
+Set FontSize 13
+Set Theme "VibrantInk"
+Set Width 600
+Set Height 300
+Set Shell "sh"
+Env PS1 "ready> "
+
+Wait
+Type "echo hel''lo"
+Enter
+Wait+Screen /^hello$/
+Type "stty size"
+Enter
+Wait+Screen@5s /^15 60$/
+Screenshot screen.png
+
+Set Width 400
+Type "stty size"
+Enter
+Wait /^ready>$/
+Wait+Screen /^15 40$/
+Sleep 100ms
+Type "cat"
+Enter
+Sleep 200ms
+Ctrl+C
+Wait
+"#);
+    assert!(ok, "play failed: {}", stderr);
+    assert!(stdout.contains("Screenshot: screen.png (600x300)"), "got: {}", stdout);
+    let png = std::fs::read(dir.path().join("screen.png")).expect("screenshot not written");
+    assert!(png.starts_with(b"\x89PNG"));
+}
+
+#[test]
+fn test_play_reports_failing_step() {
+    let (ok, _, stderr, _dir) = play(r#"Set Shell "sh"
+Env PS1 "ready> "
+Wait
+Type "echo first"
+Enter
+Wait@500ms /never shows up/
+Type "echo second"
+"#);
+    assert!(!ok);
+    assert!(stderr.contains("test.tape:6: Wait@500ms /never shows up/"), "got: {}", stderr);
+    assert!(stderr.contains("Screen:"), "got: {}", stderr);
+    assert!(stderr.contains("ready> echo first"), "screen not shown: {}", stderr);
+    assert!(!stderr.contains("second"), "ran past the failing step: {}", stderr);
+}
+
+#[test]
+fn test_play_rejects_unsupported_commands() {
+    // The whole tape is checked before anything runs
+    let (ok, stdout, stderr, dir) = play("Type \"echo hi\"\nScreenshot early.png\nSource other.tape\n");
+    assert!(!ok);
+    assert!(stderr.contains("test.tape:3: Unsupported command 'Source'"), "got: {}", stderr);
+    assert!(stdout.is_empty());
+    assert!(!dir.path().join("early.png").exists());
+
+    let (ok, _, stderr, _dir) = play("Set Shell \"sh\"\nType \"x\"\nSet Shell \"bash\"\n");
+    assert!(!ok);
+    assert!(stderr.contains("Set Shell must come before"), "got: {}", stderr);
+}
+
+#[test]
+fn test_play_env_is_the_shells() {
+    // Env goes to the shell, and Require looks along its PATH
+    let (ok, _, stderr, _dir) = play("Env PATH \"/nonexistent\"\nRequire sh\n");
+    assert!(!ok);
+    assert!(stderr.contains("sh is not installed"), "got: {}", stderr);
+
+    let (ok, _, stderr, _dir) = play(r#"Set Shell "/bin/sh"
+Env PS1 "ready> "
+Env GREETING "hi there"
+Wait
+Type "echo $GREETING"
+Enter
+Wait+Screen /^hi there$/
+"#);
+    assert!(ok, "got: {}", stderr);
+}
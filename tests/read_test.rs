@@ -0,0 +1,231 @@
+mod common;
+use common::{interminai_bin, emulator_args};
+
+use assert_cmd::Command;
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::net::UnixStream;
+use std::thread;
+use std::time::Duration;
+use tempfile::TempDir;
+
+struct TestEnv {
+    _temp_dir: TempDir,
+}
+
+impl TestEnv {
+    fn new() -> Self {
+        Self {
+            _temp_dir: TempDir::new().expect("Failed to create temp dir"),
+        }
+    }
+
+    fn socket(&self) -> String {
+        self._temp_dir.path().join("test.sock").to_string_lossy().to_string()
+    }
+}
+
+struct DaemonHandle {
+    _child: std::process::Child,
+    socket_path: String,
+}
+
+impl DaemonHandle {
+    fn spawn_with_socket(socket: &str, command_args: &[&str]) -> Self {
+        Self::spawn_with_args(socket, &[], command_args)
+    }
+
+    fn spawn_with_args(socket: &str, start_args: &[&str], command_args: &[&str]) -> Self {
+        use std::process::Stdio;
+
+        let mut cmd = std::process::Command::new(interminai_bin());
+        cmd.arg("start")
+            .args(emulator_args())
+            .args(start_args)
+            .arg("--socket")
+            .arg(socket)
+            .arg("--no-daemon")
+            .arg("--");
+
+        for arg in command_args {
+            cmd.arg(arg);
+        }
+
+        let mut child = cmd
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .expect("Failed to spawn daemon");
+
+        let stdout = child.stdout.take().unwrap();
+        let reader = BufReader::new(stdout);
+        let _lines: Vec<String> = reader.lines().take(3).map(|l| l.unwrap()).collect();
+
+        thread::sleep(Duration::from_millis(300));
+
+        DaemonHandle {
+            _child: child,
+            socket_path: socket.to_string(),
+        }
+    }
+}
+
+impl Drop for DaemonHandle {
+    fn drop(&mut self) {
+        let _ = Command::new(interminai_bin())
+            .arg("stop")
+            .arg("--socket")
+            .arg(&self.socket_path)
+            .output();
+    }
+}
+
+fn request(socket: &str, request: serde_json::Value) -> serde_json::Value {
+    let mut stream = UnixStream::connect(socket).expect("Failed to connect");
+    stream.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+    stream.write_all(format!("{}\n", request).as_bytes()).unwrap();
+    let mut line = String::new();
+    BufReader::new(stream).read_line(&mut line).expect("No response");
+    serde_json::from_str(&line).expect("Response is not valid JSON")
+}
+
+const COLORED: &str = "printf '\\e[31mred\\e[0m h\\xc3\\xa9llo\\n'; sleep 10";
+
+/// The bytes of a raw READ's data
+fn decode(data: &serde_json::Value) -> Vec<u8> {
+    use base64::Engine;
+    assert_eq!(data["encoding"], "base64");
+    base64::engine::general_purpose::STANDARD.decode(data["data"].as_str().unwrap()).expect("Invalid base64")
+}
+
+// Test READ returns raw output with escape sequences and the next offset
+#[test]
+fn test_read_raw() {
+    let env = TestEnv::new();
+    let daemon = DaemonHandle::spawn_with_socket(&env.socket(), &["bash", "-c", COLORED]);
+
+    let response = request(&daemon.socket_path, serde_json::json!({"type": "READ", "offset": 0}));
+    assert_eq!(response["status"], "ok");
+    let data = &response["data"];
+    assert_eq!(decode(data), "\x1b[31mred\x1b[0m h\u{e9}llo\r\n".as_bytes());
+    assert_eq!(data["offset"], 0);
+    assert_eq!(data["next_offset"], 21);
+    assert_eq!(data["end_offset"], 21);
+    assert_eq!(data["dropped"], 0);
+    assert_eq!(data["running"], true);
+
+    let response = request(&daemon.socket_path, serde_json::json!({"type": "READ", "offset": 21}));
+    assert_eq!(decode(&response["data"]), b"");
+    assert_eq!(response["data"]["next_offset"], 21);
+}
+
+// Test READ raw passes on bytes that aren't valid UTF-8 as they are
+#[test]
+fn test_read_raw_binary() {
+    let env = TestEnv::new();
+    let daemon = DaemonHandle::spawn_with_socket(&env.socket(), &["bash", "-c", "printf 'a\\xff\\xc3b\\n'; sleep 10"]);
+    thread::sleep(Duration::from_millis(300));
+
+    let response = request(&daemon.socket_path, serde_json::json!({"type": "READ", "max_bytes": 2}));
+    assert_eq!(decode(&response["data"]), b"a\xff");
+    let response = request(&daemon.socket_path, serde_json::json!({"type": "READ", "offset": 2}));
+    assert_eq!(decode(&response["data"]), b"\xc3b\r\n");
+
+    let output = Command::new(interminai_bin())
+        .args(["read", "--socket", &daemon.socket_path, "--format", "raw"])
+        .timeout(Duration::from_secs(5))
+        .output()
+        .unwrap();
+    assert_eq!(output.stdout, b"a\xff\xc3b\r\n");
+}
+
+// Test an escape sequence that just fits in max_bytes is held back until it is complete
+#[test]
+fn test_read_text_waits_for_incomplete_sequence() {
+    let env = TestEnv::new();
+    let daemon = DaemonHandle::spawn_with_socket(&env.socket(), &["bash", "-c", "printf 'ab\\e[3'; sleep 10"]);
+    thread::sleep(Duration::from_millis(300));
+
+    let response = request(&daemon.socket_path, serde_json::json!({"type": "READ", "offset": 2, "format": "text", "max_bytes": 3}));
+    assert_eq!(response["data"]["data"], "");
+    assert_eq!(response["data"]["next_offset"], 2);
+}
+
+// Test READ in text format strips escape sequences
+#[test]
+fn test_read_text() {
+    let env = TestEnv::new();
+    let daemon = DaemonHandle::spawn_with_socket(&env.socket(), &["bash", "-c", COLORED]);
+
+    let response = request(&daemon.socket_path, serde_json::json!({"type": "READ", "format": "text"}));
+    assert_eq!(response["data"]["data"], "red h\u{e9}llo\n");
+    assert_eq!(response["data"]["next_offset"], 21);
+}
+
+// Test small reads (longer than any escape sequence) never split characters or sequences
+#[test]
+fn test_read_in_small_chunks() {
+    let env = TestEnv::new();
+    let daemon = DaemonHandle::spawn_with_socket(&env.socket(), &["bash", "-c", COLORED]);
+
+    for format in ["raw", "text"] {
+        let mut offset = 0;
+        let mut collected = String::new();
+        for _ in 0..100 {
+            let response = request(&daemon.socket_path, serde_json::json!({
+                "type": "READ", "offset": offset, "format": format, "max_bytes": 6
+            }));
+            match format {
+                "raw" => collected.push_str(&String::from_utf8(decode(&response["data"])).unwrap()),
+                _ => collected.push_str(response["data"]["data"].as_str().unwrap()),
+            }
+            offset = response["data"]["next_offset"].as_u64().unwrap();
+            if offset == 21 {
+                break;
+            }
+        }
+        let expected = if format == "raw" { "\x1b[31mred\x1b[0m h\u{e9}llo\r\n" } else { "red h\u{e9}llo\n" };
+        assert_eq!(collected, expected, "format {}", format);
+    }
+}
+
+// Test start --output-buffer bounds what is kept and READ reports what was lost
+#[test]
+fn test_read_dropped_output() {
+    let env = TestEnv::new();
+    let daemon = DaemonHandle::spawn_with_args(&env.socket(), &["--output-buffer", "100"], &["bash", "-c", "seq 1000 1199; sleep 10"]);
+
+    let response = request(&daemon.socket_path, serde_json::json!({"type": "READ", "offset": 0}));
+    let data = &response["data"];
+    let end = data["end_offset"].as_u64().unwrap();
+    assert_eq!(end, 200 * 6);
+    assert_eq!(data["offset"], end - 100);
+    assert_eq!(data["dropped"], end - 100);
+    assert!(decode(data).ends_with(b"1199\r\n"));
+}
+
+// Test read --follow streams output until the application exits
+#[test]
+fn test_read_follow_cli() {
+    let env = TestEnv::new();
+    let daemon = DaemonHandle::spawn_with_socket(&env.socket(), &["bash", "-c", "echo first; sleep 0.5; echo second; sleep 0.3"]);
+
+    let output = Command::new(interminai_bin())
+        .args(["read", "--socket", &daemon.socket_path, "--follow"])
+        .timeout(Duration::from_secs(10))
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "first\nsecond\n");
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Next offset: 15"));
+}
+
+// Test invalid READ parameters are rejected
+#[test]
+fn test_read_invalid_format() {
+    let env = TestEnv::new();
+    let daemon = DaemonHandle::spawn_with_socket(&env.socket(), &["sleep", "10"]);
+
+    let response = request(&daemon.socket_path, serde_json::json!({"type": "READ", "format": "html"}));
+    assert_eq!(response["status"], "error");
+}
@@ -0,0 +1,204 @@
+mod common;
+use common::{interminai_bin, emulator_args};
+
+use assert_cmd::Command;
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::net::UnixStream;
+use std::thread;
+use std::time::Duration;
+use tempfile::TempDir;
+
+struct TestEnv {
+    _temp_dir: TempDir,
+}
+
+impl TestEnv {
+    fn new() -> Self {
+        Self {
+            _temp_dir: TempDir::new().expect("Failed to create temp dir"),
+        }
+    }
+
+    fn socket(&self) -> String {
+        self._temp_dir.path().join("test.sock").to_string_lossy().to_string()
+    }
+}
+
+struct DaemonHandle {
+    _child: std::process::Child,
+    socket_path: String,
+}
+
+impl DaemonHandle {
+    fn spawn_with_args(socket: &str, start_args: &[&str], command_args: &[&str]) -> Self {
+        use std::process::Stdio;
+
+        let mut cmd = std::process::Command::new(interminai_bin());
+        cmd.arg("start")
+            .args(emulator_args())
+            .args(start_args)
+            .arg("--socket")
+            .arg(socket)
+            .arg("--no-daemon")
+            .arg("--");
+
+        for arg in command_args {
+            cmd.arg(arg);
+        }
+
+        let mut child = cmd
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .expect("Failed to spawn daemon");
+
+        let stdout = child.stdout.take().unwrap();
+        let reader = BufReader::new(stdout);
+        let _lines: Vec<String> = reader.lines().take(3).map(|l| l.unwrap()).collect();
+
+        thread::sleep(Duration::from_millis(300));
+
+        DaemonHandle {
+            _child: child,
+            socket_path: socket.to_string(),
+        }
+    }
+}
+
+impl Drop for DaemonHandle {
+    fn drop(&mut self) {
+        let _ = Command::new(interminai_bin())
+            .arg("stop")
+            .arg("--socket")
+            .arg(&self.socket_path)
+            .output();
+    }
+}
+
+fn request(socket: &str, request: serde_json::Value) -> serde_json::Value {
+    let mut stream = UnixStream::connect(socket).expect("Failed to connect");
+    stream.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+    stream.write_all(format!("{}\n", request).as_bytes()).unwrap();
+    let mut line = String::new();
+    BufReader::new(stream).read_line(&mut line).expect("No response");
+    serde_json::from_str(&line).expect("Response is not valid JSON")
+}
+
+/// Read a recording as lines of JSON
+fn read_cast(path: &std::path::Path) -> Vec<serde_json::Value> {
+    std::fs::read_to_string(path)
+        .expect("Recording not written")
+        .lines()
+        .map(|line| serde_json::from_str(line).expect("Line is not valid JSON"))
+        .collect()
+}
+
+// Test asciicast recordings hold output, input and resize events with timing
+#[test]
+fn test_record_asciicast() {
+    let env = TestEnv::new();
+    let path = env._temp_dir.path().join("session.cast");
+    let daemon = DaemonHandle::spawn_with_args(&env.socket(),
+        &["--size", "80x24", "--record", path.to_str().unwrap()],
+        &["bash", "-c", "read line; echo \"got $line\"; sleep 10"]);
+
+    request(&daemon.socket_path, serde_json::json!({"type": "INPUT", "data": "hello\r"}));
+    thread::sleep(Duration::from_millis(300));
+    request(&daemon.socket_path, serde_json::json!({"type": "RESIZE", "cols": 100, "rows": 30}));
+    thread::sleep(Duration::from_millis(100));
+
+    let lines = read_cast(&path);
+    let header = &lines[0];
+    assert_eq!(header["version"], 2);
+    assert_eq!(header["width"], 80);
+    assert_eq!(header["height"], 24);
+    assert!(header["command"].as_str().unwrap().starts_with("bash -c"));
+
+    let events = &lines[1..];
+    assert!(events.iter().any(|e| e[1] == "i" && e[2] == "hello\r"), "No input event: {:?}", events);
+    assert!(events.iter().any(|e| e[1] == "o" && e[2].as_str().unwrap().contains("got hello")), "No output event: {:?}", events);
+    assert!(events.iter().any(|e| e[1] == "r" && e[2] == "100x30"), "No resize event: {:?}", events);
+
+    // Timestamps never go backwards
+    let times: Vec<f64> = events.iter().map(|e| e[0].as_f64().unwrap()).collect();
+    assert!(times.windows(2).all(|pair| pair[0] <= pair[1]), "Times out of order: {:?}", times);
+}
+
+// Test script recordings are a log plus a timing file that describes it exactly
+#[test]
+fn test_record_script() {
+    let env = TestEnv::new();
+    let path = env._temp_dir.path().join("session.log");
+    let daemon = DaemonHandle::spawn_with_args(&env.socket(),
+        &["--record", path.to_str().unwrap(), "--record-format", "script"],
+        &["bash", "-c", "read line; echo \"got $line\"; sleep 10"]);
+
+    request(&daemon.socket_path, serde_json::json!({"type": "INPUT", "data": "hello\r"}));
+    thread::sleep(Duration::from_millis(300));
+    request(&daemon.socket_path, serde_json::json!({"type": "RESIZE", "cols": 100, "rows": 30}));
+    thread::sleep(Duration::from_millis(100));
+
+    let log = std::fs::read(&path).expect("Log not written");
+    let timing = std::fs::read_to_string(env._temp_dir.path().join("session.log.timing")).expect("Timing not written");
+
+    // The first line is the header, the rest is described by the timing file
+    let header_len = log.iter().position(|&b| b == b'\n').unwrap() + 1;
+    assert!(String::from_utf8_lossy(&log[..header_len]).starts_with("Script started on "));
+
+    let mut described = 0;
+    for line in timing.lines() {
+        let fields: Vec<&str> = line.split(' ').collect();
+        match fields[0] {
+            "O" | "I" => described += fields[2].parse::<usize>().unwrap(),
+            "S" => assert_eq!(&fields[2..], ["SIGWINCH", "ROWS=30", "COLS=100"]),
+            "H" => {}
+            other => panic!("Unexpected timing entry {}", other),
+        }
+    }
+    assert_eq!(described, log.len() - header_len);
+    assert!(timing.lines().any(|line| line.starts_with("I ") && line.ends_with(" 6")), "No input entry: {}", timing);
+    assert!(String::from_utf8_lossy(&log).contains("got hello"));
+}
+
+// Test recordings rotate once they reach --record-max-bytes
+#[test]
+fn test_record_rotation() {
+    let env = TestEnv::new();
+    let path = env._temp_dir.path().join("session.cast");
+    let _daemon = DaemonHandle::spawn_with_args(&env.socket(),
+        &["--record", path.to_str().unwrap(), "--record-max-bytes", "400", "--record-keep", "1"],
+        &["bash", "-c", "for i in $(seq 1 30); do echo line $i; sleep 0.02; done; sleep 10"]);
+
+    thread::sleep(Duration::from_millis(1000));
+
+    let rotated = env._temp_dir.path().join("session.cast.1");
+    assert!(rotated.exists(), "Recording not rotated");
+    assert!(!env._temp_dir.path().join("session.cast.2").exists(), "Kept too many recordings");
+
+    // Every file is a complete recording with its own header
+    for file in [&path, &rotated] {
+        let lines = read_cast(file);
+        assert_eq!(lines[0]["version"], 2);
+        assert!(std::fs::metadata(file).unwrap().len() < 400 + 200);
+    }
+    let last = read_cast(&path);
+    assert!(last.iter().any(|e| e[2].as_str().is_some_and(|s| s.contains("line 30"))));
+}
+
+// Test secret input is masked in recordings
+#[test]
+fn test_record_secret_input() {
+    let env = TestEnv::new();
+    let path = env._temp_dir.path().join("session.cast");
+    let daemon = DaemonHandle::spawn_with_args(&env.socket(),
+        &["--record", path.to_str().unwrap()],
+        &["bash", "-c", "read line; sleep 10"]);
+
+    request(&daemon.socket_path, serde_json::json!({"type": "INPUT", "data": "hunter2\r", "secret": true}));
+    thread::sleep(Duration::from_millis(200));
+
+    // The application echoes what it reads, but the input event itself is masked
+    let inputs: Vec<serde_json::Value> = read_cast(&path).into_iter().skip(1).filter(|e| e[1] == "i").collect();
+    assert_eq!(inputs.len(), 1);
+    assert_eq!(inputs[0][2], "*******\r");
+}
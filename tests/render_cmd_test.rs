@@ -0,0 +1,138 @@
+use assert_cmd::Command;
+use std::fs;
+use std::time::Duration;
+use tempfile::TempDir;
+
+mod common;
+use common::{interminai_bin, emulator_args};
+
+fn render(file: &std::path::Path, args: &[&str]) -> serde_json::Value {
+    let output = Command::new(interminai_bin())
+        .arg("render")
+        .arg(file)
+        .args(emulator_args())
+        .args(["--format", "json"])
+        .args(args)
+        .timeout(Duration::from_secs(5))
+        .output()
+        .expect("Failed to run render");
+    assert!(output.status.success(), "render failed: {}", String::from_utf8_lossy(&output.stderr));
+    serde_json::from_slice(&output.stdout).expect("render output is not JSON")
+}
+
+#[test]
+fn test_render_raw_dump() {
+    let dir = TempDir::new().unwrap();
+    let file = dir.path().join("dump.bin");
+    fs::write(&file, "hello\r\n\x1b[31mred\x1b[0m world\r\n").unwrap();
+
+    let result = render(&file, &["--size", "20x4"]);
+    assert_eq!(result["screen"], "hello\nred world\n\n\n");
+    assert_eq!(result["cursor"]["row"], 2);
+    assert_eq!(result["size"]["cols"], 20);
+    assert_eq!(result["offset"], 27);
+    assert!(result.get("time").is_none(), "raw dumps have no timing: {}", result);
+
+    // Plain text output
+    Command::new(interminai_bin())
+        .arg("render")
+        .arg(&file)
+        .args(emulator_args())
+        .args(["--size", "20x4", "--format", "ascii"])
+        .timeout(Duration::from_secs(5))
+        .assert()
+        .success()
+        .stdout("hello\nred world\n\n\n");
+}
+
+#[test]
+fn test_render_at_offset() {
+    let dir = TempDir::new().unwrap();
+    let file = dir.path().join("dump.bin");
+    fs::write(&file, "first\r\nsecond\r\n").unwrap();
+
+    let result = render(&file, &["--size", "20x3", "--at-offset", "9"]);
+    assert_eq!(result["screen"], "first\nse\n\n");
+    assert_eq!(result["offset"], 9);
+}
+
+#[test]
+fn test_render_asciicast() {
+    let dir = TempDir::new().unwrap();
+    let file = dir.path().join("session.cast");
+    fs::write(
+        &file,
+        concat!(
+            "{\"version\": 2, \"width\": 30, \"height\": 5}\n",
+            "[0.1, \"o\", \"one\\r\\n\"]\n",
+            "[0.5, \"i\", \"x\"]\n",
+            "[1.0, \"o\", \"two\\r\\n\"]\n",
+            "[1.5, \"r\", \"40x6\"]\n",
+        ),
+    )
+    .unwrap();
+
+    // Size comes from the header, then the resize event
+    let result = render(&file, &[]);
+    assert_eq!(result["screen"].as_str().unwrap().lines().take(2).collect::<Vec<_>>(), ["one", "two"]);
+    assert_eq!(result["size"]["rows"], 6);
+    assert_eq!(result["size"]["cols"], 40);
+    assert_eq!(result["time"], 1.5);
+
+    let result = render(&file, &["--at-time", "0.7"]);
+    assert_eq!(result["screen"], "one\n\n\n\n\n");
+    assert_eq!(result["size"]["cols"], 30);
+    assert_eq!(result["offset"], 5);
+}
+
+#[test]
+fn test_render_script_log() {
+    let dir = TempDir::new().unwrap();
+    let file = dir.path().join("session.log");
+    fs::write(&file, "Script started on 2026-01-01 00:00:00+00:00 [COMMAND=\"sh\"]\none\r\nxtwo\r\n").unwrap();
+    fs::write(
+        dir.path().join("session.log.timing"),
+        "H 0.000000 COLUMNS 25\nH 0.000000 LINES 4\nO 0.100000 5\nI 0.200000 1\nO 0.300000 5\n",
+    )
+    .unwrap();
+
+    let result = render(&file, &[]);
+    assert_eq!(result["screen"], "one\ntwo\n\n\n");
+    assert_eq!(result["size"]["cols"], 25);
+
+    let result = render(&file, &["--at-time", "0.35"]);
+    assert_eq!(result["screen"], "one\n\n\n\n");
+}
+
+#[test]
+fn test_render_classic_script_log() {
+    // `script -t 2>timing` writes "delay bytes" lines, all output
+    let dir = TempDir::new().unwrap();
+    let file = dir.path().join("typescript");
+    fs::write(&file, "Script started on Thu Jan  1 00:00:00 2026
+one\r\ntwo\r\n").unwrap();
+    fs::write(dir.path().join("typescript.timing"), "0.100000 5\n0.250000 5\n").unwrap();
+
+    let result = render(&file, &["--size", "20x3"]);
+    assert_eq!(result["screen"], "one\ntwo\n\n");
+    assert_eq!(result["time"], 0.35);
+
+    let result = render(&file, &["--size", "20x3", "--at-time", "0.2"]);
+    assert_eq!(result["screen"], "one\n\n\n");
+}
+
+#[test]
+fn test_render_at_time_needs_timing() {
+    let dir = TempDir::new().unwrap();
+    let file = dir.path().join("dump.bin");
+    fs::write(&file, "hello").unwrap();
+
+    Command::new(interminai_bin())
+        .arg("render")
+        .arg(&file)
+        .args(["--at-time", "1"])
+        .timeout(Duration::from_secs(5))
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("no timing information"));
+}
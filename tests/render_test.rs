@@ -0,0 +1,158 @@
+mod common;
+use common::{interminai_bin, emulator, emulator_args};
+
+use assert_cmd::Command;
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::net::UnixStream;
+use std::thread;
+use std::time::Duration;
+use tempfile::TempDir;
+
+struct TestEnv {
+    _temp_dir: TempDir,
+}
+
+impl TestEnv {
+    fn new() -> Self {
+        Self {
+            _temp_dir: TempDir::new().expect("Failed to create temp dir"),
+        }
+    }
+
+    fn socket(&self) -> String {
+        self._temp_dir.path().join("test.sock").to_string_lossy().to_string()
+    }
+}
+
+struct DaemonHandle {
+    _child: std::process::Child,
+    socket_path: String,
+}
+
+impl DaemonHandle {
+    fn spawn_with_socket(socket: &str, command_args: &[&str]) -> Self {
+        Self::spawn_with_args(socket, &[], command_args)
+    }
+
+    fn spawn_with_args(socket: &str, start_args: &[&str], command_args: &[&str]) -> Self {
+        use std::process::Stdio;
+
+        let mut cmd = std::process::Command::new(interminai_bin());
+        cmd.arg("start")
+            .args(emulator_args())
+            .args(start_args)
+            .arg("--socket")
+            .arg(socket)
+            .arg("--no-daemon")
+            .arg("--");
+
+        for arg in command_args {
+            cmd.arg(arg);
+        }
+
+        let mut child = cmd
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .expect("Failed to spawn daemon");
+
+        let stdout = child.stdout.take().unwrap();
+        let reader = BufReader::new(stdout);
+        let _lines: Vec<String> = reader.lines().take(3).map(|l| l.unwrap()).collect();
+
+        thread::sleep(Duration::from_millis(300));
+
+        DaemonHandle {
+            _child: child,
+            socket_path: socket.to_string(),
+        }
+    }
+}
+
+impl Drop for DaemonHandle {
+    fn drop(&mut self) {
+        let _ = Command::new(interminai_bin())
+            .arg("stop")
+            .arg("--socket")
+            .arg(&self.socket_path)
+            .output();
+    }
+}
+
+fn request(socket: &str, request: serde_json::Value) -> serde_json::Value {
+    let mut stream = UnixStream::connect(socket).expect("Failed to connect");
+    stream.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+    stream.write_all(format!("{}\n", request).as_bytes()).unwrap();
+    let mut line = String::new();
+    BufReader::new(stream).read_line(&mut line).expect("No response");
+    serde_json::from_str(&line).expect("Response is not valid JSON")
+}
+
+// Test HTML output is a standalone document with colors and escaped text
+#[test]
+fn test_output_html() {
+    let env = TestEnv::new();
+    let daemon = DaemonHandle::spawn_with_socket(&env.socket(), &["bash", "-c",
+        "printf '<b>&amp; \\033[1;31mred\\033[0m'; sleep 10"]);
+
+    let response = request(&daemon.socket_path, serde_json::json!({"type": "OUTPUT", "format": "html"}));
+    assert_eq!(response["status"], "ok");
+    let html = response["data"]["screen"].as_str().unwrap();
+
+    assert!(html.starts_with("<!DOCTYPE html>"));
+    assert!(html.trim_end().ends_with("</html>"));
+    assert!(html.contains("&lt;b&gt;&amp;amp;"), "Text not escaped: {}", html);
+    assert!(!html.contains("<link") && !html.contains("<script"), "Document is not self-contained");
+    if emulator() != "custom" {
+        assert!(html.contains("<span style=\"color:#cd0000;font-weight:bold\">red</span>"), "Missing red span: {}", html);
+    }
+}
+
+// Test SVG output draws backgrounds, text and the cursor
+#[test]
+fn test_output_svg() {
+    let env = TestEnv::new();
+    let daemon = DaemonHandle::spawn_with_socket(&env.socket(), &["bash", "-c",
+        "printf '\\033[44mblue\\033[0m hello'; sleep 10"]);
+
+    let response = request(&daemon.socket_path, serde_json::json!({"type": "OUTPUT", "format": "svg"}));
+    assert_eq!(response["status"], "ok");
+    let svg = response["data"]["screen"].as_str().unwrap();
+
+    assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\""));
+    assert!(svg.trim_end().ends_with("</svg>"));
+    assert!(svg.contains("hello</text>"), "Missing text: {}", svg);
+    // The cursor is drawn as an inverted cell right after the text
+    assert!(svg.contains("fill=\"#e5e5e5\"/>"), "Missing cursor: {}", svg);
+    if emulator() != "custom" {
+        assert!(svg.contains("fill=\"#0000ee\"/>"), "Missing blue background: {}", svg);
+    }
+}
+
+// Test rendered formats can't be combined with incremental output
+#[test]
+fn test_output_html_since_rejected() {
+    let env = TestEnv::new();
+    let daemon = DaemonHandle::spawn_with_socket(&env.socket(), &["sleep", "10"]);
+
+    let response = request(&daemon.socket_path, serde_json::json!({"type": "OUTPUT", "format": "html", "since": 0}));
+    assert_eq!(response["status"], "error");
+}
+
+// Test output --format svg writes the document to stdout
+#[test]
+fn test_output_svg_cli() {
+    let env = TestEnv::new();
+    let daemon = DaemonHandle::spawn_with_socket(&env.socket(), &["bash", "-c", "echo hello; sleep 10"]);
+
+    let output = Command::new(interminai_bin())
+        .args(["output", "--socket", &daemon.socket_path, "--format", "svg", "--cursor", "both"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.starts_with("Cursor: row 2, col 1\n<svg"), "Unexpected output: {}", stdout);
+    assert!(stdout.contains(">hello</text>"));
+    assert!(!stdout.contains("\x1b["), "Cursor inverse applied to SVG");
+}
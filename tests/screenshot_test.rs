@@ -0,0 +1,171 @@
+mod common;
+use common::{interminai_bin, emulator, emulator_args};
+
+use assert_cmd::Command;
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::net::UnixStream;
+use std::thread;
+use std::time::Duration;
+use tempfile::TempDir;
+
+struct TestEnv {
+    _temp_dir: TempDir,
+}
+
+impl TestEnv {
+    fn new() -> Self {
+        Self {
+            _temp_dir: TempDir::new().expect("Failed to create temp dir"),
+        }
+    }
+
+    fn socket(&self) -> String {
+        self._temp_dir.path().join("test.sock").to_string_lossy().to_string()
+    }
+}
+
+struct DaemonHandle {
+    _child: std::process::Child,
+    socket_path: String,
+}
+
+impl DaemonHandle {
+    fn spawn_with_socket(socket: &str, command_args: &[&str]) -> Self {
+        Self::spawn_with_args(socket, &[], command_args)
+    }
+
+    fn spawn_with_args(socket: &str, start_args: &[&str], command_args: &[&str]) -> Self {
+        use std::process::Stdio;
+
+        let mut cmd = std::process::Command::new(interminai_bin());
+        cmd.arg("start")
+            .args(emulator_args())
+            .args(start_args)
+            .arg("--socket")
+            .arg(socket)
+            .arg("--no-daemon")
+            .arg("--");
+
+        for arg in command_args {
+            cmd.arg(arg);
+        }
+
+        let mut child = cmd
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .expect("Failed to spawn daemon");
+
+        let stdout = child.stdout.take().unwrap();
+        let reader = BufReader::new(stdout);
+        let _lines: Vec<String> = reader.lines().take(3).map(|l| l.unwrap()).collect();
+
+        thread::sleep(Duration::from_millis(300));
+
+        DaemonHandle {
+            _child: child,
+            socket_path: socket.to_string(),
+        }
+    }
+}
+
+impl Drop for DaemonHandle {
+    fn drop(&mut self) {
+        let _ = Command::new(interminai_bin())
+            .arg("stop")
+            .arg("--socket")
+            .arg(&self.socket_path)
+            .output();
+    }
+}
+
+fn request(socket: &str, request: serde_json::Value) -> serde_json::Value {
+    let mut stream = UnixStream::connect(socket).expect("Failed to connect");
+    stream.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+    stream.write_all(format!("{}\n", request).as_bytes()).unwrap();
+    let mut line = String::new();
+    BufReader::new(stream).read_line(&mut line).expect("No response");
+    serde_json::from_str(&line).expect("Response is not valid JSON")
+}
+
+/// Decode a PNG into its width, height and RGB pixels
+fn decode_png(bytes: &[u8]) -> (usize, usize, Vec<u8>) {
+    let decoder = png::Decoder::new(bytes);
+    let mut reader = decoder.read_info().expect("Not a PNG image");
+    let mut pixels = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut pixels).expect("Failed to decode PNG");
+    assert_eq!(info.color_type, png::ColorType::Rgb);
+    (info.width as usize, info.height as usize, pixels)
+}
+
+fn pixel(pixels: &[u8], width: usize, x: usize, y: usize) -> (u8, u8, u8) {
+    let i = (y * width + x) * 3;
+    (pixels[i], pixels[i + 1], pixels[i + 2])
+}
+
+// Test OUTPUT png returns a base64 image of the whole screen
+#[test]
+fn test_output_png() {
+    use base64::Engine;
+
+    let env = TestEnv::new();
+    let daemon = DaemonHandle::spawn_with_args(&env.socket(), &["--size", "40x10"], &["bash", "-c",
+        "printf '\\033[41m  \\033[0mhello'; sleep 10"]);
+
+    let response = request(&daemon.socket_path, serde_json::json!({"type": "OUTPUT", "format": "png"}));
+    assert_eq!(response["status"], "ok");
+    assert!(response["data"].get("screen").is_none());
+    assert_eq!(response["data"]["width"], 400);
+    assert_eq!(response["data"]["height"], 200);
+
+    let bytes = base64::engine::general_purpose::STANDARD
+        .decode(response["data"]["png"].as_str().unwrap())
+        .expect("Image is not valid base64");
+    let (width, height, pixels) = decode_png(&bytes);
+    assert_eq!((width, height), (400, 200));
+
+    // Cursor after "hello" (column 7) is drawn in the default foreground color
+    assert_eq!(pixel(&pixels, width, 7 * 10 + 5, 10), (0xe5, 0xe5, 0xe5));
+    // Text is drawn in the default foreground color over the black background
+    let text_pixels = (20..70).flat_map(|x| (0..20).map(move |y| (x, y)))
+        .filter(|&(x, y)| pixel(&pixels, width, x, y) != (0, 0, 0))
+        .count();
+    assert!(text_pixels > 50, "No text drawn");
+    if emulator() != "custom" {
+        assert_eq!(pixel(&pixels, width, 5, 10), (0xcd, 0x00, 0x00), "Red background not drawn");
+    }
+}
+
+// Test screenshot -o writes a PNG file
+#[test]
+fn test_screenshot_cli() {
+    let env = TestEnv::new();
+    let daemon = DaemonHandle::spawn_with_socket(&env.socket(), &["bash", "-c", "echo hello; sleep 10"]);
+    let path = env._temp_dir.path().join("screen.png");
+
+    let output = Command::new(interminai_bin())
+        .args(["screenshot", "--socket", &daemon.socket_path, "-o", path.to_str().unwrap()])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "stderr: {}", String::from_utf8_lossy(&output.stderr));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("(800x480)"));
+
+    let (width, height, _) = decode_png(&std::fs::read(&path).unwrap());
+    assert_eq!((width, height), (800, 480));
+}
+
+// Test screenshot -o - writes the image to stdout
+#[test]
+fn test_screenshot_stdout() {
+    let env = TestEnv::new();
+    let daemon = DaemonHandle::spawn_with_args(&env.socket(), &["--size", "20x5"], &["sleep", "10"]);
+
+    let output = Command::new(interminai_bin())
+        .args(["screenshot", "--socket", &daemon.socket_path, "-o", "-"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let (width, height, _) = decode_png(&output.stdout);
+    assert_eq!((width, height), (200, 100));
+}
@@ -0,0 +1,151 @@
+mod common;
+use common::{interminai_bin, emulator_args};
+
+use assert_cmd::Command;
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::net::UnixStream;
+use std::thread;
+use std::time::Duration;
+use tempfile::TempDir;
+
+struct TestEnv {
+    _temp_dir: TempDir,
+}
+
+impl TestEnv {
+    fn new() -> Self {
+        Self {
+            _temp_dir: TempDir::new().expect("Failed to create temp dir"),
+        }
+    }
+
+    fn socket(&self) -> String {
+        self._temp_dir.path().join("test.sock").to_string_lossy().to_string()
+    }
+}
+
+struct DaemonHandle {
+    _child: std::process::Child,
+    socket_path: String,
+}
+
+impl DaemonHandle {
+    fn spawn_with_socket(socket: &str, command_args: &[&str]) -> Self {
+        Self::spawn_with_args(socket, &[], command_args)
+    }
+
+    fn spawn_with_args(socket: &str, start_args: &[&str], command_args: &[&str]) -> Self {
+        use std::process::Stdio;
+
+        let mut cmd = std::process::Command::new(interminai_bin());
+        cmd.arg("start")
+            .args(emulator_args())
+            .args(start_args)
+            .arg("--socket")
+            .arg(socket)
+            .arg("--no-daemon")
+            .arg("--");
+
+        for arg in command_args {
+            cmd.arg(arg);
+        }
+
+        let mut child = cmd
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .expect("Failed to spawn daemon");
+
+        let stdout = child.stdout.take().unwrap();
+        let reader = BufReader::new(stdout);
+        let _lines: Vec<String> = reader.lines().take(3).map(|l| l.unwrap()).collect();
+
+        thread::sleep(Duration::from_millis(300));
+
+        DaemonHandle {
+            _child: child,
+            socket_path: socket.to_string(),
+        }
+    }
+}
+
+impl Drop for DaemonHandle {
+    fn drop(&mut self) {
+        let _ = Command::new(interminai_bin())
+            .arg("stop")
+            .arg("--socket")
+            .arg(&self.socket_path)
+            .output();
+    }
+}
+
+fn request(socket: &str, request: serde_json::Value) -> serde_json::Value {
+    let mut stream = UnixStream::connect(socket).expect("Failed to connect");
+    stream.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+    stream.write_all(format!("{}\n", request).as_bytes()).unwrap();
+    let mut line = String::new();
+    BufReader::new(stream).read_line(&mut line).expect("No response");
+    serde_json::from_str(&line).expect("Response is not valid JSON")
+}
+
+// Test lines that scrolled off the screen can be read back
+#[test]
+fn test_output_scrollback_cli() {
+    let env = TestEnv::new();
+    let daemon = DaemonHandle::spawn_with_socket(&env.socket(), &["bash", "-c", "for i in $(seq 1 100); do echo line$i; done; sleep 10"]);
+
+    let output = Command::new(interminai_bin())
+        .args(["output", "--socket", &daemon.socket_path, "--no-color"])
+        .output()
+        .unwrap();
+    let screen = String::from_utf8_lossy(&output.stdout);
+    assert!(!screen.contains("line1\n"), "line1 should have scrolled off: {}", screen);
+
+    let output = Command::new(interminai_bin())
+        .args(["output", "--socket", &daemon.socket_path, "--no-color", "--scrollback", "1000"])
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert_eq!(lines[0], "line1", "History should start with the first line: {}", stdout);
+    let last = lines.iter().position(|l| *l == "line100").expect("line100 missing");
+    assert!(lines[..last].iter().all(|l| !l.is_empty()), "History and screen should be contiguous: {}", stdout);
+}
+
+// Test OUTPUT returns the most recent history lines in order
+#[test]
+fn test_output_scrollback_protocol() {
+    let env = TestEnv::new();
+    let daemon = DaemonHandle::spawn_with_args(&env.socket(), &["--size", "80x5"], &["bash", "-c", "seq 1 20; sleep 10"]);
+
+    let response = request(&daemon.socket_path, serde_json::json!({"type": "OUTPUT"}));
+    assert!(response["data"].get("scrollback").is_none());
+
+    let response = request(&daemon.socket_path, serde_json::json!({"type": "OUTPUT", "scrollback": 3}));
+    assert_eq!(response["status"], "ok");
+    // Screen holds 17-20 and the empty line for the cursor, so 14-16 are the newest history
+    assert_eq!(response["data"]["scrollback"], "14\n15\n16\n");
+    assert_eq!(response["data"]["history_size"], 16);
+}
+
+// Test start --scrollback limits how much history is kept
+#[test]
+fn test_scrollback_limit() {
+    let env = TestEnv::new();
+    let daemon = DaemonHandle::spawn_with_args(&env.socket(), &["--size", "80x5", "--scrollback", "4"], &["bash", "-c", "seq 1 20; sleep 10"]);
+
+    let response = request(&daemon.socket_path, serde_json::json!({"type": "OUTPUT", "scrollback": 100}));
+    assert_eq!(response["data"]["history_size"], 4);
+    assert_eq!(response["data"]["scrollback"], "13\n14\n15\n16\n");
+}
+
+// Test start --scrollback 0 keeps no history
+#[test]
+fn test_scrollback_disabled() {
+    let env = TestEnv::new();
+    let daemon = DaemonHandle::spawn_with_args(&env.socket(), &["--size", "80x5", "--scrollback", "0"], &["bash", "-c", "seq 1 20; sleep 10"]);
+
+    let response = request(&daemon.socket_path, serde_json::json!({"type": "OUTPUT", "scrollback": 100}));
+    assert_eq!(response["data"]["history_size"], 0);
+    assert_eq!(response["data"]["scrollback"], "");
+}
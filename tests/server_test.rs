@@ -0,0 +1,239 @@
+mod common;
+use common::{interminai_bin, emulator};
+
+use assert_cmd::Command;
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::net::UnixStream;
+use std::thread;
+use std::time::{Duration, Instant};
+use tempfile::TempDir;
+
+struct ServerHandle {
+    child: std::process::Child,
+    socket_path: String,
+    _temp_dir: TempDir,
+}
+
+impl ServerHandle {
+    fn spawn() -> Self {
+        use std::process::Stdio;
+
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let socket_path = temp_dir.path().join("server.sock").to_string_lossy().to_string();
+
+        let mut child = std::process::Command::new(interminai_bin())
+            .arg("server")
+            .arg("--socket")
+            .arg(&socket_path)
+            .arg("--no-daemon")
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .expect("Failed to spawn server");
+
+        let stdout = child.stdout.take().unwrap();
+        let reader = BufReader::new(stdout);
+        let _lines: Vec<String> = reader.lines().take(3).map(|l| l.unwrap()).collect();
+
+        thread::sleep(Duration::from_millis(200));
+
+        ServerHandle {
+            child,
+            socket_path,
+            _temp_dir: temp_dir,
+        }
+    }
+
+    /// Start a session with `new`, returning what it printed
+    fn new_session(&self, args: &[&str], command: &[&str]) -> String {
+        let output = Command::new(interminai_bin())
+            .arg("new")
+            .arg("--socket")
+            .arg(&self.socket_path)
+            .args(["--emulator", &emulator()])
+            .args(args)
+            .arg("--")
+            .args(command)
+            .timeout(Duration::from_secs(5))
+            .output()
+            .expect("Failed to run new");
+        assert!(output.status.success(), "new failed: {}", String::from_utf8_lossy(&output.stderr));
+        String::from_utf8_lossy(&output.stdout).to_string()
+    }
+
+    fn client(&self, session: &str, args: &[&str]) -> std::process::Output {
+        Command::new(interminai_bin())
+            .args(args)
+            .arg("--socket")
+            .arg(&self.socket_path)
+            .arg("--session")
+            .arg(session)
+            .timeout(Duration::from_secs(5))
+            .output()
+            .expect("Failed to run client")
+    }
+
+    fn screen(&self, session: &str) -> String {
+        let output = self.client(session, &["output", "--no-color"]);
+        assert!(output.status.success(), "output failed: {}", String::from_utf8_lossy(&output.stderr));
+        String::from_utf8_lossy(&output.stdout).to_string()
+    }
+}
+
+impl Drop for ServerHandle {
+    fn drop(&mut self) {
+        let _ = Command::new(interminai_bin())
+            .arg("stop")
+            .arg("--socket")
+            .arg(&self.socket_path)
+            .output();
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+fn request(socket: &str, request: serde_json::Value) -> serde_json::Value {
+    let mut stream = UnixStream::connect(socket).expect("Failed to connect");
+    stream.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+    stream.write_all(format!("{}\n", request).as_bytes()).unwrap();
+    let mut line = String::new();
+    BufReader::new(stream).read_line(&mut line).expect("No response");
+    serde_json::from_str(&line).expect("Response is not valid JSON")
+}
+
+#[test]
+fn test_sessions_are_independent() {
+    let server = ServerHandle::spawn();
+
+    let printed = server.new_session(&["--name", "first"], &["cat"]);
+    assert!(printed.contains("Session: 1"), "got: {}", printed);
+    assert!(printed.contains("Name: first"), "got: {}", printed);
+    let printed = server.new_session(&["--size", "40x10"], &["cat"]);
+    assert!(printed.contains("Session: 2"), "got: {}", printed);
+
+    assert!(server.client("first", &["input", "--text", "apple\\r"]).status.success());
+    assert!(server.client("2", &["input", "--text", "banana\\r"]).status.success());
+    thread::sleep(Duration::from_millis(300));
+
+    let first = server.screen("first");
+    let second = server.screen("2");
+    assert!(first.contains("apple") && !first.contains("banana"), "first: {}", first);
+    assert!(second.contains("banana") && !second.contains("apple"), "second: {}", second);
+
+    // Sizes are per session
+    let status = request(&server.socket_path, serde_json::json!({"type": "OUTPUT", "session": 2}));
+    assert_eq!(status["status"], "ok");
+    assert_eq!(status["data"]["screen"].as_str().unwrap().lines().count(), 10);
+}
+
+#[test]
+fn test_list_and_close() {
+    let server = ServerHandle::spawn();
+    server.new_session(&["--name", "editor"], &["cat"]);
+    server.new_session(&["--name", "build"], &["sh", "-c", "exit 3"]);
+    thread::sleep(Duration::from_millis(300));
+
+    let list = request(&server.socket_path, serde_json::json!({"type": "LIST"}));
+    let sessions = list["data"]["sessions"].as_array().unwrap();
+    assert_eq!(sessions.len(), 2);
+    assert_eq!(sessions[0]["name"], "editor");
+    assert_eq!(sessions[0]["command"], "cat");
+    assert_eq!(sessions[0]["running"], true);
+    assert_eq!(sessions[0]["cols"], 80);
+    assert_eq!(sessions[1]["running"], false);
+    assert_eq!(sessions[1]["exit_code"], 3);
+
+    let output = Command::new(interminai_bin())
+        .args(["list", "--socket", &server.socket_path])
+        .timeout(Duration::from_secs(5))
+        .output()
+        .unwrap();
+    let table = String::from_utf8_lossy(&output.stdout);
+    assert!(table.contains("editor") && table.contains("running"), "got: {}", table);
+    assert!(table.contains("exited (3)"), "got: {}", table);
+
+    Command::new(interminai_bin())
+        .args(["close", "--socket", &server.socket_path, "editor"])
+        .timeout(Duration::from_secs(5))
+        .assert()
+        .success();
+
+    let list = request(&server.socket_path, serde_json::json!({"type": "LIST"}));
+    let sessions = list["data"]["sessions"].as_array().unwrap();
+    assert_eq!(sessions.len(), 1);
+    assert_eq!(sessions[0]["name"], "build");
+
+    let output = server.client("editor", &["status"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("No such session: editor"));
+}
+
+#[test]
+fn test_requests_need_a_session() {
+    let server = ServerHandle::spawn();
+    server.new_session(&["--name", "shell"], &["cat"]);
+
+    let response = request(&server.socket_path, serde_json::json!({"type": "STATUS"}));
+    assert_eq!(response["status"], "error");
+    assert!(response["error"].as_str().unwrap().contains("No session given"));
+
+    let response = request(&server.socket_path, serde_json::json!({"type": "NEW", "name": "shell", "command": ["cat"]}));
+    assert_eq!(response["status"], "error");
+    assert!(response["error"].as_str().unwrap().contains("already exists"));
+
+    let response = request(&server.socket_path, serde_json::json!({"type": "NEW", "name": "7", "command": ["cat"]}));
+    assert_eq!(response["status"], "error");
+
+    let response = request(&server.socket_path, serde_json::json!({"type": "NEW", "command": []}));
+    assert_eq!(response["status"], "error");
+
+    let response = request(&server.socket_path, serde_json::json!({"type": "NEW", "command": ["/nonexistent/program"]}));
+    assert_eq!(response["status"], "error");
+    assert!(response["error"].as_str().unwrap().contains("Failed to run /nonexistent/program"), "got: {}", response);
+}
+
+#[test]
+fn test_new_rejects_invalid_sizes() {
+    let server = ServerHandle::spawn();
+    server.new_session(&["--name", "shell"], &["cat"]);
+
+    for size in [serde_json::json!({"rows": 0}), serde_json::json!({"cols": 0}), serde_json::json!({"rows": 70000}), serde_json::json!({"cols": "wide"})] {
+        let mut new = serde_json::json!({"type": "NEW", "command": ["sh"]});
+        new.as_object_mut().unwrap().extend(size.as_object().unwrap().clone());
+        let response = request(&server.socket_path, new);
+        assert_eq!(response["status"], "error", "{} was accepted", size);
+        assert!(response["error"].as_str().unwrap().contains("Invalid"), "got: {}", response);
+    }
+
+    // The server and its sessions are unaffected
+    let list = request(&server.socket_path, serde_json::json!({"type": "LIST"}));
+    assert_eq!(list["data"]["sessions"].as_array().unwrap().len(), 1);
+    assert!(server.client("shell", &["status"]).status.success());
+}
+
+#[test]
+fn test_stop_shuts_down_server() {
+    let mut server = ServerHandle::spawn();
+    server.new_session(&[], &["cat"]);
+    server.new_session(&[], &["cat"]);
+
+    // Stopping one session leaves the server running
+    assert!(server.client("1", &["stop"]).status.success());
+    let list = request(&server.socket_path, serde_json::json!({"type": "LIST"}));
+    assert_eq!(list["data"]["sessions"].as_array().unwrap().len(), 1);
+
+    Command::new(interminai_bin())
+        .args(["stop", "--socket", &server.socket_path])
+        .timeout(Duration::from_secs(5))
+        .assert()
+        .success();
+
+    let deadline = Instant::now() + Duration::from_secs(5);
+    loop {
+        if server.child.try_wait().unwrap().is_some() {
+            break;
+        }
+        assert!(Instant::now() < deadline, "server did not exit");
+        thread::sleep(Duration::from_millis(50));
+    }
+}
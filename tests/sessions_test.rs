@@ -0,0 +1,199 @@
+mod common;
+use common::{interminai_bin, emulator_args};
+
+use assert_cmd::Command;
+use std::os::unix::net::UnixListener;
+use std::path::Path;
+use std::thread;
+use std::time::Duration;
+use tempfile::TempDir;
+
+/// Runtime and temp directories of our own, so other tests' sessions don't show up
+struct TestEnv {
+    temp_dir: TempDir,
+}
+
+impl TestEnv {
+    fn new() -> Self {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        std::fs::create_dir(temp_dir.path().join("run")).unwrap();
+        std::fs::create_dir(temp_dir.path().join("tmp")).unwrap();
+        Self { temp_dir }
+    }
+
+    fn runtime_dir(&self) -> std::path::PathBuf {
+        self.temp_dir.path().join("run")
+    }
+
+    fn tmp_dir(&self) -> std::path::PathBuf {
+        self.temp_dir.path().join("tmp")
+    }
+
+    fn command(&self) -> Command {
+        let mut cmd = Command::new(interminai_bin());
+        cmd.env("XDG_RUNTIME_DIR", self.runtime_dir())
+            .env("TMPDIR", self.tmp_dir())
+            .env_remove("INTERMINAI_SOCKET")
+            .timeout(Duration::from_secs(5));
+        cmd
+    }
+
+    fn run(&self, args: &[&str]) -> (bool, String, String) {
+        let output = self.command().args(args).output().expect("Failed to run interminai");
+        (
+            output.status.success(),
+            String::from_utf8_lossy(&output.stdout).to_string(),
+            String::from_utf8_lossy(&output.stderr).to_string(),
+        )
+    }
+
+    fn start_named(&self, name: &str, command: &[&str]) {
+        let output = self.command()
+            .arg("start")
+            .args(emulator_args())
+            .args(["--name", name, "--size", "40x12", "--"])
+            .args(command)
+            .output()
+            .expect("Failed to start");
+        assert!(output.status.success(), "start failed: {}", String::from_utf8_lossy(&output.stderr));
+        thread::sleep(Duration::from_millis(300));
+    }
+}
+
+/// Leave a socket nobody listens on, like a daemon that was killed
+fn stale_socket(dir: &Path) {
+    std::fs::create_dir_all(dir).unwrap();
+    drop(UnixListener::bind(dir.join("socket")).unwrap());
+}
+
+#[test]
+fn test_named_session() {
+    let env = TestEnv::new();
+    env.start_named("editor", &["cat"]);
+
+    let socket = env.runtime_dir().join("interminai/editor/socket");
+    assert!(socket.exists(), "socket not in the runtime dir");
+
+    let (ok, _, stderr) = env.run(&["input", "--name", "editor", "--text", "hello\\r"]);
+    assert!(ok, "input failed: {}", stderr);
+    thread::sleep(Duration::from_millis(200));
+
+    // The environment variable works too
+    let output = env.command()
+        .env("INTERMINAI_SOCKET", &socket)
+        .args(["output", "--no-color"])
+        .output()
+        .unwrap();
+    assert!(String::from_utf8_lossy(&output.stdout).contains("hello"));
+
+    let (ok, stdout, _) = env.run(&["status", "--name", "editor"]);
+    assert!(ok);
+    assert!(stdout.contains("Command: cat"), "got: {}", stdout);
+    assert!(stdout.contains("Size: 40x12"), "got: {}", stdout);
+    assert!(stdout.contains("PID: "), "got: {}", stdout);
+
+    // Names are unique while the session runs
+    let (ok, _, stderr) = env.run(&["start", "--name", "editor", "--", "cat"]);
+    assert!(!ok);
+    assert!(stderr.contains("already running"), "got: {}", stderr);
+
+    let (ok, _, _) = env.run(&["stop", "--name", "editor"]);
+    assert!(ok);
+    thread::sleep(Duration::from_millis(500));
+    assert!(!socket.parent().unwrap().exists(), "session dir not removed after stop");
+}
+
+#[test]
+fn test_client_needs_a_socket() {
+    let env = TestEnv::new();
+    let (ok, _, stderr) = env.run(&["status"]);
+    assert!(!ok);
+    assert!(stderr.contains("INTERMINAI_SOCKET"), "got: {}", stderr);
+
+    let (ok, _, _) = env.run(&["status", "--name", "missing"]);
+    assert!(!ok);
+}
+
+#[test]
+fn test_list_sessions() {
+    let env = TestEnv::new();
+    env.start_named("shell", &["cat"]);
+    let output = env.command()
+        .arg("start")
+        .args(emulator_args())
+        .args(["--", "sleep", "30"])
+        .output()
+        .unwrap();
+    let printed = String::from_utf8_lossy(&output.stdout).to_string();
+    let anonymous = printed.lines()
+        .find_map(|line| line.strip_prefix("Socket: "))
+        .expect("no socket printed")
+        .to_string();
+    thread::sleep(Duration::from_millis(300));
+    stale_socket(&env.runtime_dir().join("interminai/gone"));
+
+    let (ok, stdout, stderr) = env.run(&["list"]);
+    assert!(ok, "list failed: {}", stderr);
+    let shell = stdout.lines().find(|line| line.starts_with("shell")).expect("named session not listed");
+    assert!(shell.contains("running") && shell.contains("40x12") && shell.ends_with("cat"), "got: {}", shell);
+    let anon = stdout.lines().find(|line| line.contains(&anonymous)).expect("auto-generated session not listed");
+    assert!(anon.contains("sleep 30"), "got: {}", anon);
+    assert!(!stdout.contains("gone"), "stale session listed: {}", stdout);
+    assert!(stderr.contains("interminai gc"), "got: {}", stderr);
+
+    env.run(&["stop", "--name", "shell"]);
+    env.run(&["stop", "--socket", &anonymous]);
+}
+
+// Test a server that fails LIST is listed as an error instead of ending the list
+#[test]
+fn test_list_survives_failing_server() {
+    use std::io::{BufRead, BufReader, Write};
+
+    let env = TestEnv::new();
+    let dir = env.runtime_dir().join("interminai/broken");
+    std::fs::create_dir_all(&dir).unwrap();
+    let listener = UnixListener::bind(dir.join("socket")).unwrap();
+    thread::spawn(move || {
+        // Answers STATUS like a server, then hangs up on LIST
+        for (i, stream) in listener.incoming().enumerate() {
+            let mut stream = stream.unwrap();
+            let mut line = String::new();
+            BufReader::new(&stream).read_line(&mut line).unwrap();
+            if i == 0 {
+                stream.write_all(b"{\"status\": \"error\", \"error\": \"No session given\"}\n").unwrap();
+            }
+        }
+    });
+    env.start_named("shell", &["cat"]);
+
+    let (ok, stdout, stderr) = env.run(&["list"]);
+    assert!(ok, "list failed: {}", stderr);
+    let broken = stdout.lines().find(|line| line.starts_with("broken")).expect("failing server not listed");
+    assert!(broken.contains("error:"), "got: {}", broken);
+    assert!(stdout.lines().any(|line| line.starts_with("shell")), "got: {}", stdout);
+
+    env.run(&["stop", "--name", "shell"]);
+}
+
+#[test]
+fn test_gc_removes_stale_sessions() {
+    let env = TestEnv::new();
+    env.start_named("alive", &["cat"]);
+    stale_socket(&env.runtime_dir().join("interminai/dead"));
+    stale_socket(&env.tmp_dir().join("interminai-abc123"));
+
+    let (ok, stdout, _) = env.run(&["gc"]);
+    assert!(ok);
+    assert!(stdout.contains("interminai/dead"), "got: {}", stdout);
+    assert!(stdout.contains("interminai-abc123"), "got: {}", stdout);
+    assert!(!env.runtime_dir().join("interminai/dead").exists());
+    assert!(!env.tmp_dir().join("interminai-abc123").exists());
+
+    // Live sessions are left alone
+    assert!(env.runtime_dir().join("interminai/alive/socket").exists());
+    let (ok, _, _) = env.run(&["status", "--name", "alive"]);
+    assert!(ok);
+
+    env.run(&["stop", "--name", "alive"]);
+}
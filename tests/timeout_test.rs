@@ -0,0 +1,166 @@
+mod common;
+use common::{interminai_bin, emulator_args};
+
+use assert_cmd::Command;
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::thread;
+use std::time::{Duration, Instant};
+use tempfile::TempDir;
+
+struct TestEnv {
+    _temp_dir: TempDir,
+}
+
+impl TestEnv {
+    fn new() -> Self {
+        Self {
+            _temp_dir: TempDir::new().expect("Failed to create temp dir"),
+        }
+    }
+
+    fn socket(&self) -> String {
+        self._temp_dir.path().join("test.sock").to_string_lossy().to_string()
+    }
+}
+
+struct DaemonHandle {
+    _child: std::process::Child,
+    socket_path: String,
+}
+
+impl DaemonHandle {
+    fn spawn_with_socket(socket: &str, command_args: &[&str]) -> Self {
+        use std::process::Stdio;
+
+        let mut cmd = std::process::Command::new(interminai_bin());
+        cmd.arg("start")
+            .args(emulator_args())
+            .arg("--socket")
+            .arg(socket)
+            .arg("--no-daemon")
+            .arg("--");
+
+        for arg in command_args {
+            cmd.arg(arg);
+        }
+
+        let mut child = cmd
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .expect("Failed to spawn daemon");
+
+        let stdout = child.stdout.take().unwrap();
+        let reader = BufReader::new(stdout);
+        let _lines: Vec<String> = reader.lines().take(3).map(|l| l.unwrap()).collect();
+
+        thread::sleep(Duration::from_millis(300));
+
+        DaemonHandle {
+            _child: child,
+            socket_path: socket.to_string(),
+        }
+    }
+}
+
+impl Drop for DaemonHandle {
+    fn drop(&mut self) {
+        let _ = Command::new(interminai_bin())
+            .arg("stop")
+            .arg("--socket")
+            .arg(&self.socket_path)
+            .output();
+    }
+}
+
+fn run(args: &[&str]) -> std::process::Output {
+    Command::new(interminai_bin())
+        .args(args)
+        .timeout(Duration::from_secs(10))
+        .output()
+        .expect("Failed to run interminai")
+}
+
+// Test wait --timeout gives up when there is no activity
+#[test]
+fn test_wait_timeout() {
+    let env = TestEnv::new();
+    let daemon = DaemonHandle::spawn_with_socket(&env.socket(), &["sleep", "10"]);
+
+    let start = Instant::now();
+    let output = run(&["wait", "--socket", &daemon.socket_path, "--timeout", "300"]);
+
+    assert_eq!(output.status.code(), Some(124), "stderr: {}", String::from_utf8_lossy(&output.stderr));
+    assert!(start.elapsed() < Duration::from_secs(3), "Timeout not honored");
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Timed out"));
+}
+
+// Test wait --quiet --timeout gives up when the application keeps running
+#[test]
+fn test_wait_quiet_timeout() {
+    let env = TestEnv::new();
+    let daemon = DaemonHandle::spawn_with_socket(&env.socket(), &["sleep", "10"]);
+
+    let start = Instant::now();
+    let output = run(&["wait", "--socket", &daemon.socket_path, "--quiet", "--timeout", "300"]);
+
+    assert_eq!(output.status.code(), Some(124));
+    assert!(start.elapsed() < Duration::from_secs(3), "Timeout not honored");
+}
+
+// Test --timeout doesn't get in the way of requests that complete in time
+#[test]
+fn test_timeout_not_reached() {
+    let env = TestEnv::new();
+    let daemon = DaemonHandle::spawn_with_socket(&env.socket(), &["bash", "-c", "sleep 0.3; exit 3"]);
+
+    let output = run(&["--timeout", "5000", "status", "--socket", &daemon.socket_path]);
+    assert!(output.status.success());
+
+    let output = run(&["wait", "--socket", &daemon.socket_path, "--quiet", "--timeout", "5000"]);
+    assert!(output.status.success(), "stderr: {}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(String::from_utf8_lossy(&output.stdout).trim(), "3");
+}
+
+// Test the client gives up on a daemon that never answers
+#[test]
+fn test_unresponsive_daemon_times_out() {
+    let env = TestEnv::new();
+    let socket = env.socket();
+    let listener = UnixListener::bind(&socket).expect("Failed to bind socket");
+
+    // Accept connections but never reply
+    thread::spawn(move || {
+        let mut held = Vec::new();
+        for stream in listener.incoming() {
+            held.push(stream);
+        }
+    });
+
+    let start = Instant::now();
+    let output = run(&["status", "--socket", &socket, "--timeout", "300"]);
+
+    assert_eq!(output.status.code(), Some(124));
+    assert!(start.elapsed() < Duration::from_secs(3), "Timeout not honored");
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Timed out"));
+}
+
+// Test WAIT with timeout_ms reports timed_out in the protocol
+#[test]
+fn test_wait_timeout_ms_protocol() {
+    let env = TestEnv::new();
+    let daemon = DaemonHandle::spawn_with_socket(&env.socket(), &["sleep", "10"]);
+
+    let mut stream = UnixStream::connect(&daemon.socket_path).expect("Failed to connect");
+    stream.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+    stream.write_all(b"{\"type\":\"WAIT\",\"timeout_ms\":200}\n").unwrap();
+
+    let mut line = String::new();
+    BufReader::new(&stream).read_line(&mut line).expect("No response");
+    let response: serde_json::Value = serde_json::from_str(&line).unwrap();
+
+    assert_eq!(response["status"], "ok");
+    assert_eq!(response["data"]["timed_out"], true);
+    assert!(response["data"].get("exit_code").is_none());
+}
@@ -0,0 +1,108 @@
+// Generates assets/dejavu-sans-mono-10x20.bin, the bitmap font of
+// `interminai screenshot`, from the DejaVu Sans Mono TrueType fonts.
+//
+//     cargo run --release -- DejaVuSansMono.ttf DejaVuSansMono-Bold.ttf \
+//         > ../../assets/dejavu-sans-mono-10x20.bin
+//
+// With `--preview CHARS` it draws those glyphs as text instead, to check the
+// settings. See assets/README.md for the format and the font version.
+
+use fontdue::{Font, FontSettings};
+use std::io::Write;
+
+/// Cell size, as in src/screenshot.rs
+const WIDTH: usize = 10;
+const HEIGHT: usize = 20;
+
+/// Font size in pixels. DejaVu Sans Mono advances 9.99px at this size, so
+/// each glyph fills a 10px cell.
+const PIXELS: f32 = 16.6;
+
+/// Characters besides printable Latin-1 that TUIs commonly draw: punctuation,
+/// arrows, math, shapes and check marks. Box drawing, block elements and
+/// braille are drawn by src/screenshot.rs instead.
+const EXTRA: &str = "–—‘’‚“”„†‡•…‰‹›€™←↑→↓↔↕↵⇐⇑⇒⇓∀∂∃∅∇∈∑−∗√∞∧∨∩∪≈≠≡≤≥⌘⌥⏎■□▪▫▬▲△▶▷►▼▽◀◁◄◆◇○◌●◎◐◑★☆☐☑☒☓✓✔✕✖✗✘❯❮❱";
+
+fn load(path: &str) -> Font {
+    let bytes = std::fs::read(path).unwrap_or_else(|e| panic!("Failed to read {}: {}", path, e));
+    Font::from_bytes(bytes, FontSettings::default()).unwrap_or_else(|e| panic!("Failed to load {}: {}", path, e))
+}
+
+/// Coverage (0-255) of each pixel of the cell, row by row
+fn raster(font: &Font, c: char, baseline: i32) -> [u8; WIDTH * HEIGHT] {
+    let (metrics, bitmap) = font.rasterize(c, PIXELS);
+    let mut cell = [0u8; WIDTH * HEIGHT];
+    for gy in 0..metrics.height {
+        // ymin is how far the bottom of the bitmap is above the baseline
+        let y = baseline - (metrics.ymin + metrics.height as i32) + gy as i32;
+        if !(0..HEIGHT as i32).contains(&y) {
+            continue;
+        }
+        for gx in 0..metrics.width {
+            let x = metrics.xmin + gx as i32;
+            if !(0..WIDTH as i32).contains(&x) {
+                continue;
+            }
+            cell[y as usize * WIDTH + x as usize] = bitmap[gy * metrics.width + gx];
+        }
+    }
+    cell
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let (preview, paths) = match args.as_slice() {
+        [flag, chars, paths @ ..] if flag == "--preview" => (Some(chars.clone()), paths),
+        paths => (None, paths),
+    };
+    let [regular, bold] = paths else {
+        eprintln!("Usage: fontgen [--preview CHARS] REGULAR.ttf BOLD.ttf");
+        std::process::exit(2);
+    };
+    let regular = load(regular);
+    let bold = load(bold);
+
+    let baseline = regular.horizontal_line_metrics(PIXELS).expect("No horizontal metrics").ascent.round() as i32;
+
+    if let Some(chars) = preview {
+        for c in chars.chars() {
+            for (name, font) in [("regular", &regular), ("bold", &bold)] {
+                println!("{:?} {}", c, name);
+                let cell = raster(font, c, baseline);
+                for row in cell.chunks(WIDTH) {
+                    let shades: String = row.iter().map(|&v| [' ', '.', ':', '+', '#'][v as usize * 5 / 256]).collect();
+                    println!("{}|", shades);
+                }
+            }
+        }
+        return;
+    }
+
+    // Printable Latin-1 without the soft hyphen, plus the extras the font has
+    let mut chars: Vec<char> = (0x21u32..=0x7E)
+        .chain(0xA1..=0xFF)
+        .filter(|&c| c != 0xAD)
+        .filter_map(char::from_u32)
+        .chain(EXTRA.chars())
+        .collect();
+    chars.sort();
+    chars.dedup();
+    chars.retain(|&c| regular.lookup_glyph_index(c) != 0);
+
+    let mut out = Vec::new();
+    for &c in &chars {
+        out.extend((c as u32).to_le_bytes());
+        for font in [&regular, &bold] {
+            // Fall back to the regular glyph if bold lacks it
+            let font = if font.lookup_glyph_index(c) == 0 { &regular } else { font };
+            // 4 bits per pixel, rounded, two pixels per byte (high nibble first)
+            for pair in raster(font, c, baseline).chunks(2) {
+                let high = (pair[0] as u16 * 15 + 127) / 255;
+                let low = (pair[1] as u16 * 15 + 127) / 255;
+                out.push((high << 4 | low) as u8);
+            }
+        }
+    }
+    std::io::stdout().write_all(&out).expect("Failed to write the font");
+    eprintln!("{} glyphs, {} bytes", chars.len(), out.len());
+}